@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::metrics::Op;
+use crate::query::type_name;
+use crate::{Database, DbError, Entry, Value};
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Aggregation {
+    pub count: usize,
+    pub numeric_count: usize,
+    pub sum: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub avg: Option<f64>,
+    pub types: BTreeMap<String, usize>,
+}
+
+impl Aggregation {
+    fn add(&mut self, value: &Value) {
+        self.count += 1;
+        *self.types.entry(type_name(value).to_string()).or_insert(0) += 1;
+
+        // Hodnoty z PHP přichází jako řetězce, proto bereme i číselné stringy
+        let number = match value {
+            Value::Integer(n) => Some(*n as f64),
+            Value::Float(f) => Some(*f),
+            Value::String(s) => s.trim().parse::<f64>().ok(),
+            _ => None,
+        };
+
+        if let Some(n) = number {
+            self.numeric_count += 1;
+            self.sum += n;
+            self.min = Some(self.min.map_or(n, |min| min.min(n)));
+            self.max = Some(self.max.map_or(n, |max| max.max(n)));
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.numeric_count > 0 {
+            self.avg = Some(self.sum / self.numeric_count as f64);
+        }
+    }
+}
+
+impl Database {
+    fn for_each_live<F>(&self, pattern: &str, mut f: F) -> Result<(), DbError>
+    where
+        F: FnMut(&Entry),
+    {
+        Entry::parse_path(pattern)?;
+        let pattern_components: Vec<String> = pattern.split('/')
+            .map(|s| s.to_string())
+            .collect();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        for entry in self.data.iter() {
+            let entry = entry.value();
+            if entry.matches_pattern(&pattern_components)
+                && !entry.expiry.map(|exp| exp < now).unwrap_or(false)
+            {
+                f(entry);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn aggregate(&self, pattern: &str) -> Result<Aggregation, DbError> {
+        self.observe(Op::Aggregate, pattern, || self.collect_aggregation(pattern))
+    }
+
+    fn collect_aggregation(&self, pattern: &str) -> Result<Aggregation, DbError> {
+        let mut aggregation = Aggregation::default();
+        self.for_each_live(pattern, |entry| aggregation.add(&entry.value))?;
+        aggregation.finish();
+        Ok(aggregation)
+    }
+
+    // Groups matching entries by the path component at `segment` (0-based),
+    // e.g. `aggregate_by("user/*", 1)` gives one bucket per user id.
+    pub fn aggregate_by(&self, pattern: &str, segment: usize) -> Result<BTreeMap<String, Aggregation>, DbError> {
+        self.observe(Op::Aggregate, pattern, || self.collect_aggregation_by(pattern, segment))
+    }
+
+    fn collect_aggregation_by(&self, pattern: &str, segment: usize) -> Result<BTreeMap<String, Aggregation>, DbError> {
+        let mut groups: BTreeMap<String, Aggregation> = BTreeMap::new();
+        self.for_each_live(pattern, |entry| {
+            if let Some(key) = entry.path_components.get(segment) {
+                groups.entry(key.clone()).or_default().add(&entry.value);
+            }
+        })?;
+
+        for aggregation in groups.values_mut() {
+            aggregation.finish();
+        }
+        Ok(groups)
+    }
+}
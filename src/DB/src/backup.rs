@@ -0,0 +1,455 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::metrics::Op;
+use crate::target::{BackupTarget, LocalTarget};
+use crate::{Database, DbError, Entry, SerializableDb};
+
+const CATALOG_FILE: &str = "catalog.json";
+const MANIFEST_SUFFIX: &str = ".manifest.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupKind {
+    Full,
+    // changes since the previous backup of any kind
+    Incremental,
+    // changes since the last full backup
+    Differential,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupRecord {
+    pub id: String,
+    pub kind: BackupKind,
+    pub file: String,
+    pub parent: Option<String>,
+    pub base_revision: u64,
+    pub revision: u64,
+    pub key_count: usize,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BackupCatalog {
+    pub backups: Vec<BackupRecord>,
+}
+
+// Written next to every backup file as `<backup>.manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub file: String,
+    pub kind: BackupKind,
+    pub key_count: usize,
+    pub size: u64,
+    pub checksum: String,
+    pub revision: u64,
+    pub source_version: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupVerification {
+    pub path: String,
+    pub valid: bool,
+    pub key_count: usize,
+    pub checksum: String,
+    pub errors: Vec<String>,
+}
+
+// Entries changed after `base_revision` plus every key alive at `revision`,
+// which is enough to replay deletions on restore.
+#[derive(Debug, Serialize, Deserialize)]
+struct IncrementalBackup {
+    base_revision: u64,
+    revision: u64,
+    data: HashMap<String, Entry>,
+    keys: Vec<String>,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub(crate) fn manifest_name(name: &str) -> String {
+    format!("{}{}", name, MANIFEST_SUFFIX)
+}
+
+// Splits a local backup path into a target for its directory and the file name
+fn local_target(path: &str) -> Result<(LocalTarget, String), DbError> {
+    let path = Path::new(path);
+    let name = path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or(DbError::InvalidPath)?;
+    let dir = path.parent()
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| ".".to_string());
+    Ok((LocalTarget::new(&dir), name))
+}
+
+// Writes the backup object and its manifest
+fn write_backup(
+    target: &dyn BackupTarget,
+    name: &str,
+    contents: &[u8],
+    kind: BackupKind,
+    key_count: usize,
+    revision: u64,
+    source_version: &str,
+) -> Result<BackupManifest, DbError> {
+    target.put(name, contents)?;
+
+    let manifest = BackupManifest {
+        file: name.to_string(),
+        kind,
+        key_count,
+        size: contents.len() as u64,
+        checksum: sha256_hex(contents),
+        revision,
+        source_version: source_version.to_string(),
+        created_at: Utc::now(),
+    };
+    target.put(&manifest_name(name), serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    Ok(manifest)
+}
+
+fn verify_object(target: &dyn BackupTarget, name: &str) -> BackupVerification {
+    let mut verification = BackupVerification {
+        path: name.to_string(),
+        valid: false,
+        key_count: 0,
+        checksum: String::new(),
+        errors: Vec::new(),
+    };
+
+    let contents = match target.get(name) {
+        Ok(contents) => contents,
+        Err(e) => {
+            verification.errors.push(format!("Cannot read backup: {}", e));
+            return verification;
+        }
+    };
+    verification.checksum = sha256_hex(&contents);
+
+    let manifest: Option<BackupManifest> = match target.get(&manifest_name(name)) {
+        Ok(content) => match serde_json::from_slice(&content) {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                verification.errors.push(format!("Invalid manifest: {}", e));
+                None
+            }
+        },
+        Err(_) => {
+            verification.errors.push("Manifest is missing".to_string());
+            None
+        }
+    };
+
+    let kind = manifest.as_ref().map(|m| m.kind).unwrap_or(BackupKind::Full);
+    let entries = match kind {
+        BackupKind::Full => serde_json::from_slice::<SerializableDb>(&contents)
+            .map(|saved| (saved.data, None)),
+        BackupKind::Incremental | BackupKind::Differential => serde_json::from_slice::<IncrementalBackup>(&contents)
+            .map(|backup| (backup.data, Some(backup.keys))),
+    };
+
+    match entries {
+        Ok((data, keys)) => {
+            for (key, entry) in &data {
+                if Entry::parse_path(key).ok().as_ref() != Some(&entry.path_components) {
+                    verification.errors.push(format!("Entry {} has inconsistent path components", key));
+                }
+            }
+            verification.key_count = keys.map(|k| k.len()).unwrap_or(data.len());
+        }
+        Err(e) => verification.errors.push(format!("Cannot parse backup: {}", e)),
+    }
+
+    if let Some(manifest) = manifest {
+        if manifest.checksum != verification.checksum {
+            verification.errors.push("Checksum mismatch".to_string());
+        }
+        if manifest.size != contents.len() as u64 {
+            verification.errors.push(format!("Size mismatch: expected {}, found {}", manifest.size, contents.len()));
+        }
+        if manifest.key_count != verification.key_count {
+            verification.errors.push(format!("Key count mismatch: expected {}, found {}", manifest.key_count, verification.key_count));
+        }
+    }
+
+    verification.valid = verification.errors.is_empty();
+    verification
+}
+
+impl BackupCatalog {
+    pub fn load(dir: &str) -> Result<Self, DbError> {
+        Self::load_from(&LocalTarget::new(dir))
+    }
+
+    pub fn load_from(target: &dyn BackupTarget) -> Result<Self, DbError> {
+        if target.list(CATALOG_FILE)?.iter().all(|name| name != CATALOG_FILE) {
+            return Ok(BackupCatalog::default());
+        }
+        Ok(serde_json::from_slice(&target.get(CATALOG_FILE)?)?)
+    }
+
+    fn save(&self, target: &dyn BackupTarget) -> Result<(), DbError> {
+        target.put(CATALOG_FILE, serde_json::to_string_pretty(self)?.as_bytes())
+    }
+
+    fn find(&self, id: &str) -> Option<&BackupRecord> {
+        self.backups.iter().find(|b| b.id == id)
+    }
+
+    // Chain (full first) ending with the newest backup taken at or before `point`
+    pub fn chain(&self, point: Option<DateTime<Utc>>) -> Result<Vec<&BackupRecord>, DbError> {
+        let last = self.backups.iter()
+            .filter(|b| point.map(|p| b.created_at <= p).unwrap_or(true))
+            .max_by_key(|b| (b.created_at, b.revision))
+            .ok_or_else(|| DbError::System("No backup available for the requested point in time".to_string()))?;
+
+        let mut chain = vec![last];
+        let mut current = last;
+        while current.kind != BackupKind::Full {
+            let parent_id = current.parent.as_deref()
+                .ok_or_else(|| DbError::System(format!("Backup {} has no parent", current.id)))?;
+            current = self.find(parent_id)
+                .ok_or_else(|| DbError::System(format!("Backup {} is missing from the catalog", parent_id)))?;
+            chain.push(current);
+        }
+        chain.reverse();
+        Ok(chain)
+    }
+}
+
+// Data of the chain up to `point` merged into a single full backup
+pub(crate) fn load_catalog(target: &dyn BackupTarget, point: Option<DateTime<Utc>>) -> Result<SerializableDb, DbError> {
+    let catalog = BackupCatalog::load_from(target)?;
+    let chain = catalog.chain(point)?;
+
+    let mut merged: Option<SerializableDb> = None;
+    for record in chain {
+        let content = target.get(&record.file)?;
+
+        match (record.kind, merged.as_mut()) {
+            (BackupKind::Full, _) => {
+                merged = Some(serde_json::from_slice(&content)?);
+            }
+            (BackupKind::Incremental | BackupKind::Differential, Some(saved)) => {
+                let backup: IncrementalBackup = serde_json::from_slice(&content)?;
+                let alive: HashSet<String> = backup.keys.into_iter().collect();
+                saved.data.retain(|key, _| alive.contains(key));
+                saved.data.extend(backup.data);
+            }
+            (_, None) => {
+                return Err(DbError::System(format!("Backup chain does not start with a full backup: {}", record.file)));
+            }
+        }
+    }
+
+    merged.ok_or_else(|| DbError::System("Backup catalog is empty".to_string()))
+}
+
+impl Database {
+    fn write_full_backup(&self, target: &dyn BackupTarget, name: &str) -> Result<BackupManifest, DbError> {
+        // Záloha se zapisuje ze snapshotu, zápisy během serializace neblokuje
+        let created_at = Utc::now();
+        let snapshot = self.snapshot();
+        let mut serializable = snapshot.to_serializable();
+        // Záloha nese svůj čas, živá hodnota se změní až po úspěšném zápisu
+        serializable.last_backup = Some(created_at);
+        let manifest = write_backup(
+            target,
+            name,
+            serde_json::to_string(&serializable)?.as_bytes(),
+            BackupKind::Full,
+            serializable.data.len(),
+            snapshot.revision(),
+            &self.version,
+        )?;
+        *self.last_backup.write() = Some(created_at);
+        self.save_to_disk()?;
+        Ok(manifest)
+    }
+
+    pub fn create_backup(&self, backup_path: &str) -> Result<(), DbError> {
+        self.observe(Op::Backup, backup_path, || self.write_backup_file(backup_path))
+    }
+
+    fn write_backup_file(&self, backup_path: &str) -> Result<(), DbError> {
+        let (target, name) = local_target(backup_path)?;
+        self.write_full_backup(&target, &name)?;
+        Ok(())
+    }
+
+    pub fn create_backup_to(&self, target: &dyn BackupTarget, name: &str) -> Result<BackupManifest, DbError> {
+        self.observe(Op::Backup, name, || self.write_full_backup(target, name))
+    }
+
+    pub fn export_json_to(&self, target: &dyn BackupTarget, name: &str) -> Result<(), DbError> {
+        self.observe(Op::Export, name, || self.write_export_to(target, name))
+    }
+
+    fn write_export_to(&self, target: &dyn BackupTarget, name: &str) -> Result<(), DbError> {
+        let json = serde_json::to_string_pretty(&self.snapshot().to_serializable())?;
+        target.put(name, json.as_bytes())
+    }
+
+    pub fn restore_from_target(&self, target: &dyn BackupTarget, name: &str) -> Result<(), DbError> {
+        // Jméno katalogu = obnova celého řetězce záloh
+        if name == CATALOG_FILE {
+            return self.restore_catalog_from(target, None);
+        }
+        self.observe(Op::Restore, name, || self.load_backup_from(target, name))
+    }
+
+    fn load_backup_from(&self, target: &dyn BackupTarget, name: &str) -> Result<(), DbError> {
+        let saved: SerializableDb = serde_json::from_slice(&target.get(name)?)?;
+        self.replace_all(saved.data)?;
+        *self.created_at.write() = saved.created_at;
+        *self.last_backup.write() = saved.last_backup;
+        Ok(())
+    }
+
+    pub fn create_catalog_backup(&self, dir: &str, kind: BackupKind) -> Result<BackupRecord, DbError> {
+        self.create_catalog_backup_in(&LocalTarget::new(dir), kind)
+    }
+
+    pub fn create_catalog_backup_in(&self, target: &dyn BackupTarget, kind: BackupKind) -> Result<BackupRecord, DbError> {
+        self.observe(Op::Backup, "", || self.write_catalog_backup(target, kind))
+    }
+
+    fn write_catalog_backup(&self, target: &dyn BackupTarget, kind: BackupKind) -> Result<BackupRecord, DbError> {
+        let _catalog = self.catalog_lock.lock();
+        let mut catalog = BackupCatalog::load_from(target)?;
+
+        let parent = match kind {
+            BackupKind::Full => None,
+            BackupKind::Incremental => catalog.backups.iter()
+                .max_by_key(|b| (b.created_at, b.revision)),
+            BackupKind::Differential => catalog.backups.iter()
+                .filter(|b| b.kind == BackupKind::Full)
+                .max_by_key(|b| (b.created_at, b.revision)),
+        };
+        // Bez předchozí plné zálohy nelze řetězit, uděláme plnou
+        let (kind, parent) = match parent {
+            Some(parent) => (kind, Some(parent.clone())),
+            None => (BackupKind::Full, None),
+        };
+
+        let created_at = Utc::now();
+        let snapshot = self.snapshot();
+        let id = format!("{}-{}", created_at.format("%Y%m%dT%H%M%S%.3fZ"), snapshot.revision());
+        let file = format!("{}.{}.json", id, serde_json::to_value(kind)?.as_str().unwrap_or("backup"));
+        let base_revision = parent.as_ref().map(|p| p.revision).unwrap_or(0);
+
+        let manifest = match kind {
+            BackupKind::Full => {
+                let mut serializable = snapshot.to_serializable();
+                serializable.last_backup = Some(created_at);
+                write_backup(
+                    target,
+                    &file,
+                    serde_json::to_string(&serializable)?.as_bytes(),
+                    kind,
+                    serializable.data.len(),
+                    snapshot.revision(),
+                    &self.version,
+                )?
+            }
+            BackupKind::Incremental | BackupKind::Differential => {
+                let entries = snapshot.entries();
+                let backup = IncrementalBackup {
+                    base_revision,
+                    revision: snapshot.revision(),
+                    keys: entries.keys().cloned().collect(),
+                    data: entries.into_iter()
+                        .filter(|(_, entry)| entry.revision > base_revision)
+                        .collect(),
+                };
+                write_backup(
+                    target,
+                    &file,
+                    serde_json::to_string(&backup)?.as_bytes(),
+                    kind,
+                    backup.keys.len(),
+                    snapshot.revision(),
+                    &self.version,
+                )?
+            }
+        };
+
+        let record = BackupRecord {
+            id,
+            kind,
+            file,
+            parent: parent.map(|p| p.id),
+            base_revision,
+            revision: snapshot.revision(),
+            key_count: manifest.key_count,
+            created_at,
+        };
+        catalog.backups.push(record.clone());
+        catalog.save(target)?;
+
+        *self.last_backup.write() = Some(created_at);
+        self.save_to_disk()?;
+        Ok(record)
+    }
+
+    // Checks a backup file (or every file of a catalog directory) against its manifest without restoring it
+    pub fn verify_backup(path: &str) -> Result<Vec<BackupVerification>, DbError> {
+        if Path::new(path).is_dir() {
+            return Self::verify_backup_in(&LocalTarget::new(path), CATALOG_FILE);
+        }
+        let (target, name) = local_target(path)?;
+        Self::verify_backup_in(&target, &name)
+    }
+
+    pub fn verify_backup_in(target: &dyn BackupTarget, name: &str) -> Result<Vec<BackupVerification>, DbError> {
+        if name != CATALOG_FILE {
+            return Ok(vec![verify_object(target, name)]);
+        }
+
+        let catalog = BackupCatalog::load_from(target)?;
+        let mut results = Vec::new();
+        for record in &catalog.backups {
+            let mut verification = verify_object(target, &record.file);
+            if let Some(parent) = &record.parent {
+                if catalog.find(parent).is_none() {
+                    verification.errors.push(format!("Parent backup {} is missing from the catalog", parent));
+                    verification.valid = false;
+                }
+            }
+            results.push(verification);
+        }
+        Ok(results)
+    }
+
+    // Replays the full + incremental/differential chain up to `point` (latest when None)
+    pub fn restore_catalog(&self, dir: &str, point: Option<DateTime<Utc>>) -> Result<(), DbError> {
+        self.restore_catalog_from(&LocalTarget::new(dir), point)
+    }
+
+    pub fn restore_catalog_from(&self, target: &dyn BackupTarget, point: Option<DateTime<Utc>>) -> Result<(), DbError> {
+        self.observe(Op::Restore, "", || self.load_catalog_chain(target, point))
+    }
+
+    fn load_catalog_chain(&self, target: &dyn BackupTarget, point: Option<DateTime<Utc>>) -> Result<(), DbError> {
+        let saved = load_catalog(target, point)?;
+        self.replace_all(saved.data)?;
+        *self.created_at.write() = saved.created_at;
+        *self.last_backup.write() = saved.last_backup;
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use std::io::{self, BufReader, BufWriter};
+use std::process::ExitCode;
+
+use rust_db::{Database, DbError, ImportMode, ImportOptions, PrefixRemap, StorageConfig, StreamFormat};
+
+const USAGE: &str = "usage:
+  rust-db-cli export <db.json> [--format ndjson|csv] [--pattern <pattern>]   > dump
+  rust-db-cli import <db.json> [--format ndjson|csv] [--mode replace|merge_overwrite|merge_keep_existing|merge_newest_wins]
+                               [--prefix <pattern>] [--remap <from>=<to>]... [--dry-run]   < dump
+  import keeps existing keys unless --mode says otherwise; replace drops the keys in scope first";
+
+struct Args {
+    command: String,
+    db_path: String,
+    format: StreamFormat,
+    pattern: Option<String>,
+    options: ImportOptions,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let command = args.next().ok_or("missing command")?;
+    let db_path = args.next().ok_or("missing database path")?;
+    let mut parsed = Args {
+        command,
+        db_path,
+        format: StreamFormat::Ndjson,
+        pattern: None,
+        // Replace by mistake would wipe the database, so the CLI merges unless told otherwise
+        options: ImportOptions {
+            mode: ImportMode::MergeKeepExisting,
+            ..Default::default()
+        },
+    };
+
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", flag));
+        match flag.as_str() {
+            "--format" => parsed.format = StreamFormat::parse(&value()?).map_err(|e| e.to_string())?,
+            "--pattern" => parsed.pattern = Some(value()?),
+            "--prefix" => parsed.options.prefix = Some(value()?),
+            "--mode" => {
+                let mode = value()?;
+                parsed.options.mode = serde_json::from_value::<ImportMode>(serde_json::Value::String(mode.clone()))
+                    .map_err(|_| format!("unknown mode '{}'", mode))?;
+            }
+            "--remap" => {
+                let rule = value()?;
+                let (from, to) = rule.split_once('=').ok_or(format!("--remap expects <from>=<to>, got '{}'", rule))?;
+                parsed.options.remap.push(PrefixRemap {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                });
+            }
+            "--dry-run" => parsed.options.dry_run = true,
+            _ => return Err(format!("unknown option '{}'", flag)),
+        }
+    }
+    Ok(parsed)
+}
+
+fn run(args: Args) -> Result<(), DbError> {
+    // A database that fails to load must not be exported as empty or overwritten by an import
+    let db = Database::open(&args.db_path, &StorageConfig::default())?;
+
+    match args.command.as_str() {
+        "export" => {
+            let stdout = io::stdout();
+            let summary = db.export_stream(BufWriter::new(stdout.lock()), args.format, args.pattern.as_deref())?;
+            eprintln!("exported {} entries, skipped {}", summary.exported, summary.skipped);
+        }
+        "import" => {
+            let stdin = io::stdin();
+            let report = db.import_stream(BufReader::new(stdin.lock()), args.format, &args.options)?;
+            eprintln!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        other => return Err(DbError::System(format!("unknown command '{}'", other))),
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
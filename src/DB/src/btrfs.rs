@@ -0,0 +1,250 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::metrics::Op;
+use crate::storage::read_storage_file;
+use crate::{Database, DbError};
+
+const SNAPSHOT_NAME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+// btrfs-progs from PATH; BtrfsConfig and VolumeConfig take another binary
+pub(crate) const DEFAULT_BTRFS_BIN: &str = "btrfs";
+
+// Runs a command and turns a non-zero exit status into an error carrying its stderr
+pub(crate) fn run_command(program: &str, args: &[&str]) -> Result<String, DbError> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| DbError::System(format!("{} could not be started: {}", program, e)))?;
+
+    if !output.status.success() {
+        return Err(DbError::Command {
+            command: format!("{} {}", program, args.join(" ")),
+            status: output.status.to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn path_str(path: &Path) -> Result<&str, DbError> {
+    path.to_str().ok_or_else(|| DbError::System("Invalid path".to_string()))
+}
+
+pub fn is_btrfs_subvolume(path: &str) -> bool {
+    subvolume_exists(DEFAULT_BTRFS_BIN, path)
+}
+
+pub fn is_btrfs_subvolume_with(path: &str, btrfs_bin: &str) -> bool {
+    subvolume_exists(btrfs_bin, path)
+}
+
+fn subvolume_exists(bin: &str, path: &str) -> bool {
+    run_command(bin, &["subvolume", "show", path]).is_ok()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BtrfsConfig {
+    // Holds the read-only snapshots; must be outside the storage subvolume
+    pub snapshot_dir: String,
+    // btrfs-progs executable, "btrfs" from PATH by default
+    pub btrfs_bin: String,
+}
+
+impl Default for BtrfsConfig {
+    fn default() -> Self {
+        BtrfsConfig {
+            snapshot_dir: String::new(),
+            btrfs_bin: DEFAULT_BTRFS_BIN.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BtrfsSnapshotInfo {
+    pub name: String,
+    pub path: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+// Storage directory living on its own subvolume plus the directory that
+// holds its read-only snapshots (same filesystem, outside the subvolume)
+pub(crate) struct BtrfsSnapshots {
+    subvolume: PathBuf,
+    snapshot_dir: PathBuf,
+    bin: String,
+}
+
+impl BtrfsSnapshots {
+    fn snapshot_path(&self, name: &str) -> Result<PathBuf, DbError> {
+        if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+            return Err(DbError::InvalidPath);
+        }
+        Ok(self.snapshot_dir.join(name))
+    }
+
+    fn info(&self, name: &str) -> BtrfsSnapshotInfo {
+        BtrfsSnapshotInfo {
+            name: name.to_string(),
+            path: self.snapshot_dir.join(name).to_string_lossy().into_owned(),
+            created_at: NaiveDateTime::parse_from_str(name, SNAPSHOT_NAME_FORMAT).ok().map(|t| t.and_utc()),
+        }
+    }
+}
+
+impl Database {
+    // Creates the subvolume the storage directory lives on; call before Database::new
+    pub fn init_btrfs_subvolume(dir: &str) -> Result<(), DbError> {
+        Self::init_btrfs_subvolume_with(dir, DEFAULT_BTRFS_BIN)
+    }
+
+    pub fn init_btrfs_subvolume_with(dir: &str, btrfs_bin: &str) -> Result<(), DbError> {
+        if subvolume_exists(btrfs_bin, dir) {
+            return Ok(());
+        }
+        if Path::new(dir).exists() {
+            return Err(DbError::System(format!("{} already exists and is not a Btrfs subvolume", dir)));
+        }
+        if let Some(parent) = Path::new(dir).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        run_command(btrfs_bin, &["subvolume", "create", dir])?;
+        Ok(())
+    }
+
+    pub fn enable_btrfs_snapshots(&self, snapshot_dir: &str) -> Result<(), DbError> {
+        self.enable_btrfs_snapshots_with(&BtrfsConfig {
+            snapshot_dir: snapshot_dir.to_string(),
+            ..Default::default()
+        })
+    }
+
+    pub fn enable_btrfs_snapshots_with(&self, config: &BtrfsConfig) -> Result<(), DbError> {
+        if self.storage_path.is_empty() {
+            return Err(DbError::System("Btrfs snapshots need a storage path".to_string()));
+        }
+        let parent = Path::new(&self.storage_path).parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let subvolume = fs::canonicalize(parent)?;
+        if !subvolume_exists(&config.btrfs_bin, path_str(&subvolume)?) {
+            return Err(DbError::System(format!(
+                "{} is not a Btrfs subvolume, create it with init_btrfs_subvolume",
+                subvolume.display()
+            )));
+        }
+
+        if config.snapshot_dir.is_empty() {
+            return Err(DbError::System("Btrfs snapshots need a snapshot directory".to_string()));
+        }
+        fs::create_dir_all(&config.snapshot_dir)?;
+        let snapshot_dir = fs::canonicalize(&config.snapshot_dir)?;
+        if snapshot_dir.starts_with(&subvolume) {
+            return Err(DbError::System("Snapshot directory must be outside the storage subvolume".to_string()));
+        }
+
+        *self.btrfs.write() = Some(BtrfsSnapshots {
+            subvolume,
+            snapshot_dir,
+            bin: config.btrfs_bin.clone(),
+        });
+        Ok(())
+    }
+
+    fn with_btrfs<T>(&self, f: impl FnOnce(&BtrfsSnapshots) -> Result<T, DbError>) -> Result<T, DbError> {
+        match self.btrfs.read().as_ref() {
+            Some(btrfs) => f(btrfs),
+            None => Err(DbError::System("Btrfs snapshots are not enabled".to_string())),
+        }
+    }
+
+    // Flushes the database and takes a read-only snapshot of the storage subvolume
+    pub fn btrfs_snapshot(&self, name: Option<&str>) -> Result<BtrfsSnapshotInfo, DbError> {
+        self.observe(Op::Backup, name.unwrap_or(""), || self.take_btrfs_snapshot(name))
+    }
+
+    fn take_btrfs_snapshot(&self, name: Option<&str>) -> Result<BtrfsSnapshotInfo, DbError> {
+        self.with_btrfs(|btrfs| {
+            let name = name.map(|n| n.to_string())
+                .unwrap_or_else(|| Utc::now().format(SNAPSHOT_NAME_FORMAT).to_string());
+            let target = btrfs.snapshot_path(&name)?;
+            if target.exists() {
+                return Err(DbError::System(format!("Snapshot {} already exists", name)));
+            }
+
+            self.with_flushed(|| {
+                run_command(&btrfs.bin, &[
+                    "subvolume", "snapshot", "-r",
+                    path_str(&btrfs.subvolume)?,
+                    path_str(&target)?,
+                ])
+            })?;
+            *self.last_backup.write() = Some(Utc::now());
+            Ok(btrfs.info(&name))
+        })
+    }
+
+    pub fn list_btrfs_snapshots(&self) -> Result<Vec<BtrfsSnapshotInfo>, DbError> {
+        self.with_btrfs(|btrfs| {
+            let mut names: Vec<String> = fs::read_dir(&btrfs.snapshot_dir)?
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            Ok(names.iter().map(|name| btrfs.info(name)).collect())
+        })
+    }
+
+    // Writes a `btrfs send` stream of the snapshot, incremental when a parent snapshot is given
+    pub fn btrfs_send(&self, name: &str, parent: Option<&str>, output: &str) -> Result<(), DbError> {
+        self.with_btrfs(|btrfs| {
+            let snapshot = btrfs.snapshot_path(name)?;
+            let parent = parent.map(|p| btrfs.snapshot_path(p)).transpose()?;
+
+            let mut args = vec!["send", "-f", output];
+            if let Some(parent) = &parent {
+                args.push("-p");
+                args.push(path_str(parent)?);
+            }
+            args.push(path_str(&snapshot)?);
+            run_command(&btrfs.bin, &args)?;
+            Ok(())
+        })
+    }
+
+    // Loads the database file from the snapshot; the live subvolume stays mounted
+    pub fn restore_btrfs_snapshot(&self, name: &str) -> Result<(), DbError> {
+        self.observe(Op::Restore, name, || self.rollback_btrfs_snapshot(name))
+    }
+
+    fn rollback_btrfs_snapshot(&self, name: &str) -> Result<(), DbError> {
+        let file = self.with_btrfs(|btrfs| {
+            let storage_name = Path::new(&self.storage_path).file_name()
+                .ok_or(DbError::InvalidPath)?;
+            Ok(btrfs.snapshot_path(name)?.join(storage_name))
+        })?;
+
+        // Soubor ve snapshotu má stejný formát jako živé úložiště; snapshot může být
+        // read-only, proto se jen čte
+        let saved = read_storage_file(&file, self.storage_engine())?
+            .ok_or_else(|| DbError::System(format!("Snapshot {} holds no database file", name)))?;
+        self.replace_all(saved.data)?;
+        *self.created_at.write() = saved.created_at;
+        *self.last_backup.write() = saved.last_backup;
+        self.save_to_disk()
+    }
+
+    pub fn delete_btrfs_snapshot(&self, name: &str) -> Result<(), DbError> {
+        self.with_btrfs(|btrfs| {
+            let snapshot = btrfs.snapshot_path(name)?;
+            run_command(&btrfs.bin, &["subvolume", "delete", path_str(&snapshot)?])?;
+            Ok(())
+        })
+    }
+}
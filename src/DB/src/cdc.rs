@@ -0,0 +1,365 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::storage::write_atomic;
+use crate::{ChangeKind, Database, DbError, Entry, Value};
+
+// How often the age based retention is evaluated
+const RETENTION_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+thread_local! {
+    // Hloubka vnořených Database::cdc_batch na tomto vlákně; záznamy uvnitř dávky
+    // se synchronizují jednou na jejím konci
+    static BATCH_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CdcRetention {
+    pub max_bytes: Option<u64>,
+    pub max_age_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CdcRecord {
+    pub seq: u64,
+    pub kind: ChangeKind,
+    pub path: String,
+    pub revision: Option<u64>,
+    pub value: Option<Value>,
+    pub expiry: Option<u64>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CdcState {
+    last_seq: u64,
+    consumers: HashMap<String, u64>,
+}
+
+// Where each record starts in the log, so reads seek instead of scanning the file
+struct IndexEntry {
+    seq: u64,
+    offset: u64,
+    timestamp: i64,
+}
+
+struct ChangeLogInner {
+    file: File,
+    size: u64,
+    index: Vec<IndexEntry>,
+    // Appended inside a batch and not synced yet
+    dirty: bool,
+    state: CdcState,
+    retention: CdcRetention,
+    last_retention_check: Instant,
+}
+
+// Append-only NDJSON log stored next to the database file (`<storage>.cdc`),
+// consumer offsets live in `<storage>.cdc.state`.
+pub struct ChangeLog {
+    log_path: PathBuf,
+    state_path: PathBuf,
+    inner: Mutex<ChangeLogInner>,
+}
+
+fn corrupt(path: &Path, offset: u64) -> DbError {
+    DbError::System(format!("Corrupt CDC record at byte {} of {}", offset, path.display()))
+}
+
+// Indexes the log and returns the length of its valid part. A torn last record (crash
+// during an append) is left out so it can be cut off; a corrupt record anywhere else is an error.
+fn scan_log(path: &Path) -> Result<(Vec<IndexEntry>, u64), DbError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+        Err(e) => return Err(e.into()),
+    };
+    let len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let mut index = Vec::new();
+    let mut offset = 0;
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)? as u64;
+        if read == 0 {
+            break;
+        }
+        let last = offset + read == len;
+        if !line.ends_with(b"\n") {
+            break;
+        }
+        if !line.iter().all(u8::is_ascii_whitespace) {
+            match serde_json::from_slice::<CdcRecord>(&line) {
+                Ok(record) => index.push(IndexEntry {
+                    seq: record.seq,
+                    offset,
+                    timestamp: record.timestamp.timestamp(),
+                }),
+                Err(_) if last => break,
+                Err(_) => return Err(corrupt(path, offset)),
+            }
+        }
+        offset += read;
+    }
+    Ok((index, offset))
+}
+
+impl ChangeLog {
+    pub fn open(storage_path: &str, retention: CdcRetention) -> Result<Self, DbError> {
+        let log_path = PathBuf::from(format!("{}.cdc", storage_path));
+        let state_path = PathBuf::from(format!("{}.cdc.state", storage_path));
+
+        let mut state: CdcState = match fs::read_to_string(&state_path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(_) => CdcState::default(),
+        };
+        let (index, valid) = scan_log(&log_path)?;
+        if let Some(last) = index.last() {
+            state.last_seq = state.last_seq.max(last.seq);
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&log_path)?;
+        // Nedopsaný poslední záznam by se slepil s dalším, proto ho uřízneme
+        if file.metadata()?.len() > valid {
+            file.set_len(valid)?;
+            file.sync_data()?;
+        }
+
+        Ok(ChangeLog {
+            log_path,
+            state_path,
+            inner: Mutex::new(ChangeLogInner {
+                file,
+                size: valid,
+                index,
+                dirty: false,
+                state,
+                retention,
+                last_retention_check: Instant::now(),
+            }),
+        })
+    }
+
+    fn save_state(&self, state: &CdcState) -> Result<(), DbError> {
+        write_atomic(&self.state_path, serde_json::to_string(state)?.as_bytes())
+    }
+
+    // Outside a Database::cdc_batch every record is synced before this returns
+    pub(crate) fn append(&self, kind: ChangeKind, path: &str, entry: Option<&Entry>) -> Result<u64, DbError> {
+        let mut inner = self.inner.lock();
+        let seq = inner.state.last_seq + 1;
+
+        let record = CdcRecord {
+            seq,
+            kind,
+            path: path.to_string(),
+            revision: entry.map(|e| e.revision),
+            value: entry.map(|e| e.value.clone()),
+            expiry: entry.and_then(|e| e.expiry),
+            timestamp: Utc::now(),
+        };
+
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        if let Err(e) = inner.file.write_all(line.as_bytes()) {
+            // Částečně zapsaný řádek by rozbil index i další záznamy
+            let _ = inner.file.set_len(inner.size);
+            return Err(e.into());
+        }
+        let offset = inner.size;
+        inner.index.push(IndexEntry {
+            seq,
+            offset,
+            timestamp: record.timestamp.timestamp(),
+        });
+        inner.size += line.len() as u64;
+        inner.state.last_seq = seq;
+        inner.dirty = true;
+        if BATCH_DEPTH.with(|depth| depth.get()) == 0 {
+            inner.file.sync_data()?;
+            inner.dirty = false;
+        }
+
+        let over_size = inner.retention.max_bytes
+            .map(|max| inner.size > max)
+            .unwrap_or(false);
+        let check_age = inner.retention.max_age_secs.is_some()
+            && inner.last_retention_check.elapsed() >= RETENTION_CHECK_INTERVAL;
+        if over_size || check_age {
+            self.apply_retention(&mut inner)?;
+        }
+
+        Ok(seq)
+    }
+
+    pub(crate) fn sync(&self) -> Result<(), DbError> {
+        let mut inner = self.inner.lock();
+        if inner.dirty {
+            inner.file.sync_data()?;
+            inner.dirty = false;
+        }
+        Ok(())
+    }
+
+    fn apply_retention(&self, inner: &mut ChangeLogInner) -> Result<(), DbError> {
+        inner.last_retention_check = Instant::now();
+
+        let mut start = 0;
+        if let Some(max_age) = inner.retention.max_age_secs {
+            let cutoff = Utc::now().timestamp() - max_age as i64;
+            start = inner.index.partition_point(|e| e.timestamp < cutoff);
+        }
+
+        // Nejstarší záznamy zahodíme, dokud log nezapadne do limitu (ponecháme aspoň polovinu)
+        if let Some(max_bytes) = inner.retention.max_bytes {
+            let target = max_bytes / 2;
+            while start < inner.index.len() && inner.size - inner.index[start].offset > target {
+                start += 1;
+            }
+        }
+        if start == 0 {
+            return Ok(());
+        }
+
+        let base = inner.index.get(start).map(|e| e.offset).unwrap_or(inner.size);
+        let mut kept = Vec::new();
+        let mut file = File::open(&self.log_path)?;
+        file.seek(SeekFrom::Start(base))?;
+        file.take(inner.size - base).read_to_end(&mut kept)?;
+        write_atomic(&self.log_path, &kept)?;
+
+        inner.index.drain(..start);
+        for entry in &mut inner.index {
+            entry.offset -= base;
+        }
+        inner.file = OpenOptions::new().append(true).open(&self.log_path)?;
+        inner.size = kept.len() as u64;
+        inner.dirty = false;
+        self.save_state(&inner.state)
+    }
+
+    pub fn read(&self, offset: u64, limit: usize) -> Result<Vec<CdcRecord>, DbError> {
+        let inner = self.inner.lock();
+        let start = inner.index.partition_point(|e| e.seq <= offset);
+        let entries = &inner.index[start..inner.index.len().min(start.saturating_add(limit))];
+        let Some(first) = entries.first() else {
+            return Ok(Vec::new());
+        };
+
+        // Načte se jen úsek souboru s požadovanými záznamy
+        let end = inner.index.get(start + entries.len()).map(|e| e.offset).unwrap_or(inner.size);
+        let mut data = Vec::new();
+        let mut file = File::open(&self.log_path)?;
+        file.seek(SeekFrom::Start(first.offset))?;
+        file.take(end - first.offset).read_to_end(&mut data)?;
+
+        let mut records = Vec::with_capacity(entries.len());
+        for (i, entry) in entries.iter().enumerate() {
+            let from = (entry.offset - first.offset) as usize;
+            let to = entries.get(i + 1).map(|next| next.offset).unwrap_or(end) - first.offset;
+            let record: CdcRecord = data.get(from..to as usize)
+                .and_then(|line| serde_json::from_slice(line).ok())
+                .filter(|record: &CdcRecord| record.seq == entry.seq)
+                .ok_or_else(|| corrupt(&self.log_path, entry.offset))?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    pub fn last_seq(&self) -> u64 {
+        self.inner.lock().state.last_seq
+    }
+
+    pub fn consumer_offset(&self, consumer: &str) -> u64 {
+        self.inner.lock().state.consumers.get(consumer).copied().unwrap_or(0)
+    }
+
+    pub fn ack(&self, consumer: &str, seq: u64) -> Result<(), DbError> {
+        let mut inner = self.inner.lock();
+        if seq > inner.state.last_seq {
+            return Err(DbError::System(format!("Sequence {} has not been written yet", seq)));
+        }
+        let offset = inner.state.consumers.entry(consumer.to_string()).or_insert(0);
+        *offset = (*offset).max(seq);
+        self.save_state(&inner.state)
+    }
+}
+
+impl Database {
+    pub fn enable_cdc(&self, retention: CdcRetention) -> Result<(), DbError> {
+        if self.storage_path.is_empty() {
+            return Err(DbError::System("Change data capture needs a storage path".to_string()));
+        }
+        let log = ChangeLog::open(&self.storage_path, retention)?;
+        *self.cdc.write() = Some(log);
+        Ok(())
+    }
+
+    fn with_cdc<T>(&self, f: impl FnOnce(&ChangeLog) -> Result<T, DbError>) -> Result<T, DbError> {
+        match self.cdc.read().as_ref() {
+            Some(log) => f(log),
+            None => Err(DbError::System("Change data capture is not enabled".to_string())),
+        }
+    }
+
+    pub fn cdc_read(&self, offset: u64, limit: usize) -> Result<Vec<CdcRecord>, DbError> {
+        self.with_cdc(|log| log.read(offset, limit))
+    }
+
+    // Reads records after the consumer's last acknowledged offset
+    pub fn cdc_read_consumer(&self, consumer: &str, limit: usize) -> Result<Vec<CdcRecord>, DbError> {
+        self.with_cdc(|log| log.read(log.consumer_offset(consumer), limit))
+    }
+
+    pub fn cdc_ack(&self, consumer: &str, seq: u64) -> Result<(), DbError> {
+        self.with_cdc(|log| log.ack(consumer, seq))
+    }
+
+    pub fn cdc_last_seq(&self) -> Result<u64, DbError> {
+        self.with_cdc(|log| Ok(log.last_seq()))
+    }
+
+    pub(crate) fn cdc_append(&self, kind: ChangeKind, path: &str, entry: Option<&Entry>) -> Result<(), DbError> {
+        if let Some(log) = self.cdc.read().as_ref() {
+            log.append(kind, path, entry)?;
+        }
+        Ok(())
+    }
+
+    // Runs an operation touching many keys with one CDC fsync at its end instead of one per record
+    pub(crate) fn cdc_batch<T>(&self, f: impl FnOnce() -> Result<T, DbError>) -> Result<T, DbError> {
+        struct Depth;
+        impl Drop for Depth {
+            fn drop(&mut self) {
+                BATCH_DEPTH.with(|depth| depth.set(depth.get() - 1));
+            }
+        }
+
+        let outermost = BATCH_DEPTH.with(|depth| {
+            depth.set(depth.get() + 1);
+            depth.get() == 1
+        });
+        let result = {
+            let _depth = Depth;
+            f()
+        };
+        // Zapsané záznamy se synchronizují i po chybě uprostřed operace
+        if outermost {
+            if let Some(log) = self.cdc.read().as_ref() {
+                log.sync()?;
+            }
+        }
+        result
+    }
+}
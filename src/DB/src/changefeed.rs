@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::{Database, DbError, Entry};
+
+// Number of recent events kept for cursor based polling (FFI)
+const FEED_HISTORY: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Set,
+    Delete,
+    Expire,
+    // expiry set or removed, value unchanged
+    Ttl,
+    // removed by the maxmemory policy
+    Evict,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeEvent {
+    pub seq: u64,
+    pub kind: ChangeKind,
+    pub path: String,
+    pub old_revision: Option<u64>,
+    pub new_revision: Option<u64>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangeBatch {
+    pub cursor: u64,
+    pub events: Vec<ChangeEvent>,
+    // true when events between the requested cursor and the oldest kept one were dropped
+    pub truncated: bool,
+}
+
+pub struct ChangeFeed {
+    sender: broadcast::Sender<ChangeEvent>,
+    history: Mutex<(u64, VecDeque<ChangeEvent>)>,
+}
+
+impl ChangeFeed {
+    pub(crate) fn new() -> Self {
+        let (sender, _) = broadcast::channel(FEED_HISTORY);
+        ChangeFeed {
+            sender,
+            history: Mutex::new((0, VecDeque::with_capacity(FEED_HISTORY))),
+        }
+    }
+
+    pub(crate) fn publish(&self, kind: ChangeKind, path: &str, old_revision: Option<u64>, new_revision: Option<u64>) {
+        let mut history = self.history.lock();
+        history.0 += 1;
+
+        let event = ChangeEvent {
+            seq: history.0,
+            kind,
+            path: path.to_string(),
+            old_revision,
+            new_revision,
+            timestamp: Utc::now(),
+        };
+
+        if history.1.len() == FEED_HISTORY {
+            history.1.pop_front();
+        }
+        history.1.push_back(event.clone());
+
+        // Chyba znamená jen to, že nikdo neodebírá
+        let _ = self.sender.send(event);
+    }
+
+    pub(crate) fn sender(&self) -> broadcast::Sender<ChangeEvent> {
+        self.sender.clone()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum FeedMessage {
+    Change(ChangeEvent),
+    // The subscriber fell behind and this many events (of any path) were dropped;
+    // poll_changes() from the last seen seq catches up with what is still kept
+    Lagged(u64),
+}
+
+pub struct Subscription {
+    receiver: broadcast::Receiver<ChangeEvent>,
+    pattern: Vec<String>,
+}
+
+impl Subscription {
+    fn matches(&self, event: &ChangeEvent) -> bool {
+        Entry::path_matches(&event.path, &self.pattern)
+    }
+
+    // Waits for the next event matching the subscribed pattern, or reports dropped events.
+    // Returns None when the database has been dropped.
+    pub async fn recv(&mut self) -> Option<FeedMessage> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) if self.matches(&event) => return Some(FeedMessage::Change(event)),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => return Some(FeedMessage::Lagged(skipped)),
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    // None when no event is waiting
+    pub fn try_recv(&mut self) -> Option<FeedMessage> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(event) if self.matches(&event) => return Some(FeedMessage::Change(event)),
+                Ok(_) => continue,
+                Err(broadcast::error::TryRecvError::Lagged(skipped)) => return Some(FeedMessage::Lagged(skipped)),
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+impl Database {
+    pub fn subscribe(&self, pattern: &str) -> Result<Subscription, DbError> {
+        Entry::parse_path(pattern)?;
+        Ok(Subscription {
+            receiver: self.changes.sender.subscribe(),
+            pattern: pattern.split('/').map(|s| s.to_string()).collect(),
+        })
+    }
+
+    // Polling variant for callers without an async runtime (PHP over FFI):
+    // returns events after `cursor` matching `pattern`, oldest first.
+    pub fn poll_changes(&self, pattern: &str, cursor: u64, limit: usize) -> Result<ChangeBatch, DbError> {
+        Entry::parse_path(pattern)?;
+        let pattern: Vec<String> = pattern.split('/').map(|s| s.to_string()).collect();
+
+        let history = self.changes.history.lock();
+        let truncated = history.1.front()
+            .map(|oldest| oldest.seq > cursor + 1)
+            .unwrap_or(false);
+
+        let mut next_cursor = cursor;
+        let mut events = Vec::new();
+
+        for event in history.1.iter().filter(|e| e.seq > cursor) {
+            if events.len() == limit {
+                break;
+            }
+            next_cursor = event.seq;
+            if Entry::path_matches(&event.path, &pattern) {
+                events.push(event.clone());
+            }
+        }
+
+        Ok(ChangeBatch {
+            cursor: next_cursor,
+            events,
+            truncated,
+        })
+    }
+
+    pub fn current_change_cursor(&self) -> u64 {
+        self.changes.history.lock().0
+    }
+}
@@ -0,0 +1,271 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+
+use crate::storage::sync_dir;
+use crate::{Database, DbError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Durability {
+    // Writes reach the OS page cache, the kernel decides when they hit the disk
+    #[default]
+    None,
+    // A background thread fsyncs every interval_ms when something changed
+    Periodic { interval_ms: u64 },
+    // fsync before the write returns; concurrent writers share one fsync (group commit)
+    Always,
+}
+
+const LATENCY_BUCKETS: usize = 32;
+
+// Time spent persisting writes (save_to_disk), fsync counts for the storage file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteMetrics {
+    pub durability: Durability,
+    pub writes: u64,
+    pub fsyncs: u64,
+    // Writes made durable by another writer's fsync
+    pub grouped_writes: u64,
+    pub avg_latency_us: f64,
+    pub max_latency_us: u64,
+    // Upper bounds of power-of-two latency buckets
+    pub p50_latency_us: u64,
+    pub p99_latency_us: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct WriteStats {
+    durability: Durability,
+    writes: AtomicU64,
+    fsyncs: AtomicU64,
+    grouped: AtomicU64,
+    total_us: AtomicU64,
+    max_us: AtomicU64,
+    // Bucket i počítá latence pod 2^i µs
+    buckets: [AtomicU64; LATENCY_BUCKETS],
+}
+
+impl WriteStats {
+    pub(crate) fn new(durability: Durability) -> Self {
+        WriteStats {
+            durability,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn record_write(&self, latency: Duration) {
+        let us = latency.as_micros().min(u64::MAX as u128) as u64;
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        self.total_us.fetch_add(us, Ordering::Relaxed);
+        self.max_us.fetch_max(us, Ordering::Relaxed);
+        let bucket = (u64::BITS - us.leading_zeros()) as usize;
+        self.buckets[bucket.min(LATENCY_BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_fsync(&self) {
+        self.fsyncs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_grouped(&self) {
+        self.grouped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn percentile(&self, counts: &[u64], quantile: f64) -> u64 {
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return 0;
+        }
+        let rank = ((total as f64 * quantile).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, count) in counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return 1u64 << i;
+            }
+        }
+        1u64 << (LATENCY_BUCKETS - 1)
+    }
+
+    pub(crate) fn metrics(&self) -> WriteMetrics {
+        let counts: Vec<u64> = self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect();
+        let writes = self.writes.load(Ordering::Relaxed);
+        WriteMetrics {
+            durability: self.durability,
+            writes,
+            fsyncs: self.fsyncs.load(Ordering::Relaxed),
+            grouped_writes: self.grouped.load(Ordering::Relaxed),
+            avg_latency_us: if writes > 0 {
+                self.total_us.load(Ordering::Relaxed) as f64 / writes as f64
+            } else {
+                0.0
+            },
+            max_latency_us: self.max_us.load(Ordering::Relaxed),
+            p50_latency_us: self.percentile(&counts, 0.5),
+            p99_latency_us: self.percentile(&counts, 0.99),
+        }
+    }
+}
+
+struct SyncShared {
+    stopped: Mutex<bool>,
+    wakeup: Condvar,
+    dirty: AtomicBool,
+    error: Mutex<Option<String>>,
+}
+
+impl SyncShared {
+    fn sync(&self, path: &Path, stats: &WriteStats) {
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return;
+        }
+        // fsync přes nový deskriptor, soubor mohl být mezitím nahrazen kompakcí nebo
+        // přepsán přes rename, proto i adresář
+        let synced = File::open(path)
+            .and_then(|file| file.sync_data())
+            .map_err(DbError::from)
+            .and_then(|_| sync_dir(path));
+        match synced {
+            Ok(()) => stats.record_fsync(),
+            Err(e) => {
+                self.dirty.store(true, Ordering::SeqCst);
+                *self.error.lock() = Some(e.to_string());
+            }
+        }
+    }
+}
+
+// Background fsync of the storage file; stops, syncs once more and joins on drop
+struct PeriodicSync {
+    shared: Arc<SyncShared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PeriodicSync {
+    fn start(path: PathBuf, interval: Duration, stats: Arc<WriteStats>) -> Self {
+        let shared = Arc::new(SyncShared {
+            stopped: Mutex::new(false),
+            wakeup: Condvar::new(),
+            dirty: AtomicBool::new(false),
+            error: Mutex::new(None),
+        });
+
+        let thread_shared = shared.clone();
+        let handle = thread::Builder::new()
+            .name("rust-db-fsync".to_string())
+            .spawn(move || loop {
+                let mut stopped = thread_shared.stopped.lock();
+                if !*stopped {
+                    thread_shared.wakeup.wait_for(&mut stopped, interval);
+                }
+                let stop = *stopped;
+                drop(stopped);
+
+                thread_shared.sync(&path, &stats);
+                if stop {
+                    break;
+                }
+            });
+
+        let handle = match handle {
+            Ok(handle) => Some(handle),
+            Err(e) => {
+                *shared.error.lock() = Some(format!("fsync thread could not be started: {}", e));
+                None
+            }
+        };
+        PeriodicSync { shared, handle }
+    }
+}
+
+impl Drop for PeriodicSync {
+    fn drop(&mut self) {
+        *self.shared.stopped.lock() = true;
+        self.shared.wakeup.notify_all();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+// Makes data the backend has handed to the OS durable according to the configured level
+pub(crate) struct Syncer {
+    durability: Durability,
+    stats: Arc<WriteStats>,
+    periodic: Option<PeriodicSync>,
+}
+
+impl Syncer {
+    pub(crate) fn new(path: PathBuf, durability: Durability, stats: Arc<WriteStats>) -> Self {
+        let periodic = match durability {
+            Durability::Periodic { interval_ms } => Some(PeriodicSync::start(
+                path,
+                Duration::from_millis(interval_ms.max(1)),
+                stats.clone(),
+            )),
+            _ => None,
+        };
+        Syncer {
+            durability,
+            stats,
+            periodic,
+        }
+    }
+
+    pub(crate) fn durability(&self) -> Durability {
+        self.durability
+    }
+
+    // `file` is the written storage file; only fsynced for Durability::Always
+    pub(crate) fn written(&self, file: Option<&File>) -> Result<(), DbError> {
+        match (&self.periodic, self.durability) {
+            (Some(periodic), _) => {
+                self.check()?;
+                periodic.shared.dirty.store(true, Ordering::SeqCst);
+            }
+            (None, Durability::Always) => {
+                if let Some(file) = file {
+                    file.sync_data()?;
+                    self.stats.record_fsync();
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // A whole file was rewritten and fsynced by the caller
+    pub(crate) fn synced(&self) {
+        self.stats.record_fsync();
+    }
+
+    // The write was already persisted by an earlier writer
+    pub(crate) fn covered(&self) -> Result<(), DbError> {
+        if self.durability == Durability::Always {
+            self.stats.record_grouped();
+        }
+        self.check()
+    }
+
+    // Chyba z fsync vlákna se ohlásí při nejbližším zápisu
+    fn check(&self) -> Result<(), DbError> {
+        if let Some(periodic) = &self.periodic {
+            if let Some(error) = periodic.shared.error.lock().take() {
+                return Err(DbError::System(format!("Periodic fsync failed: {}", error)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Database {
+    pub fn write_metrics(&self) -> WriteMetrics {
+        self.write_stats.metrics()
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::backup::load_catalog;
+use crate::metrics::Op;
+use crate::{ChangeKind, Database, DbError, Entry, LocalTarget, SerializableDb};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    // Existing keys in scope are removed before loading
+    #[default]
+    Replace,
+    MergeOverwrite,
+    MergeKeepExisting,
+    // Higher updated_at wins, revision breaks ties
+    MergeNewestWins,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefixRemap {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportOptions {
+    #[serde(default)]
+    pub mode: ImportMode,
+    #[serde(default)]
+    pub dry_run: bool,
+    // Only source paths matching this pattern are imported ("*" matches any segment)
+    pub prefix: Option<String>,
+    // First matching rule rewrites the leading path segments of imported keys
+    #[serde(default)]
+    pub remap: Vec<PrefixRemap>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    Overwritten,
+    KeptExisting,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportConflict {
+    pub path: String,
+    pub resolution: ConflictResolution,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub existing_updated_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub incoming_updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub mode: ImportMode,
+    pub dry_run: bool,
+    pub imported: usize,
+    pub unchanged: usize,
+    pub skipped: usize,
+    pub deleted: usize,
+    pub conflicts: Vec<ImportConflict>,
+}
+
+fn has_prefix(path: &str, prefix: &str) -> bool {
+    prefix.is_empty()
+        || path == prefix
+        || (path.starts_with(prefix) && path.as_bytes()[prefix.len()] == b'/')
+}
+
+fn remap_path(path: &str, remap: &[PrefixRemap]) -> String {
+    for rule in remap {
+        let from = rule.from.trim_end_matches('/');
+        if has_prefix(path, from) {
+            let rest = path[from.len()..].trim_start_matches('/');
+            let to = rule.to.trim_end_matches('/');
+            return match (to.is_empty(), rest.is_empty()) {
+                (true, _) => rest.to_string(),
+                (false, true) => to.to_string(),
+                (false, false) => format!("{}/{}", to, rest),
+            };
+        }
+    }
+    path.to_string()
+}
+
+impl Database {
+    pub fn import_json_with(&self, import_path: &str, options: &ImportOptions) -> Result<ImportReport, DbError> {
+        self.observe(Op::Import, import_path, || self.import_file_with(import_path, options))
+    }
+
+    fn import_file_with(&self, import_path: &str, options: &ImportOptions) -> Result<ImportReport, DbError> {
+        let content = fs::read_to_string(import_path)?;
+        let saved: SerializableDb = serde_json::from_str(&content)?;
+        self.import_entries(saved.data, options)
+    }
+
+    // Like restore_from_backup, but loads the backup (or catalog directory) with import options
+    pub fn restore_from_backup_with(&self, backup_path: &str, options: &ImportOptions) -> Result<ImportReport, DbError> {
+        self.observe(Op::Restore, backup_path, || self.restore_file_with(backup_path, options))
+    }
+
+    fn restore_file_with(&self, backup_path: &str, options: &ImportOptions) -> Result<ImportReport, DbError> {
+        let saved = if Path::new(backup_path).is_dir() {
+            load_catalog(&LocalTarget::new(backup_path), None)?
+        } else {
+            serde_json::from_str(&fs::read_to_string(backup_path)?)?
+        };
+        self.import_entries(saved.data, options)
+    }
+
+    pub(crate) fn import_entries(&self, data: HashMap<String, Entry>, options: &ImportOptions) -> Result<ImportReport, DbError> {
+        self.import_iter(data.into_iter().map(Ok), options)
+    }
+
+    // Entries are applied one at a time, so the source can be a stream larger than memory;
+    // only the imported keys are remembered for the replace mode.
+    pub(crate) fn import_iter<I>(&self, entries: I, options: &ImportOptions) -> Result<ImportReport, DbError>
+    where
+        I: IntoIterator<Item = Result<(String, Entry), DbError>>,
+    {
+        self.cdc_batch(|| {
+            let mut report = ImportReport {
+                mode: options.mode,
+                dry_run: options.dry_run,
+                ..Default::default()
+            };
+
+            let filter: Option<Vec<String>> = options.prefix.as_ref()
+                .map(|prefix| prefix.split('/').map(|s| s.to_string()).collect());
+
+            // Nejdřív celý vstup načteme a ověříme, chybný záznam uprostřed tak nic nezmění
+            let mut incoming = Vec::new();
+            for item in entries {
+                let (path, mut entry) = item?;
+                if let Some(filter) = &filter {
+                    if !Entry::path_matches(&path, filter) {
+                        report.skipped += 1;
+                        continue;
+                    }
+                }
+                let path = remap_path(&path, &options.remap);
+                entry.path_components = Entry::parse_path(&path)?;
+                incoming.push((path, entry));
+            }
+
+            let applied = self.apply_import(incoming, options, &mut report);
+            // Co už bylo zapsáno, musí na disk i když se import nedokončil
+            let saved = if options.dry_run { Ok(()) } else { self.save_to_disk() };
+            applied.and(saved)?;
+            report.conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+            Ok(report)
+        })
+    }
+
+    fn apply_import(&self, incoming: Vec<(String, Entry)>, options: &ImportOptions, report: &mut ImportReport) -> Result<(), DbError> {
+        let mut seen: HashSet<String> = HashSet::new();
+        for (path, entry) in incoming {
+            if options.mode == ImportMode::Replace {
+                seen.insert(path.clone());
+            }
+            self.import_one(path, entry, options, report)?;
+        }
+
+        // Replace maže jen v rozsahu importu: celou databázi, nebo cílový prefix
+        if options.mode == ImportMode::Replace {
+            let scope: Option<Vec<String>> = options.prefix.as_ref()
+                .map(|prefix| remap_path(prefix, &options.remap).split('/').map(|s| s.to_string()).collect());
+            let stale: Vec<String> = self.data.iter()
+                .map(|entry| entry.key().clone())
+                .filter(|key| !seen.contains(key))
+                .filter(|key| scope.as_ref().map(|scope| Entry::path_matches(key, scope)).unwrap_or(true))
+                .collect();
+
+            for key in stale {
+                report.deleted += 1;
+                if options.dry_run {
+                    continue;
+                }
+                self.commit_remove(ChangeKind::Delete, &key, |_| true)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn import_one(&self, path: String, entry: Entry, options: &ImportOptions, report: &mut ImportReport) -> Result<(), DbError> {
+        let existing = self.data.get(&path).map(|e| e.clone());
+
+        if let Some(existing) = &existing {
+            if existing.value == entry.value && existing.expiry == entry.expiry {
+                report.unchanged += 1;
+                return Ok(());
+            }
+
+            let overwrite = match options.mode {
+                ImportMode::Replace | ImportMode::MergeOverwrite => true,
+                ImportMode::MergeKeepExisting => false,
+                ImportMode::MergeNewestWins => {
+                    (entry.updated_at, entry.revision) > (existing.updated_at, existing.revision)
+                }
+            };
+            report.conflicts.push(ImportConflict {
+                path: path.clone(),
+                resolution: if overwrite { ConflictResolution::Overwritten } else { ConflictResolution::KeptExisting },
+                existing_updated_at: existing.updated_at,
+                incoming_updated_at: entry.updated_at,
+            });
+            if !overwrite {
+                return Ok(());
+            }
+        }
+
+        report.imported += 1;
+        if options.dry_run {
+            return Ok(());
+        }
+        self.commit_insert(&path, entry)?;
+        Ok(())
+    }
+}
@@ -1,19 +1,77 @@
 use std::{
-    collections::HashMap,
-    time::{SystemTime, UNIX_EPOCH},
+    collections::{BTreeMap, HashMap},
+    time::{Instant, SystemTime, UNIX_EPOCH},
     sync::Arc,
+    sync::atomic::{AtomicU64, Ordering},
     path::Path,
     fs,
 };
 use dashmap::DashMap;
+use dashmap::mapref::entry::Entry as MapEntry;
+use parking_lot::{Mutex, RwLock};
 use serde::{Serialize, Deserialize};
 use thiserror::Error;
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
-use nix::mount::{mount, MsFlags};
 use chrono::{DateTime, Utc};
-use std::path::PathBuf;
-use glob::Pattern;
+
+mod aggregate;
+mod backup;
+mod btrfs;
+mod cdc;
+mod changefeed;
+mod durability;
+mod import;
+mod memory;
+mod metrics;
+mod namespace;
+mod pubsub;
+mod query;
+mod reaper;
+mod redis;
+mod scheduler;
+mod search;
+mod slowlog;
+mod snapshot;
+mod sqlite;
+mod storage;
+mod stream;
+mod target;
+mod volume;
+
+pub use aggregate::Aggregation;
+pub use backup::{BackupCatalog, BackupKind, BackupManifest, BackupRecord, BackupVerification};
+pub use btrfs::{is_btrfs_subvolume, is_btrfs_subvolume_with, BtrfsConfig, BtrfsSnapshotInfo};
+pub use cdc::{CdcRecord, CdcRetention, ChangeLog};
+pub use changefeed::{ChangeBatch, ChangeEvent, ChangeFeed, ChangeKind, FeedMessage, Subscription};
+pub use durability::{Durability, WriteMetrics};
+pub use import::{ConflictResolution, ImportConflict, ImportMode, ImportOptions, ImportReport, PrefixRemap};
+pub use memory::{EvictionPolicy, MemoryConfig, MemoryStats};
+pub use metrics::{HistogramBucket, LatencyHistogram, Metrics};
+pub use namespace::{NamespaceConfig, NamespaceStats, Namespaces, DEFAULT_NAMESPACE};
+pub use pubsub::MAX_PUBSUB_CLIENTS;
+pub use query::{Query, QueryResult};
+pub use reaper::{ReaperConfig, ReaperStatus};
+pub use redis::{map_redis_key, RedisImportConfig, RedisImportSummary};
+pub use scheduler::{backup_name, prune_backups, BackupScheduler, CronSchedule, RetentionPolicy, ScheduleConfig, ScheduleStatus};
+pub use search::{SearchHit, SearchIndex};
+pub use slowlog::{SlowlogConfig, SlowlogEntry};
+pub use snapshot::Snapshot;
+pub use sqlite::{MapTableSpec, SqliteExportConfig, SqliteExportSummary};
+pub use storage::{DiskUsage, StorageConfig, StorageEngine};
+pub use stream::{ExportSummary, StreamFormat, StreamRecord};
+pub use target::{BackupTarget, LocalTarget, S3Config, S3Target};
+pub use volume::{Volume, VolumeConfig, VolumeKind, VolumeUsage};
+
+use btrfs::BtrfsSnapshots;
+use durability::WriteStats;
+use memory::{entry_live_size, entry_size, Access};
+use metrics::{MetricsServer, Op, OpMetrics};
+use pubsub::PubsubServer;
+use reaper::TtlReaper;
+use slowlog::Slowlog;
+use snapshot::SnapshotRegistry;
+use storage::{Mutation, StorageBackend};
 
 #[derive(Error, Debug)]
 pub enum DbError {
@@ -31,9 +89,21 @@ pub enum DbError {
     System(String),
     #[error("Mount error: {0}")]
     Mount(#[from] nix::Error),
+    #[error("Query error: {0}")]
+    Query(String),
+    #[error("Out of memory: write rejected by the maxmemory limit")]
+    OutOfMemory,
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Command `{command}` failed ({status}): {stderr}")]
+    Command {
+        command: String,
+        status: String,
+        stderr: String,
+    },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Value {
     String(String),
     Integer(i64),
@@ -53,6 +123,10 @@ struct Entry {
     created_at: DateTime<Utc>,
     #[serde(with = "chrono::serde::ts_seconds")]
     updated_at: DateTime<Utc>,
+    #[serde(default)]
+    revision: u64,
+    #[serde(skip)]
+    access: Access,
 }
 
 impl Entry {
@@ -63,6 +137,8 @@ impl Entry {
             expiry: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            revision: 0,
+            access: Access::new(),
         })
     }
 
@@ -76,6 +152,17 @@ impl Entry {
             .collect())
     }
 
+    fn path_matches(path: &str, pattern_components: &[String]) -> bool {
+        let components: Vec<&str> = path.split('/').collect();
+        if pattern_components.len() > components.len() {
+            return false;
+        }
+
+        pattern_components.iter()
+            .zip(components)
+            .all(|(pattern, component)| pattern == "*" || pattern == component)
+    }
+
     fn matches_pattern(&self, pattern_components: &[String]) -> bool {
         if pattern_components.len() > self.path_components.len() {
             return false;
@@ -98,6 +185,9 @@ struct SerializableDb {
     #[serde(with = "chrono::serde::ts_seconds_option")]
     last_backup: Option<DateTime<Utc>>,
     version: String,
+    // Čítač revizí; smazané klíče z něj nesmí zmizet, jinak by se revize po restartu opakovaly
+    #[serde(default)]
+    revision: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,6 +195,7 @@ pub struct DbStats {
     pub total_keys: usize,
     pub expired_keys: usize,
     pub memory_usage: u64,
+    pub disk_usage: u64,
     #[serde(with = "chrono::serde::ts_seconds")]
     pub created_at: DateTime<Utc>,
     #[serde(with = "chrono::serde::ts_seconds_option")]
@@ -124,267 +215,500 @@ pub struct DetailedDbStats {
     pub array_values: usize,
     pub map_values: usize,
     pub null_values: usize,
+    // Counted in the same pass as memory_by_type, prefixes and allocated_memory, so the
+    // figures agree even while writes go on (memory_stats() may differ slightly then)
     pub memory_usage: u64,
     #[serde(with = "chrono::serde::ts_seconds")]
     pub created_at: DateTime<Utc>,
     #[serde(with = "chrono::serde::ts_seconds_option")]
     pub last_backup: Option<DateTime<Utc>>,
     pub average_path_depth: f64,
+    // Bytes per value type ("string", "map", ...)
+    pub memory_by_type: BTreeMap<String, u64>,
+    // Bytes in use, without spare capacity
+    pub live_memory: u64,
+    // memory_usage plus empty slots of the key table
+    pub allocated_memory: u64,
+    // allocated_memory / live_memory
+    pub fragmentation_ratio: f64,
+    pub disk: DiskUsage,
+    // By first path component; keys without a '/' are under ""
+    pub prefixes: BTreeMap<String, PrefixStats>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrefixStats {
+    pub keys: usize,
+    pub memory_usage: u64,
 }
 
 pub struct Database {
     data: Arc<DashMap<String, Entry>>,
     storage_path: String,
-    created_at: DateTime<Utc>,
-    last_backup: Option<DateTime<Utc>>,
+    created_at: RwLock<DateTime<Utc>>,
+    last_backup: RwLock<Option<DateTime<Utc>>>,
     version: String,
+    search_index: RwLock<Option<SearchIndex>>,
+    revision: AtomicU64,
+    changes: ChangeFeed,
+    pubsub: Mutex<Option<PubsubServer>>,
+    cdc: RwLock<Option<ChangeLog>>,
+    snapshots: Arc<SnapshotRegistry>,
+    write_gate: RwLock<()>,
+    scheduler: Mutex<Option<BackupScheduler>>,
+    btrfs: RwLock<Option<BtrfsSnapshots>>,
+    // Serializuje zápisy souboru, aby se dva save_to_disk nepřekrývaly
+    persist_lock: Mutex<()>,
+    // Katalog záloh se čte, doplní a zapíše celý; dvě zálohy naráz by si záznamy přepsaly
+    catalog_lock: Mutex<()>,
+    storage: Box<dyn StorageBackend>,
+    write_stats: Arc<WriteStats>,
+    memory_config: RwLock<MemoryConfig>,
+    // Součet entry_size všech záznamů, udržovaný v commit_*
+    used_memory: AtomicU64,
+    evicted_keys: AtomicU64,
+    rejected_writes: AtomicU64,
+    eviction_pool: Mutex<Vec<String>>,
+    metrics: OpMetrics,
+    slowlog: Slowlog,
+    reaper: Mutex<Option<TtlReaper>>,
+    metrics_server: Mutex<Option<MetricsServer>>,
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        // Plánované zálohy musí doběhnout dřív, než databáze zmizí
+        self.stop_backups();
+        self.stop_reaper();
+        self.stop_pubsub();
+        self.stop_metrics_server();
+    }
 }
 
 impl Database {
+    // Unreadable storage starts empty, use open() to get the error instead
     pub fn new(storage_path: &str) -> Self {
-        let mut db = Database {
+        let db = Self::with_backend(storage_path, &StorageConfig::default());
+        if let Ok(Some(saved)) = db.storage.load() {
+            db.load_saved(saved);
+        }
+        db
+    }
+
+    pub fn open(storage_path: &str, config: &StorageConfig) -> Result<Self, DbError> {
+        log::info!(target: "rust_db", "opening {:?} with the {:?} engine", storage_path, config.engine);
+        let db = Self::with_backend(storage_path, config);
+        if let Some(saved) = db.storage.load()? {
+            db.load_saved(saved);
+        }
+        Ok(db)
+    }
+
+    // Nothing is read or written; features that keep files next to the database are unavailable
+    pub fn in_memory() -> Self {
+        Self::with_backend("", &StorageConfig {
+            engine: StorageEngine::Memory,
+            ..Default::default()
+        })
+    }
+
+    fn with_backend(storage_path: &str, config: &StorageConfig) -> Self {
+        let write_stats = Arc::new(WriteStats::new(config.durability));
+        Database {
             data: Arc::new(DashMap::new()),
             storage_path: storage_path.to_string(),
-            created_at: Utc::now(),
-            last_backup: None,
+            created_at: RwLock::new(Utc::now()),
+            last_backup: RwLock::new(None),
             version: env!("CARGO_PKG_VERSION").to_string(),
-        };
-        
-        if Path::new(storage_path).exists() {
-            if let Ok(content) = std::fs::read_to_string(storage_path) {
-                if let Ok(saved) = serde_json::from_str::<SerializableDb>(&content) {
-                    for (key, value) in saved.data {
-                        db.data.insert(key, value);
-                    }
-                    db.created_at = saved.created_at;
-                    db.last_backup = saved.last_backup;
-                }
-            }
+            search_index: RwLock::new(None),
+            revision: AtomicU64::new(0),
+            changes: ChangeFeed::new(),
+            pubsub: Mutex::new(None),
+            cdc: RwLock::new(None),
+            snapshots: Arc::new(SnapshotRegistry::default()),
+            write_gate: RwLock::new(()),
+            scheduler: Mutex::new(None),
+            btrfs: RwLock::new(None),
+            persist_lock: Mutex::new(()),
+            catalog_lock: Mutex::new(()),
+            storage: storage::open_backend(storage_path, config, write_stats.clone()),
+            write_stats,
+            memory_config: RwLock::new(MemoryConfig::default()),
+            used_memory: AtomicU64::new(0),
+            evicted_keys: AtomicU64::new(0),
+            rejected_writes: AtomicU64::new(0),
+            eviction_pool: Mutex::new(Vec::new()),
+            metrics: OpMetrics::default(),
+            slowlog: Slowlog::default(),
+            reaper: Mutex::new(None),
+            metrics_server: Mutex::new(None),
         }
-        
-        db
+    }
+
+    fn load_saved(&self, saved: SerializableDb) {
+        for (key, mut value) in saved.data {
+            value.access = Access::new();
+            self.used_memory.fetch_add(entry_size(&key, &value), Ordering::SeqCst);
+            self.data.insert(key, value);
+        }
+        self.revision.fetch_max(saved.revision, Ordering::SeqCst);
+        self.sync_revision();
+        *self.created_at.write() = saved.created_at;
+        *self.last_backup.write() = saved.last_backup;
+    }
+
+    pub fn storage_engine(&self) -> StorageEngine {
+        self.storage.engine()
     }
 
     fn to_serializable(&self) -> SerializableDb {
+        let data = self.data.iter()
+            .map(|ref_multi| (ref_multi.key().clone(), ref_multi.value().clone()))
+            .collect();
         SerializableDb {
-            data: self.data.iter()
-                .map(|ref_multi| (ref_multi.key().clone(), ref_multi.value().clone()))
-                .collect(),
-            created_at: self.created_at,
-            last_backup: self.last_backup,
+            data,
+            created_at: *self.created_at.read(),
+            last_backup: *self.last_backup.read(),
             version: self.version.clone(),
+            // Čte se až po datech, takže pokryje každou zkopírovanou revizi
+            revision: self.revision(),
         }
     }
 
-    pub fn init_btrfs_volume(mount_path: &str, size_mb: u64) -> Result<(), DbError> {
-        let mount_path_buf = PathBuf::from(mount_path);
-        let file_path = mount_path_buf.join("db.img");
-        let file_path_str = file_path.to_str().ok_or_else(|| 
-            DbError::System("Invalid path".to_string()))?;
-        
-        // Vytvoření image souboru
-        std::process::Command::new("dd")
-            .args(&[
-                "if=/dev/zero",
-                &format!("of={}", file_path_str),
-                "bs=1M",
-                &format!("count={}", size_mb)
-            ])
-            .output()
-            .map_err(|e| DbError::System(e.to_string()))?;
-
-        // Formátování na Btrfs
-        std::process::Command::new("mkfs.btrfs")
-            .arg(file_path_str)
-            .output()
-            .map_err(|e| DbError::System(e.to_string()))?;
-
-        // Vytvoření mount pointu
-        fs::create_dir_all(mount_path)?;
-
-        // Připojení svazku
-        mount(
-            Some(file_path_str),
-            mount_path,
-            Some("btrfs"),
-            MsFlags::MS_NOATIME,
-            None::<&str>,
-        )?;
+    fn next_revision(&self) -> u64 {
+        self.revision.fetch_add(1, Ordering::SeqCst) + 1
+    }
 
-        Ok(())
+    // Soubory bez uloženého čítače: navázat aspoň na nejvyšší revizi mezi záznamy
+    fn sync_revision(&self) {
+        let max = self.data.iter()
+            .map(|entry| entry.value().revision)
+            .max()
+            .unwrap_or(0);
+        self.revision.fetch_max(max, Ordering::SeqCst);
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    // Všechny zápisy do mapy jdou přes commit_* kvůli revizím a copy-on-write snapshotům.
+    // Chybu úložiště při apply backend nahlásí v nejbližším save_to_disk. CDC záznam se
+    // zapisuje ještě pod zámkem klíče, aby pořadí seq odpovídalo pořadí zápisů; jeho chyba
+    // se vrací až po aktualizaci indexu a change feedu, změna v mapě už zůstane.
+    fn commit_insert(&self, path: &str, entry: Entry) -> Result<(Option<Entry>, Entry), DbError> {
+        // Vyřazování maže jiné klíče, musí proběhnout před zámkem zápisu
+        self.reserve_memory(path, &entry)?;
+        self.commit_insert_reserved(path, entry)
+    }
+
+    // commit_insert without the maxmemory check, for callers that checked the limit themselves
+    fn commit_insert_reserved(&self, path: &str, mut entry: Entry) -> Result<(Option<Entry>, Entry), DbError> {
+        let gate = self.write_gate.read();
+        let slot = self.data.entry(path.to_string());
+        // Revize až pod zámkem klíče, jinak by souběžné zápisy téhož klíče mohly dostat revize obráceně
+        entry.revision = self.next_revision();
+        entry.access = Access::new();
+        // Do mapy jde původní entry, aby její velikost odpovídala té z reserve_memory
+        let stored = entry.clone();
+        let (old, logged) = match slot {
+            MapEntry::Occupied(mut occupied) => {
+                self.snapshots.preserve(path, occupied.get());
+                let old = occupied.insert(entry);
+                self.used_memory.fetch_add(entry_size(path, occupied.get()), Ordering::SeqCst);
+                self.used_memory.fetch_sub(entry_size(path, &old), Ordering::SeqCst);
+                let _ = self.storage.apply(&Mutation::Set { path, entry: &stored });
+                (Some(old), self.cdc_append(ChangeKind::Set, path, Some(&stored)))
+            }
+            MapEntry::Vacant(vacant) => {
+                let inserted = vacant.insert(entry);
+                self.used_memory.fetch_add(entry_size(path, &inserted), Ordering::SeqCst);
+                let _ = self.storage.apply(&Mutation::Set { path, entry: &stored });
+                (None, self.cdc_append(ChangeKind::Set, path, Some(&stored)))
+            }
+        };
+        drop(gate);
+        self.record_mutation(ChangeKind::Set, path, old.as_ref().map(|e| e.revision), Some(&stored));
+        logged.map(|_| (old, stored))
+    }
+
+    fn commit_remove<F>(&self, kind: ChangeKind, path: &str, predicate: F) -> Result<Option<Entry>, DbError>
+    where
+        F: FnOnce(&Entry) -> bool,
+    {
+        let gate = self.write_gate.read();
+        let mut logged = Ok(());
+        let old = self.data.remove_if(path, |_, entry| {
+            let remove = predicate(entry);
+            if remove {
+                self.snapshots.preserve(path, entry);
+                self.used_memory.fetch_sub(entry_size(path, entry), Ordering::SeqCst);
+                let _ = self.storage.apply(&Mutation::Remove { path });
+                logged = self.cdc_append(kind, path, None);
+            }
+            remove
+        }).map(|(_, old)| old);
+        drop(gate);
+        if let Some(old) = &old {
+            self.record_mutation(kind, path, Some(old.revision), None);
+        }
+        logged.map(|_| old)
+    }
+
+    fn commit_update<F>(&self, path: &str, update: F) -> Result<Option<Entry>, DbError>
+    where
+        F: FnOnce(&mut Entry),
+    {
+        let gate = self.write_gate.read();
+        let Some(mut entry) = self.data.get_mut(path) else { return Ok(None) };
+        self.snapshots.preserve(path, &entry);
+        let old_revision = entry.revision;
+        let old_size = entry_size(path, &entry);
+        update(&mut entry);
+        entry.revision = self.next_revision();
+        self.used_memory.fetch_add(entry_size(path, &entry), Ordering::SeqCst);
+        self.used_memory.fetch_sub(old_size, Ordering::SeqCst);
+        let _ = self.storage.apply(&Mutation::Set { path, entry: &entry });
+        let logged = self.cdc_append(ChangeKind::Ttl, path, Some(&entry));
+        let updated = entry.clone();
+        drop(entry);
+        drop(gate);
+        self.record_mutation(ChangeKind::Ttl, path, Some(old_revision), Some(&updated));
+        logged.map(|_| Some(updated))
+    }
+
+    // Fulltext index a change feed; čtou mapu, proto až po uvolnění zámku klíče
+    fn record_mutation(&self, kind: ChangeKind, path: &str, old_revision: Option<u64>, new: Option<&Entry>) {
+        self.search_index_update(path);
+        self.changes.publish(kind, path, old_revision, new.map(|e| e.revision));
+    }
+
+    fn replace_all(&self, data: HashMap<String, Entry>) -> Result<(), DbError> {
+        self.cdc_batch(|| {
+            // Limit se ověří dřív, než se cokoli smaže; vyřazování během vkládání by jinak
+            // mazalo právě obnovené klíče nebo skončilo s polovičními daty
+            let incoming = data.iter().map(|(key, entry)| entry_size(key, entry)).sum();
+            self.check_replace_memory(incoming)?;
+
+            let keys: Vec<String> = self.data.iter()
+                .map(|entry| entry.key().clone())
+                .collect();
+            for key in keys {
+                self.commit_remove(ChangeKind::Delete, &key, |_| true)?;
+            }
+
+            // Načtené záznamy dostanou nové revize, staré by se míchaly s aktuálním čítačem
+            for (key, entry) in data {
+                self.commit_insert_reserved(&key, entry)?;
+            }
+            Ok(())
+        })
     }
 
     pub fn set(&self, path: &str, value: Value) -> Result<(), DbError> {
-        let entry = Entry::new(value, path)?;
-        self.data.insert(path.to_string(), entry);
-        self.save_to_disk()?;
-        Ok(())
+        self.observe(Op::Set, path, || self.store(path, value))
+    }
+
+    fn store(&self, path: &str, value: Value) -> Result<(), DbError> {
+        self.cdc_batch(|| {
+            self.commit_insert(path, Entry::new(value, path)?)?;
+            self.save_to_disk()?;
+            Ok(())
+        })
     }
 
     // New method to find entries by path pattern
     pub fn find_by_path(&self, pattern: &str) -> Result<HashMap<String, Value>, DbError> {
-        let pattern_components = pattern.split('/')
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>();
+        self.observe(Op::FindByPath, pattern, || {
+            let pattern_components = pattern.split('/')
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>();
 
-        let mut results = HashMap::new();
+            let mut results = HashMap::new();
         
-        for entry in self.data.iter() {
-            if entry.value().matches_pattern(&pattern_components) {
-                results.insert(
-                    entry.key().clone(),
-                    entry.value().value.clone()
-                );
+            for entry in self.data.iter() {
+                if entry.value().matches_pattern(&pattern_components) {
+                    results.insert(
+                        entry.key().clone(),
+                        entry.value().value.clone()
+                    );
+                }
             }
-        }
 
-        Ok(results)
+            Ok(results)
+        })
     }
 
     // Helper method to list all entries under a path
     pub fn list_directory(&self, prefix: &str) -> Result<Vec<String>, DbError> {
-        let prefix_components = prefix.split('/')
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>();
+        self.observe(Op::ListDirectory, prefix, || {
+            let prefix_components = prefix.split('/')
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>();
 
-        let mut results = Vec::new();
+            let mut results = Vec::new();
         
-        for entry in self.data.iter() {
-            let components = &entry.value().path_components;
-            if components.starts_with(&prefix_components) {
-                if components.len() > prefix_components.len() {
+            for entry in self.data.iter() {
+                let components = &entry.value().path_components;
+                if components.starts_with(&prefix_components) && components.len() > prefix_components.len() {
                     let next_component = &components[prefix_components.len()];
                     if !results.contains(next_component) {
                         results.push(next_component.clone());
                     }
                 }
             }
-        }
 
-        Ok(results)
+            Ok(results)
+        })
     }
 
     pub fn get(&self, key: &str) -> Result<Value, DbError> {
-        if let Some(entry) = self.data.get(key) {
-            if let Some(expiry) = entry.expiry {
-                if expiry < SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs() 
-                {
-                    self.data.remove(key);
-                    return Err(DbError::KeyNotFound);
+        self.observe(Op::Get, key, || self.lookup(key))
+    }
+
+    fn lookup(&self, key: &str) -> Result<Value, DbError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        // Referenci do mapy je nutné uvolnit před odstraněním klíče
+        match self.data.get(key) {
+            Some(entry) => match entry.expiry {
+                Some(expiry) if expiry < now => {}
+                _ => {
+                    entry.access.touch();
+                    self.metrics.hit();
+                    return Ok(entry.value.clone());
                 }
+            },
+            None => {
+                self.metrics.miss();
+                return Err(DbError::KeyNotFound);
             }
-            Ok(entry.value.clone())
-        } else {
-            Err(DbError::KeyNotFound)
         }
+
+        self.metrics.miss();
+        let expired = self.commit_remove(ChangeKind::Expire, key, |entry| {
+            entry.expiry.map(|exp| exp < now).unwrap_or(false)
+        });
+        // Klíč je pryč i při chybě zápisu do CDC logu, volající dostane KeyNotFound
+        if !matches!(expired, Ok(None)) {
+            self.metrics.expired();
+        }
+        Err(DbError::KeyNotFound)
     }
 
     pub fn delete(&self, key: &str) -> Result<(), DbError> {
-        if self.data.remove(key).is_some() {
-            self.save_to_disk()?;
-            Ok(())
-        } else {
-            Err(DbError::KeyNotFound)
-        }
+        self.observe(Op::Delete, key, || {
+            if self.commit_remove(ChangeKind::Delete, key, |_| true)?.is_some() {
+                self.save_to_disk()?;
+                Ok(())
+            } else {
+                Err(DbError::KeyNotFound)
+            }
+        })
     }
 
     pub fn increment(&self, path: &str) -> Result<i64, DbError> {
-        let mut current_value = match self.get(path) {
-            Ok(Value::Integer(n)) => n,
-            Ok(Value::String(s)) => s.parse::<i64>().map_err(|_| DbError::InvalidType)?,
-            Ok(_) => return Err(DbError::InvalidType),
-            Err(DbError::KeyNotFound) => 0,
-            Err(e) => return Err(e),
-        };
-
-        current_value += 1;
-        self.set(path, Value::Integer(current_value))?;
-        Ok(current_value)
+        self.observe(Op::Increment, path, || {
+            let mut current_value = match self.lookup(path) {
+                Ok(Value::Integer(n)) => n,
+                Ok(Value::String(s)) => s.parse::<i64>().map_err(|_| DbError::InvalidType)?,
+                Ok(_) => return Err(DbError::InvalidType),
+                Err(DbError::KeyNotFound) => 0,
+                Err(e) => return Err(e),
+            };
+
+            current_value += 1;
+            self.store(path, Value::Integer(current_value))?;
+            Ok(current_value)
+        })
     }
 
     pub fn set_expiry(&self, path: &str, seconds: u64) -> Result<(), DbError> {
-        if let Some(mut entry) = self.data.get_mut(path) {
+        self.observe(Op::SetExpiry, path, || {
             let expiry = SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_secs() + seconds;
-            
-            entry.expiry = Some(expiry);
-            self.save_to_disk()?;
-            Ok(())
-        } else {
-            Err(DbError::KeyNotFound)
-        }
+
+            self.commit_update(path, |entry| entry.expiry = Some(expiry))?
+                .ok_or(DbError::KeyNotFound)?;
+            self.save_to_disk()
+        })
     }
 
     pub fn remove_expiry(&self, path: &str) -> Result<(), DbError> {
-        if let Some(mut entry) = self.data.get_mut(path) {
-            entry.expiry = None;
-            self.save_to_disk()?;
-            Ok(())
-        } else {
-            Err(DbError::KeyNotFound)
-        }
+        self.observe(Op::RemoveExpiry, path, || {
+            self.commit_update(path, |entry| entry.expiry = None)?
+                .ok_or(DbError::KeyNotFound)?;
+            self.save_to_disk()
+        })
     }
 
     // Získání času do expirace
     pub fn ttl(&self, path: &str) -> Result<Option<u64>, DbError> {
-        if let Some(entry) = self.data.get(path) {
-            if let Some(expiry) = entry.expiry {
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
+        self.observe(Op::Ttl, path, || {
+            if let Some(entry) = self.data.get(path) {
+                if let Some(expiry) = entry.expiry {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs();
                 
-                if expiry > now {
-                    return Ok(Some(expiry - now));
+                    if expiry > now {
+                        return Ok(Some(expiry - now));
+                    }
                 }
+                Ok(None)
+            } else {
+                Err(DbError::KeyNotFound)
             }
-            Ok(None)
-        } else {
-            Err(DbError::KeyNotFound)
-        }
+        })
     }
 
     pub fn exists(&self, path: &str) -> bool {
-        if let Some(entry) = self.data.get(path) {
-            if let Some(expiry) = entry.expiry {
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-                return expiry > now;
+        self.observe(Op::Exists, path, || {
+            if let Some(entry) = self.data.get(path) {
+                if let Some(expiry) = entry.expiry {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs();
+                    return expiry > now;
+                }
+                true
+            } else {
+                false
             }
-            true
-        } else {
-            false
-        }
+        })
     }
 
     pub fn delete_by_pattern(&self, pattern: &str) -> Result<usize, DbError> {
-        let mut deleted = 0;
-        let pattern_components: Vec<String> = pattern.split('/')
-            .map(|s| s.to_string())
-            .collect();
-
-        let keys_to_delete: Vec<String> = self.data.iter()
-            .filter(|entry| entry.value().matches_pattern(&pattern_components))
-            .map(|entry| entry.key().clone())
-            .collect();
-
-        for key in keys_to_delete {
-            self.data.remove(&key);
-            deleted += 1;
-        }
+        self.observe(Op::DeleteByPattern, pattern, || self.cdc_batch(|| {
+            let mut deleted = 0;
+            let pattern_components: Vec<String> = pattern.split('/')
+                .map(|s| s.to_string())
+                .collect();
+
+            let keys_to_delete: Vec<String> = self.data.iter()
+                .filter(|entry| entry.value().matches_pattern(&pattern_components))
+                .map(|entry| entry.key().clone())
+                .collect();
+
+            for key in keys_to_delete {
+                if self.commit_remove(ChangeKind::Delete, &key, |_| true)?.is_some() {
+                    deleted += 1;
+                }
+            }
 
-        if deleted > 0 {
-            self.save_to_disk()?;
-        }
+            if deleted > 0 {
+                self.save_to_disk()?;
+            }
 
-        Ok(deleted)
+            Ok(deleted)
+        }))
     }
 
     pub fn get_detailed_stats(&self) -> DetailedDbStats {
@@ -405,10 +729,16 @@ impl Database {
             array_values: 0,
             map_values: 0,
             null_values: 0,
-            memory_usage: std::mem::size_of_val(&*self.data) as u64,
-            created_at: self.created_at,
-            last_backup: self.last_backup,
+            memory_usage: 0,
+            created_at: *self.created_at.read(),
+            last_backup: *self.last_backup.read(),
             average_path_depth: 0.0,
+            memory_by_type: BTreeMap::new(),
+            live_memory: 0,
+            allocated_memory: 0,
+            fragmentation_ratio: 0.0,
+            disk: self.disk_usage(),
+            prefixes: BTreeMap::new(),
         };
 
         let mut total_depth = 0;
@@ -439,12 +769,31 @@ impl Database {
             }
 
             total_depth += entry.value().path_components.len();
+
+            let size = entry_size(entry.key(), entry.value());
+            stats.memory_usage += size;
+            stats.live_memory += entry_live_size(entry.key(), entry.value());
+            *stats.memory_by_type.entry(query::type_name(&entry.value().value).to_string()).or_default() += size;
+            let prefix = match entry.key().split_once('/') {
+                Some((first, _)) => first,
+                None => "",
+            };
+            let prefix_stats = stats.prefixes.entry(prefix.to_string()).or_default();
+            prefix_stats.keys += 1;
+            prefix_stats.memory_usage += size;
         }
 
         if stats.total_keys > 0 {
             stats.average_path_depth = total_depth as f64 / stats.total_keys as f64;
         }
 
+        // Prázdné sloty tabulky klíčů (DashMap = hashbrown shardy, bajt navíc na slot)
+        let spare_slots = self.data.capacity().saturating_sub(stats.total_keys) as u64;
+        stats.allocated_memory = stats.memory_usage + spare_slots * (std::mem::size_of::<(String, Entry)>() as u64 + 1);
+        if stats.live_memory > 0 {
+            stats.fragmentation_ratio = stats.allocated_memory as f64 / stats.live_memory as f64;
+        }
+
         stats
     }
 
@@ -467,79 +816,104 @@ impl Database {
         DbStats {
             total_keys: self.data.len(),
             expired_keys: expired,
-            memory_usage: std::mem::size_of_val(&*self.data) as u64,
-            created_at: self.created_at,
-            last_backup: self.last_backup,
+            memory_usage: self.used_memory.load(Ordering::SeqCst),
+            disk_usage: self.disk_usage().total_bytes,
+            created_at: *self.created_at.read(),
+            last_backup: *self.last_backup.read(),
         }
     }
 
-    pub fn create_backup(&mut self, backup_path: &str) -> Result<(), DbError> {
-        self.save_to_disk()?;
-        fs::copy(&self.storage_path, backup_path)?;
-        self.last_backup = Some(Utc::now());
-        Ok(())
+    pub fn restore_from_backup(&self, backup_path: &str) -> Result<(), DbError> {
+        // Adresář s katalogem = obnova celého řetězce záloh
+        if Path::new(backup_path).is_dir() {
+            return self.restore_catalog(backup_path, None);
+        }
+        self.observe(Op::Restore, backup_path, || self.restore_file(backup_path))
     }
 
-    pub fn restore_from_backup(&mut self, backup_path: &str) -> Result<(), DbError> {
+    fn restore_file(&self, backup_path: &str) -> Result<(), DbError> {
         let content = fs::read_to_string(backup_path)?;
         let saved: SerializableDb = serde_json::from_str(&content)?;
         
-        self.data.clear();
-        for (key, value) in saved.data {
-            self.data.insert(key, value);
-        }
+        self.replace_all(saved.data)?;
         
-        self.created_at = saved.created_at;
-        self.last_backup = saved.last_backup;
+        *self.created_at.write() = saved.created_at;
+        *self.last_backup.write() = saved.last_backup;
         Ok(())
     }
 
     pub fn export_json(&self, export_path: &str) -> Result<(), DbError> {
-        let json = serde_json::to_string_pretty(&self.to_serializable())?;
-        fs::write(export_path, json)?;
-        Ok(())
+        self.observe(Op::Export, export_path, || self.snapshot().export_json(export_path))
+    }
+
+    pub fn import_json(&self, import_path: &str) -> Result<(), DbError> {
+        self.observe(Op::Import, import_path, || self.import_file(import_path))
     }
 
-    pub fn import_json(&mut self, import_path: &str) -> Result<(), DbError> {
+    fn import_file(&self, import_path: &str) -> Result<(), DbError> {
         let content = fs::read_to_string(import_path)?;
         let saved: SerializableDb = serde_json::from_str(&content)?;
         
-        self.data.clear();
-        for (key, value) in saved.data {
-            self.data.insert(key, value);
-        }
-        Ok(())
+        self.replace_all(saved.data)
     }
 
     pub fn flush(&self) -> Result<(), DbError> {
+        if self.storage.on_demand() {
+            return self.compact();
+        }
         self.save_to_disk()
     }
 
     pub fn clear(&self) -> Result<(), DbError> {
-        self.data.clear();
+        self.replace_all(HashMap::new())?;
         self.save_to_disk()
     }
 
     fn save_to_disk(&self) -> Result<(), DbError> {
-        let serializable = SerializableDb {
-            data: self.data.iter()
-                .map(|ref_multi| {
-                    (ref_multi.key().clone(), ref_multi.value().clone())
-                })
-                .collect(),
-            created_at: self.created_at,
-            last_backup: self.last_backup,
-            version: self.version.clone(),
+        let started = Instant::now();
+        let result = if self.storage.needs_compaction() {
+            self.compact()
+        } else {
+            let ticket = self.storage.ticket();
+            let _persist = self.persist_lock.lock();
+            self.storage.snapshot(self, ticket)
         };
-        
-        let json = serde_json::to_string(&serializable)?;
-        fs::write(&self.storage_path, json)?;
-        Ok(())
+        let elapsed = started.elapsed();
+        self.write_stats.record_write(elapsed);
+        self.metrics.record(Op::Persist, elapsed);
+        // Zápis na disk bývá nejčastější příčinou pomalých operací
+        self.slowlog.record(Op::Persist.name(), &self.storage_path, elapsed, &result);
+        if let Err(e) = &result {
+            log::error!(target: "rust_db", "persisting {:?} failed: {}", self.storage_path, e);
+        }
+        result
+    }
+
+    // Rewrites the storage from memory (log engine: drops superseded records)
+    pub fn compact(&self) -> Result<(), DbError> {
+        self.observe(Op::Compact, "", || {
+            let _gate = self.write_gate.write();
+            let _persist = self.persist_lock.lock();
+            self.storage.compact(self)
+        })
+    }
+
+    // Runs `f` with writes paused and the file on disk matching memory
+    pub(crate) fn with_flushed<T>(&self, f: impl FnOnce() -> Result<T, DbError>) -> Result<T, DbError> {
+        let _gate = self.write_gate.write();
+        let _persist = self.persist_lock.lock();
+        if self.storage.on_demand() {
+            self.storage.compact(self)?;
+        } else {
+            self.storage.snapshot(self, self.storage.ticket())?;
+        }
+        f()
     }
 }
 
 // FFI rozhraní
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn db_create(path: *const c_char) -> *mut Database {
     let c_str = unsafe { CStr::from_ptr(path) };
     let path_str = c_str.to_str().unwrap();
@@ -547,159 +921,804 @@ pub extern "C" fn db_create(path: *const c_char) -> *mut Database {
 }
 
 #[no_mangle]
-pub extern "C" fn db_init_volume(path: *const c_char, size_mb: u64) -> bool {
-    let c_str = unsafe { CStr::from_ptr(path) };
-    let path_str = c_str.to_str().unwrap();
-    
-    Database::init_btrfs_volume(path_str, size_mb).is_ok()
+pub extern "C" fn db_create_memory() -> *mut Database {
+    Box::into_raw(Box::new(Database::in_memory()))
 }
 
+// config je JSON se strukturou StorageConfig, vrací null při chybě načtení
 #[no_mangle]
-pub extern "C" fn db_set(db: *mut Database, key: *const c_char, value: *const c_char) -> bool {
-    let database = unsafe { &*db };
-    let key_str = unsafe { CStr::from_ptr(key) }.to_str().unwrap();
-    let value_str = unsafe { CStr::from_ptr(value) }.to_str().unwrap();
-    
-    database.set(key_str, Value::String(value_str.to_string())).is_ok()
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_create_with(path: *const c_char, config: *const c_char) -> *mut Database {
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+    let config_str = unsafe { CStr::from_ptr(config) }.to_str().unwrap();
+
+    let Ok(config) = serde_json::from_str::<StorageConfig>(config_str) else {
+        return std::ptr::null_mut();
+    };
+    match Database::open(path_str, &config) {
+        Ok(db) => Box::into_raw(Box::new(db)),
+        Err(_) => std::ptr::null_mut(),
+    }
 }
 
 #[no_mangle]
-pub extern "C" fn db_get(db: *mut Database, key: *const c_char) -> *mut c_char {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_compact(db: *mut Database) -> bool {
     let database = unsafe { &*db };
-    let key_str = unsafe { CStr::from_ptr(key) }.to_str().unwrap();
-    
-    match database.get(key_str) {
-        Ok(Value::String(s)) => {
-            let c_string = CString::new(s).unwrap();
-            c_string.into_raw()
-        }
-        _ => std::ptr::null_mut(),
-    }
+    database.compact().is_ok()
 }
 
+// config je JSON se strukturou MemoryConfig, např. {"max_memory": 104857600, "policy": "allkeys-lru"}
 #[no_mangle]
-pub extern "C" fn db_find_by_path(db: *mut Database, pattern: *const c_char) -> *mut c_char {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_set_memory_limit(db: *mut Database, config: *const c_char) -> bool {
     let database = unsafe { &*db };
-    let pattern_str = unsafe { CStr::from_ptr(pattern) }.to_str().unwrap();
-    
-    match database.find_by_path(pattern_str) {
-        Ok(results) => {
-            match serde_json::to_string(&results) {
-                Ok(json) => {
-                    let c_string = CString::new(json).unwrap();
-                    c_string.into_raw()
-                }
-                Err(_) => std::ptr::null_mut(),
-            }
-        }
-        Err(_) => std::ptr::null_mut(),
+    let config_str = unsafe { CStr::from_ptr(config) }.to_str().unwrap();
+
+    match serde_json::from_str::<MemoryConfig>(config_str) {
+        Ok(config) => database.set_memory_limit(config).is_ok(),
+        Err(_) => false,
     }
 }
 
+// Vrací JSON MemoryStats
 #[no_mangle]
-pub extern "C" fn db_increment(db: *mut Database, path: *const c_char) -> i64 {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_memory_stats(db: *mut Database) -> *mut c_char {
     let database = unsafe { &*db };
-    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
-    
-    match database.increment(path_str) {
-        Ok(value) => value,
-        Err(_) => -1,
-    }
+    let json = serde_json::to_string(&database.memory_stats()).unwrap();
+    CString::new(json).unwrap().into_raw()
 }
 
+// Vrací JSON DiskUsage
 #[no_mangle]
-pub extern "C" fn db_set_expiry(db: *mut Database, path: *const c_char, seconds: u64) -> bool {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_disk_usage(db: *mut Database) -> *mut c_char {
     let database = unsafe { &*db };
-    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
-    
-    database.set_expiry(path_str, seconds).is_ok()
+    let json = serde_json::to_string(&database.disk_usage()).unwrap();
+    CString::new(json).unwrap().into_raw()
 }
 
+// Vrací JSON Metrics
 #[no_mangle]
-pub extern "C" fn db_ttl(db: *mut Database, path: *const c_char) -> i64 {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_metrics(db: *mut Database) -> *mut c_char {
     let database = unsafe { &*db };
-    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
-    
-    match database.ttl(path_str) {
-        Ok(Some(ttl)) => ttl as i64,
-        _ => -1,
-    }
+    let json = serde_json::to_string(&database.metrics()).unwrap();
+    CString::new(json).unwrap().into_raw()
 }
 
+// Vrací metriky v textovém formátu Prometheus, např. pro /metrics endpoint v PHP
 #[no_mangle]
-pub extern "C" fn db_exists(db: *mut Database, path: *const c_char) -> bool {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_metrics_prometheus(db: *mut Database) -> *mut c_char {
     let database = unsafe { &*db };
-    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
-    
-    database.exists(path_str)
+    CString::new(database.prometheus_metrics()).unwrap().into_raw()
 }
 
+// Spustí HTTP listener s GET /metrics pro Prometheus (např. "0.0.0.0:9100"), prázdná adresa ho zastaví
 #[no_mangle]
-pub extern "C" fn db_delete_by_pattern(db: *mut Database, pattern: *const c_char) -> i64 {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_metrics_serve(db: *mut Database, addr: *const c_char) -> bool {
     let database = unsafe { &*db };
-    let pattern_str = unsafe { CStr::from_ptr(pattern) }.to_str().unwrap();
-    
-    match database.delete_by_pattern(pattern_str) {
-        Ok(count) => count as i64,
-        Err(_) => -1,
+    let addr_str = unsafe { CStr::from_ptr(addr) }.to_str().unwrap();
+
+    if addr_str.is_empty() {
+        database.stop_metrics_server();
+        return true;
     }
+    // SAFETY: FFI databases are boxed and dropped only by db_destroy
+    unsafe { database.serve_metrics(addr_str) }.is_ok()
 }
 
+// filter má syntaxi RUST_LOG (např. "rust_db=debug"), prázdný = proměnná RUST_LOG.
+// Vrací false, pokud už je logger nastavený.
 #[no_mangle]
-pub extern "C" fn db_get_detailed_stats(db: *mut Database) -> *mut c_char {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_init_logging(filter: *const c_char) -> bool {
+    let filter_str = unsafe { CStr::from_ptr(filter) }.to_str().unwrap();
+    let mut builder = if filter_str.is_empty() {
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn"))
+    } else {
+        let mut builder = env_logger::Builder::new();
+        builder.parse_filters(filter_str);
+        builder
+    };
+    builder.try_init().is_ok()
+}
+
+// config je JSON se strukturou SlowlogConfig
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_slowlog_config(db: *mut Database, config: *const c_char) -> bool {
     let database = unsafe { &*db };
-    let stats = database.get_detailed_stats();
-    
-    match serde_json::to_string(&stats) {
-        Ok(json) => {
-            let c_string = CString::new(json).unwrap();
-            c_string.into_raw()
+    let config_str = unsafe { CStr::from_ptr(config) }.to_str().unwrap();
+    match serde_json::from_str::<SlowlogConfig>(config_str) {
+        Ok(config) => {
+            database.set_slowlog_config(config);
+            true
         }
-        Err(_) => std::ptr::null_mut(),
+        Err(_) => false,
     }
 }
 
+// Vrací JSON pole SlowlogEntry, nejnovější první
 #[no_mangle]
-pub extern "C" fn db_delete(db: *mut Database, key: *const c_char) -> bool {
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_slowlog_get(db: *mut Database, limit: u64) -> *mut c_char {
     let database = unsafe { &*db };
-    let key_str = unsafe { CStr::from_ptr(key) }.to_str().unwrap();
-    
-    database.delete(key_str).is_ok()
+    let json = serde_json::to_string(&database.slowlog(limit as usize)).unwrap();
+    CString::new(json).unwrap().into_raw()
 }
 
 #[no_mangle]
-pub extern "C" fn db_free_string(s: *mut c_char) {
-    unsafe {
-        if !s.is_null() {
-            let _ = CString::from_raw(s);
-        }
-    }
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_slowlog_reset(db: *mut Database) {
+    let database = unsafe { &*db };
+    database.slowlog_reset();
 }
 
+// Vrací JSON WriteMetrics
 #[no_mangle]
-pub extern "C" fn db_destroy(db: *mut Database) {
-    unsafe {
-        let _ = Box::from_raw(db);
-    }
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_write_metrics(db: *mut Database) -> *mut c_char {
+    let database = unsafe { &*db };
+    let json = serde_json::to_string(&database.write_metrics()).unwrap();
+    CString::new(json).unwrap().into_raw()
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_init_volume(path: *const c_char, size_mb: u64) -> bool {
+    let c_str = unsafe { CStr::from_ptr(path) };
+    let path_str = c_str.to_str().unwrap();
+    
+    Database::init_btrfs_volume(path_str, size_mb).is_ok()
+}
+
+// config je JSON se strukturou VolumeConfig
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_init_volume_with(path: *const c_char, config: *const c_char) -> bool {
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+    let config_str = unsafe { CStr::from_ptr(config) }.to_str().unwrap();
+
+    match serde_json::from_str::<VolumeConfig>(config_str) {
+        Ok(config) => Volume::create_with(path_str, &config).is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_volume_unmount(path: *const c_char) -> bool {
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+
+    Volume::open(path_str).and_then(|volume| volume.unmount()).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_volume_resize(path: *const c_char, size_mb: u64) -> bool {
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+
+    Volume::open(path_str).and_then(|volume| volume.resize(size_mb)).is_ok()
+}
+
+// Vrací JSON VolumeUsage
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_volume_usage(path: *const c_char) -> *mut c_char {
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+
+    match Volume::open(path_str).and_then(|volume| volume.usage()) {
+        Ok(usage) => {
+            let json = serde_json::to_string(&usage).unwrap();
+            CString::new(json).unwrap().into_raw()
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_btrfs_init_subvolume(path: *const c_char) -> bool {
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+
+    Database::init_btrfs_subvolume(path_str).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_btrfs_enable(db: *mut Database, snapshot_dir: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let dir_str = unsafe { CStr::from_ptr(snapshot_dir) }.to_str().unwrap();
+
+    database.enable_btrfs_snapshots(dir_str).is_ok()
+}
+
+// config je JSON se strukturou BtrfsConfig (snapshot_dir, volitelně btrfs_bin)
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_btrfs_enable_with(db: *mut Database, config: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let config_str = unsafe { CStr::from_ptr(config) }.to_str().unwrap();
+
+    match serde_json::from_str::<BtrfsConfig>(config_str) {
+        Ok(config) => database.enable_btrfs_snapshots_with(&config).is_ok(),
+        Err(_) => false,
+    }
+}
+
+// Prázdné jméno = časové razítko, vrací JSON BtrfsSnapshotInfo
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_btrfs_snapshot(db: *mut Database, name: *const c_char) -> *mut c_char {
+    let database = unsafe { &*db };
+    let name_str = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
+
+    let name = if name_str.is_empty() { None } else { Some(name_str) };
+    match database.btrfs_snapshot(name) {
+        Ok(info) => match serde_json::to_string(&info) {
+            Ok(json) => CString::new(json).unwrap().into_raw(),
+            Err(_) => std::ptr::null_mut(),
+        },
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_btrfs_list(db: *mut Database) -> *mut c_char {
+    let database = unsafe { &*db };
+
+    match database.list_btrfs_snapshots() {
+        Ok(snapshots) => match serde_json::to_string(&snapshots) {
+            Ok(json) => CString::new(json).unwrap().into_raw(),
+            Err(_) => std::ptr::null_mut(),
+        },
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+// Prázdný parent = plný send stream
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_btrfs_send(db: *mut Database, name: *const c_char, parent: *const c_char, output: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let name_str = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
+    let parent_str = unsafe { CStr::from_ptr(parent) }.to_str().unwrap();
+    let output_str = unsafe { CStr::from_ptr(output) }.to_str().unwrap();
+
+    let parent = if parent_str.is_empty() { None } else { Some(parent_str) };
+    database.btrfs_send(name_str, parent, output_str).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_btrfs_restore(db: *mut Database, name: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let name_str = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
+
+    database.restore_btrfs_snapshot(name_str).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_btrfs_delete(db: *mut Database, name: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let name_str = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
+
+    database.delete_btrfs_snapshot(name_str).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_set(db: *mut Database, key: *const c_char, value: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let key_str = unsafe { CStr::from_ptr(key) }.to_str().unwrap();
+    let value_str = unsafe { CStr::from_ptr(value) }.to_str().unwrap();
+    
+    database.set(key_str, Value::String(value_str.to_string())).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_get(db: *mut Database, key: *const c_char) -> *mut c_char {
+    let database = unsafe { &*db };
+    let key_str = unsafe { CStr::from_ptr(key) }.to_str().unwrap();
+    
+    match database.get(key_str) {
+        Ok(Value::String(s)) => {
+            let c_string = CString::new(s).unwrap();
+            c_string.into_raw()
+        }
+        _ => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_find_by_path(db: *mut Database, pattern: *const c_char) -> *mut c_char {
+    let database = unsafe { &*db };
+    let pattern_str = unsafe { CStr::from_ptr(pattern) }.to_str().unwrap();
+    
+    match database.find_by_path(pattern_str) {
+        Ok(results) => {
+            match serde_json::to_string(&results) {
+                Ok(json) => {
+                    let c_string = CString::new(json).unwrap();
+                    c_string.into_raw()
+                }
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_query(db: *mut Database, query: *const c_char) -> *mut c_char {
+    let database = unsafe { &*db };
+    let query_str = unsafe { CStr::from_ptr(query) }.to_str().unwrap();
+    
+    match database.query(query_str) {
+        Ok(results) => {
+            match serde_json::to_string(&results) {
+                Ok(json) => {
+                    let c_string = CString::new(json).unwrap();
+                    c_string.into_raw()
+                }
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_enable_search(db: *mut Database, prefixes: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let prefixes_str = unsafe { CStr::from_ptr(prefixes) }.to_str().unwrap();
+    
+    // Prefixy oddělené čárkou, prázdný řetězec = celá databáze
+    let prefixes: Vec<&str> = prefixes_str.split(',')
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect();
+    database.enable_search(&prefixes);
+    true
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_search(db: *mut Database, query: *const c_char, prefix: *const c_char, limit: u64) -> *mut c_char {
+    let database = unsafe { &*db };
+    let query_str = unsafe { CStr::from_ptr(query) }.to_str().unwrap();
+    let prefix_str = unsafe { CStr::from_ptr(prefix) }.to_str().unwrap();
+    
+    match database.search(query_str, prefix_str, limit as usize) {
+        Ok(hits) => {
+            match serde_json::to_string(&hits) {
+                Ok(json) => {
+                    let c_string = CString::new(json).unwrap();
+                    c_string.into_raw()
+                }
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_aggregate(db: *mut Database, pattern: *const c_char) -> *mut c_char {
+    let database = unsafe { &*db };
+    let pattern_str = unsafe { CStr::from_ptr(pattern) }.to_str().unwrap();
+    
+    match database.aggregate(pattern_str) {
+        Ok(aggregation) => {
+            match serde_json::to_string(&aggregation) {
+                Ok(json) => {
+                    let c_string = CString::new(json).unwrap();
+                    c_string.into_raw()
+                }
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_aggregate_by(db: *mut Database, pattern: *const c_char, segment: u64) -> *mut c_char {
+    let database = unsafe { &*db };
+    let pattern_str = unsafe { CStr::from_ptr(pattern) }.to_str().unwrap();
+    
+    match database.aggregate_by(pattern_str, segment as usize) {
+        Ok(groups) => {
+            match serde_json::to_string(&groups) {
+                Ok(json) => {
+                    let c_string = CString::new(json).unwrap();
+                    c_string.into_raw()
+                }
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_change_cursor(db: *mut Database) -> u64 {
+    let database = unsafe { &*db };
+    
+    database.current_change_cursor()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_poll_changes(db: *mut Database, pattern: *const c_char, cursor: u64, limit: u64) -> *mut c_char {
+    let database = unsafe { &*db };
+    let pattern_str = unsafe { CStr::from_ptr(pattern) }.to_str().unwrap();
+    
+    match database.poll_changes(pattern_str, cursor, limit as usize) {
+        Ok(batch) => {
+            match serde_json::to_string(&batch) {
+                Ok(json) => {
+                    let c_string = CString::new(json).unwrap();
+                    c_string.into_raw()
+                }
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+// Spustí RESP listener pro SUBSCRIBE/PSUBSCRIBE, prázdná adresa ho zastaví
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_pubsub_serve(db: *mut Database, addr: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let addr_str = unsafe { CStr::from_ptr(addr) }.to_str().unwrap();
+
+    if addr_str.is_empty() {
+        database.stop_pubsub();
+        return true;
+    }
+    database.serve_pubsub(addr_str).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_enable_cdc(db: *mut Database, max_bytes: u64, max_age_secs: u64) -> bool {
+    let database = unsafe { &*db };
+    
+    // 0 = bez limitu
+    let retention = CdcRetention {
+        max_bytes: (max_bytes > 0).then_some(max_bytes),
+        max_age_secs: (max_age_secs > 0).then_some(max_age_secs),
+    };
+    database.enable_cdc(retention).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_cdc_read(db: *mut Database, consumer: *const c_char, limit: u64) -> *mut c_char {
+    let database = unsafe { &*db };
+    let consumer_str = unsafe { CStr::from_ptr(consumer) }.to_str().unwrap();
+    
+    match database.cdc_read_consumer(consumer_str, limit as usize) {
+        Ok(records) => {
+            match serde_json::to_string(&records) {
+                Ok(json) => {
+                    let c_string = CString::new(json).unwrap();
+                    c_string.into_raw()
+                }
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_cdc_read_from(db: *mut Database, offset: u64, limit: u64) -> *mut c_char {
+    let database = unsafe { &*db };
+    
+    match database.cdc_read(offset, limit as usize) {
+        Ok(records) => {
+            match serde_json::to_string(&records) {
+                Ok(json) => {
+                    let c_string = CString::new(json).unwrap();
+                    c_string.into_raw()
+                }
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_cdc_ack(db: *mut Database, consumer: *const c_char, seq: u64) -> bool {
+    let database = unsafe { &*db };
+    let consumer_str = unsafe { CStr::from_ptr(consumer) }.to_str().unwrap();
+    
+    database.cdc_ack(consumer_str, seq).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_increment(db: *mut Database, path: *const c_char) -> i64 {
+    let database = unsafe { &*db };
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+    
+    database.increment(path_str).unwrap_or(-1)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_set_expiry(db: *mut Database, path: *const c_char, seconds: u64) -> bool {
+    let database = unsafe { &*db };
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+    
+    database.set_expiry(path_str, seconds).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_ttl(db: *mut Database, path: *const c_char) -> i64 {
+    let database = unsafe { &*db };
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+    
+    match database.ttl(path_str) {
+        Ok(Some(ttl)) => ttl as i64,
+        _ => -1,
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_exists(db: *mut Database, path: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+    
+    database.exists(path_str)
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_delete_by_pattern(db: *mut Database, pattern: *const c_char) -> i64 {
+    let database = unsafe { &*db };
+    let pattern_str = unsafe { CStr::from_ptr(pattern) }.to_str().unwrap();
+    
+    match database.delete_by_pattern(pattern_str) {
+        Ok(count) => count as i64,
+        Err(_) => -1,
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_get_detailed_stats(db: *mut Database) -> *mut c_char {
+    let database = unsafe { &*db };
+    let stats = database.get_detailed_stats();
+    
+    match serde_json::to_string(&stats) {
+        Ok(json) => {
+            let c_string = CString::new(json).unwrap();
+            c_string.into_raw()
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_delete(db: *mut Database, key: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let key_str = unsafe { CStr::from_ptr(key) }.to_str().unwrap();
+    
+    database.delete(key_str).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_free_string(s: *mut c_char) {
+    unsafe {
+        if !s.is_null() {
+            let _ = CString::from_raw(s);
+        }
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_destroy(db: *mut Database) {
+    unsafe {
+        let _ = Box::from_raw(db);
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn db_backup(db: *mut Database, path: *const c_char) -> bool {
-    let database = unsafe { &mut *db };
+    let database = unsafe { &*db };
     let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
     
     database.create_backup(path_str).is_ok()
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn db_restore(db: *mut Database, path: *const c_char) -> bool {
-    let database = unsafe { &mut *db };
+    let database = unsafe { &*db };
     let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
     
     database.restore_from_backup(path_str).is_ok()
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_backup_chained(db: *mut Database, dir: *const c_char, kind: u8) -> bool {
+    let database = unsafe { &*db };
+    let dir_str = unsafe { CStr::from_ptr(dir) }.to_str().unwrap();
+    
+    let kind = match kind {
+        0 => BackupKind::Full,
+        1 => BackupKind::Incremental,
+        2 => BackupKind::Differential,
+        _ => return false,
+    };
+    database.create_catalog_backup(dir_str, kind).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_restore_point(db: *mut Database, dir: *const c_char, timestamp: i64) -> bool {
+    let database = unsafe { &*db };
+    let dir_str = unsafe { CStr::from_ptr(dir) }.to_str().unwrap();
+    
+    // 0 = poslední dostupný stav
+    let point = if timestamp > 0 {
+        match DateTime::from_timestamp(timestamp, 0) {
+            Some(point) => Some(point),
+            None => return false,
+        }
+    } else {
+        None
+    };
+    database.restore_catalog(dir_str, point).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_backup_catalog(dir: *const c_char) -> *mut c_char {
+    let dir_str = unsafe { CStr::from_ptr(dir) }.to_str().unwrap();
+    
+    match BackupCatalog::load(dir_str) {
+        Ok(catalog) => {
+            match serde_json::to_string(&catalog) {
+                Ok(json) => {
+                    let c_string = CString::new(json).unwrap();
+                    c_string.into_raw()
+                }
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_verify_backup(path: *const c_char) -> *mut c_char {
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+    
+    match Database::verify_backup(path_str) {
+        Ok(results) => {
+            match serde_json::to_string(&results) {
+                Ok(json) => {
+                    let c_string = CString::new(json).unwrap();
+                    c_string.into_raw()
+                }
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+fn s3_target_from_json(config: *const c_char) -> Option<S3Target> {
+    let config_str = unsafe { CStr::from_ptr(config) }.to_str().ok()?;
+    let config: S3Config = serde_json::from_str(config_str).ok()?;
+    S3Target::new(config).ok()
+}
+
+// config je JSON se strukturou S3Config
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_backup_s3(db: *mut Database, config: *const c_char, name: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let name_str = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
+    
+    match s3_target_from_json(config) {
+        Some(target) => database.create_backup_to(&target, name_str).is_ok(),
+        None => false,
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_restore_s3(db: *mut Database, config: *const c_char, name: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let name_str = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
+    
+    match s3_target_from_json(config) {
+        Some(target) => database.restore_from_target(&target, name_str).is_ok(),
+        None => false,
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_export_s3(db: *mut Database, config: *const c_char, name: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let name_str = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
+    
+    match s3_target_from_json(config) {
+        Some(target) => database.export_json_to(&target, name_str).is_ok(),
+        None => false,
+    }
+}
+
+// config je JSON se strukturou ScheduleConfig, např.
+// {"cron": "0 3 * * *", "dir": "/backups", "retention": {"keep_last": 3, "keep_daily": 7, "keep_weekly": 4}}
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_schedule_backups(db: *mut Database, config: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let config_str = unsafe { CStr::from_ptr(config) }.to_str().unwrap();
+
+    match serde_json::from_str::<ScheduleConfig>(config_str) {
+        // SAFETY: FFI databases are boxed and dropped only by db_destroy
+        Ok(config) => unsafe { database.schedule_backups(config) }.is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_stop_backups(db: *mut Database) {
+    let database = unsafe { &*db };
+    database.stop_backups();
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_backup_schedule_status(db: *mut Database) -> *mut c_char {
+    let database = unsafe { &*db };
+
+    match database.backup_schedule_status() {
+        Some(status) => match serde_json::to_string(&status) {
+            Ok(json) => CString::new(json).unwrap().into_raw(),
+            Err(_) => std::ptr::null_mut(),
+        },
+        None => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn db_export(db: *mut Database, path: *const c_char) -> bool {
     let database = unsafe { &*db };
     let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
@@ -708,9 +1727,292 @@ pub extern "C" fn db_export(db: *mut Database, path: *const c_char) -> bool {
 }
 
 #[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn db_import(db: *mut Database, path: *const c_char) -> bool {
-    let database = unsafe { &mut *db };
+    let database = unsafe { &*db };
     let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
     
     database.import_json(path_str).is_ok()
-}
\ No newline at end of file
+}
+
+fn import_options_from_json(options: *const c_char) -> Option<ImportOptions> {
+    let options_str = unsafe { CStr::from_ptr(options) }.to_str().ok()?;
+    serde_json::from_str(options_str).ok()
+}
+
+// options je JSON se strukturou ImportOptions, vrací ImportReport
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_import_with(db: *mut Database, path: *const c_char, options: *const c_char) -> *mut c_char {
+    let database = unsafe { &*db };
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+
+    let report = import_options_from_json(options)
+        .and_then(|options| database.import_json_with(path_str, &options).ok());
+    match report.map(|report| serde_json::to_string(&report)) {
+        Some(Ok(json)) => CString::new(json).unwrap().into_raw(),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_restore_with(db: *mut Database, path: *const c_char, options: *const c_char) -> *mut c_char {
+    let database = unsafe { &*db };
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+
+    let report = import_options_from_json(options)
+        .and_then(|options| database.restore_from_backup_with(path_str, &options).ok());
+    match report.map(|report| serde_json::to_string(&report)) {
+        Some(Ok(json)) => CString::new(json).unwrap().into_raw(),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+// format: 0 = NDJSON, 1 = CSV; prázdný pattern exportuje vše. Vrací počet záznamů, -1 při chybě
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_export_stream(db: *mut Database, path: *const c_char, format: u8, pattern: *const c_char) -> i64 {
+    let database = unsafe { &*db };
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+    let pattern_str = unsafe { CStr::from_ptr(pattern) }.to_str().unwrap();
+
+    let format = if format == 1 { StreamFormat::Csv } else { StreamFormat::Ndjson };
+    let pattern = if pattern_str.is_empty() { None } else { Some(pattern_str) };
+    let result = fs::File::create(path_str)
+        .map_err(DbError::from)
+        .and_then(|file| database.export_stream(std::io::BufWriter::new(file), format, pattern));
+    match result {
+        Ok(summary) => summary.exported as i64,
+        Err(_) => -1,
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_import_stream(db: *mut Database, path: *const c_char, format: u8, options: *const c_char) -> *mut c_char {
+    let database = unsafe { &*db };
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+
+    let format = if format == 1 { StreamFormat::Csv } else { StreamFormat::Ndjson };
+    let report = import_options_from_json(options).and_then(|options| {
+        let file = fs::File::open(path_str).ok()?;
+        database.import_stream(std::io::BufReader::new(file), format, &options).ok()
+    });
+    match report.map(|report| serde_json::to_string(&report)) {
+        Some(Ok(json)) => CString::new(json).unwrap().into_raw(),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+// path je RDB dump, AOF soubor nebo adresář appendonlydir; config je JSON se strukturou RedisImportConfig
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_import_redis(db: *mut Database, path: *const c_char, config: *const c_char) -> *mut c_char {
+    let database = unsafe { &*db };
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+    let config_str = unsafe { CStr::from_ptr(config) }.to_str().unwrap();
+
+    let summary = serde_json::from_str::<RedisImportConfig>(config_str).ok()
+        .and_then(|config| database.import_redis(path_str, &config).ok());
+    match summary.map(|summary| serde_json::to_string(&summary)) {
+        Some(Ok(json)) => CString::new(json).unwrap().into_raw(),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+// config je JSON se strukturou SqliteExportConfig, vrací SqliteExportSummary
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_export_sqlite(db: *mut Database, path: *const c_char, config: *const c_char) -> *mut c_char {
+    let database = unsafe { &*db };
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+    let config_str = unsafe { CStr::from_ptr(config) }.to_str().unwrap();
+
+    let summary = serde_json::from_str::<SqliteExportConfig>(config_str).ok()
+        .and_then(|config| database.export_sqlite(path_str, &config).ok());
+    match summary.map(|summary| serde_json::to_string(&summary)) {
+        Some(Ok(json)) => CString::new(json).unwrap().into_raw(),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_snapshot_create(db: *mut Database) -> *mut Snapshot {
+    let database = unsafe { &*db };
+    Box::into_raw(Box::new(database.snapshot()))
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_snapshot_revision(snapshot: *mut Snapshot) -> u64 {
+    let snapshot = unsafe { &*snapshot };
+    snapshot.revision()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_snapshot_get(snapshot: *mut Snapshot, key: *const c_char) -> *mut c_char {
+    let snapshot = unsafe { &*snapshot };
+    let key_str = unsafe { CStr::from_ptr(key) }.to_str().unwrap();
+    
+    match snapshot.get(key_str) {
+        Ok(Value::String(s)) => {
+            let c_string = CString::new(s).unwrap();
+            c_string.into_raw()
+        }
+        _ => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_snapshot_find_by_path(snapshot: *mut Snapshot, pattern: *const c_char) -> *mut c_char {
+    let snapshot = unsafe { &*snapshot };
+    let pattern_str = unsafe { CStr::from_ptr(pattern) }.to_str().unwrap();
+    
+    match snapshot.find_by_path(pattern_str) {
+        Ok(results) => {
+            match serde_json::to_string(&results) {
+                Ok(json) => {
+                    let c_string = CString::new(json).unwrap();
+                    c_string.into_raw()
+                }
+                Err(_) => std::ptr::null_mut(),
+            }
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_snapshot_export(snapshot: *mut Snapshot, path: *const c_char) -> bool {
+    let snapshot = unsafe { &*snapshot };
+    let path_str = unsafe { CStr::from_ptr(path) }.to_str().unwrap();
+    
+    snapshot.export_json(path_str).is_ok()
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_snapshot_destroy(snapshot: *mut Snapshot) {
+    unsafe {
+        let _ = Box::from_raw(snapshot);
+    }
+}
+
+// config je JSON se strukturou ReaperConfig, interval_ms 0 reaper vypne
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_set_reaper(db: *mut Database, config: *const c_char) -> bool {
+    let database = unsafe { &*db };
+    let config_str = unsafe { CStr::from_ptr(config) }.to_str().unwrap();
+
+    match serde_json::from_str::<ReaperConfig>(config_str) {
+        // SAFETY: FFI databases are boxed and dropped only by db_destroy
+        Ok(config) => unsafe { database.start_reaper(config) }.is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_reaper_status(db: *mut Database) -> *mut c_char {
+    let database = unsafe { &*db };
+
+    match database.reaper_status() {
+        Some(status) => CString::new(serde_json::to_string(&status).unwrap()).unwrap().into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+// Prázdný dir = namespaces jen v paměti, vrací null při chybě
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_namespaces_open(dir: *const c_char) -> *mut Namespaces {
+    let dir_str = unsafe { CStr::from_ptr(dir) }.to_str().unwrap();
+    if dir_str.is_empty() {
+        return Box::into_raw(Box::new(Namespaces::in_memory()));
+    }
+    match Namespaces::open(dir_str) {
+        Ok(namespaces) => Box::into_raw(Box::new(namespaces)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_namespaces_destroy(namespaces: *mut Namespaces) {
+    unsafe {
+        let _ = Box::from_raw(namespaces);
+    }
+}
+
+// Databáze vrácené db_namespace_* se předávají běžným db_* funkcím a uvolňují
+// přes db_namespace_release, nikdy db_destroy
+fn namespace_handle(result: Result<Arc<Database>, DbError>) -> *mut Database {
+    match result {
+        Ok(db) => Arc::into_raw(db) as *mut Database,
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+// config je JSON se strukturou NamespaceConfig (prázdný = výchozí), u existujícího namespace se ignoruje
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_namespace_open(namespaces: *mut Namespaces, name: *const c_char, config: *const c_char) -> *mut Database {
+    let namespaces = unsafe { &*namespaces };
+    let name_str = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
+    let config_str = unsafe { CStr::from_ptr(config) }.to_str().unwrap();
+
+    let config = if config_str.is_empty() {
+        NamespaceConfig::default()
+    } else {
+        match serde_json::from_str::<NamespaceConfig>(config_str) {
+            Ok(config) => config,
+            Err(_) => return std::ptr::null_mut(),
+        }
+    };
+    namespace_handle(namespaces.open_namespace(name_str, config))
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_namespace_switch(namespaces: *mut Namespaces, name: *const c_char) -> *mut Database {
+    let namespaces = unsafe { &*namespaces };
+    let name_str = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
+    namespace_handle(namespaces.switch(name_str))
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_namespace_current(namespaces: *mut Namespaces) -> *mut Database {
+    let namespaces = unsafe { &*namespaces };
+    namespace_handle(Ok(namespaces.current()))
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_namespace_drop(namespaces: *mut Namespaces, name: *const c_char) -> bool {
+    let namespaces = unsafe { &*namespaces };
+    let name_str = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
+    namespaces.drop_namespace(name_str).is_ok()
+}
+
+#[no_mangle]
+pub extern "C" fn db_namespace_release(db: *mut Database) {
+    unsafe {
+        drop(Arc::from_raw(db as *const Database));
+    }
+}
+
+// Vrací JSON pole NamespaceStats
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn db_namespace_stats(namespaces: *mut Namespaces) -> *mut c_char {
+    let namespaces = unsafe { &*namespaces };
+    let json = serde_json::to_string(&namespaces.stats()).unwrap();
+    CString::new(json).unwrap().into_raw()
+}
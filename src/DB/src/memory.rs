@@ -0,0 +1,245 @@
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ChangeKind, Database, DbError, Entry, Value};
+
+// Kandidáti na vyřazení se hledají průchodem celé mapy, jeden průchod jich připraví tolik
+const EVICTION_POOL: usize = 64;
+// LFU counter halves for every this many idle seconds
+const LFU_DECAY_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EvictionPolicy {
+    // Writes that need more memory fail with OutOfMemory
+    #[default]
+    Noeviction,
+    AllkeysLru,
+    // Only keys with an expiry are evicted
+    VolatileLru,
+    AllkeysLfu,
+    // Keys closest to expiring go first
+    VolatileTtl,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    // Bytes, 0 = unlimited
+    pub max_memory: u64,
+    pub policy: EvictionPolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryStats {
+    pub used_memory: u64,
+    pub max_memory: u64,
+    pub policy: EvictionPolicy,
+    pub evicted_keys: u64,
+    pub rejected_writes: u64,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+// Last access and hit count, updated through shared references on reads
+#[derive(Debug, Default)]
+pub(crate) struct Access {
+    last_ms: AtomicU64,
+    hits: AtomicU32,
+}
+
+impl Clone for Access {
+    fn clone(&self) -> Self {
+        Access {
+            last_ms: AtomicU64::new(self.last_ms.load(Ordering::Relaxed)),
+            hits: AtomicU32::new(self.hits.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl Access {
+    pub(crate) fn new() -> Self {
+        Access {
+            last_ms: AtomicU64::new(now_ms()),
+            hits: AtomicU32::new(1),
+        }
+    }
+
+    pub(crate) fn touch(&self) {
+        self.last_ms.store(now_ms(), Ordering::Relaxed);
+        let _ = self.hits.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |h| h.checked_add(1));
+    }
+
+    fn frequency(&self, now: u64) -> u64 {
+        let idle_secs = now.saturating_sub(self.last_ms.load(Ordering::Relaxed)) / 1000;
+        let halvings = (idle_secs / LFU_DECAY_SECS).min(31) as u32;
+        (self.hits.load(Ordering::Relaxed) >> halvings) as u64
+    }
+}
+
+// Bytes owned by a value on the heap; `live` counts only the bytes in use, without the
+// spare capacity of strings, vectors and hash tables
+fn value_heap(value: &Value, live: bool) -> usize {
+    let string = |s: &String| if live { s.len() } else { s.capacity() };
+    match value {
+        Value::String(s) => string(s),
+        Value::Array(items) => {
+            let slots = if live { items.len() } else { items.capacity() };
+            slots * size_of::<String>() + items.iter().map(string).sum::<usize>()
+        }
+        // hashbrown: sloty plus jeden kontrolní bajt na slot
+        Value::Map(map) => {
+            let slots = if live { map.len() } else { map.capacity() };
+            slots * (size_of::<(String, String)>() + 1)
+                + map.iter().map(|(k, v)| string(k) + string(v)).sum::<usize>()
+        }
+        Value::Integer(_) | Value::Float(_) | Value::Bool(_) | Value::Null => 0,
+    }
+}
+
+fn entry_heap(path: &str, entry: &Entry, live: bool) -> u64 {
+    let slot = size_of::<(String, Entry)>() + 1;
+    let components = &entry.path_components;
+    let component_slots = if live { components.len() } else { components.capacity() };
+    let components_heap = component_slots * size_of::<String>()
+        + components.iter().map(|c| if live { c.len() } else { c.capacity() }).sum::<usize>();
+    (slot + path.len() + components_heap + value_heap(&entry.value, live)) as u64
+}
+
+// Bytes one stored entry occupies: its map slot (key + Entry inline), the key and all
+// heap allocations owned by the entry. Allocator overhead is not included.
+pub(crate) fn entry_size(path: &str, entry: &Entry) -> u64 {
+    entry_heap(path, entry, false)
+}
+
+// Same as entry_size, counting only bytes actually in use
+pub(crate) fn entry_live_size(path: &str, entry: &Entry) -> u64 {
+    entry_heap(path, entry, true)
+}
+
+impl Database {
+    pub fn set_memory_limit(&self, config: MemoryConfig) -> Result<(), DbError> {
+        *self.memory_config.write() = config;
+        self.eviction_pool.lock().clear();
+        // Nový limit může být pod aktuální spotřebou; vyřazené klíče musí zmizet i z disku,
+        // jinak by se po restartu vrátily
+        let evicted = self.evicted_keys.load(Ordering::SeqCst);
+        let result = self.make_room(None, 0);
+        if self.evicted_keys.load(Ordering::SeqCst) != evicted {
+            self.save_to_disk()?;
+        }
+        result
+    }
+
+    pub fn memory_stats(&self) -> MemoryStats {
+        let config = self.memory_config.read();
+        MemoryStats {
+            used_memory: self.used_memory.load(Ordering::SeqCst),
+            max_memory: config.max_memory,
+            policy: config.policy,
+            evicted_keys: self.evicted_keys.load(Ordering::SeqCst),
+            rejected_writes: self.rejected_writes.load(Ordering::SeqCst),
+        }
+    }
+
+    // Before writing `entry` to `path`: evicts other keys until it fits under max_memory.
+    // Writes that don't grow the database always pass.
+    pub(crate) fn reserve_memory(&self, path: &str, entry: &Entry) -> Result<(), DbError> {
+        if self.memory_config.read().max_memory == 0 {
+            return Ok(());
+        }
+        let old = self.data.get(path).map(|e| entry_size(path, &e)).unwrap_or(0);
+        let new = entry_size(path, entry);
+        if new <= old {
+            return Ok(());
+        }
+        self.make_room(Some(path), new - old)
+    }
+
+    // Bulk replace (restore, import_json): the new dataset replaces everything, so only
+    // its own size counts; rejected as a whole instead of evicting keys it brings in
+    pub(crate) fn check_replace_memory(&self, incoming: u64) -> Result<(), DbError> {
+        let max_memory = self.memory_config.read().max_memory;
+        if max_memory > 0 && incoming > max_memory {
+            self.rejected_writes.fetch_add(1, Ordering::SeqCst);
+            return Err(DbError::OutOfMemory);
+        }
+        Ok(())
+    }
+
+    fn make_room(&self, keep: Option<&str>, needed: u64) -> Result<(), DbError> {
+        self.cdc_batch(|| {
+            let config = self.memory_config.read().clone();
+            if config.max_memory == 0 {
+                return Ok(());
+            }
+
+            while self.used_memory.load(Ordering::SeqCst) + needed > config.max_memory {
+                let victim = match config.policy {
+                    EvictionPolicy::Noeviction => None,
+                    _ => self.next_victim(config.policy, keep),
+                };
+                let Some(victim) = victim else {
+                    self.rejected_writes.fetch_add(1, Ordering::SeqCst);
+                    return Err(DbError::OutOfMemory);
+                };
+                if self.commit_remove(ChangeKind::Evict, &victim, |_| true)?.is_some() {
+                    self.evicted_keys.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn next_victim(&self, policy: EvictionPolicy, keep: Option<&str>) -> Option<String> {
+        let volatile = matches!(policy, EvictionPolicy::VolatileLru | EvictionPolicy::VolatileTtl);
+        let eligible = |path: &str, entry: &Entry| Some(path) != keep && (!volatile || entry.expiry.is_some());
+
+        let mut pool = self.eviction_pool.lock();
+        for refilled in [false, true] {
+            if refilled {
+                *pool = self.eviction_candidates(policy, &eligible);
+            }
+            // Kandidát z dřívějšího průchodu mohl mezitím zmizet nebo se změnit
+            while let Some(path) = pool.pop() {
+                if self.data.get(&path).map(|e| eligible(&path, e.value())).unwrap_or(false) {
+                    return Some(path);
+                }
+            }
+        }
+        None
+    }
+
+    // Best candidates last, so the pool is consumed with pop()
+    fn eviction_candidates(&self, policy: EvictionPolicy, eligible: &dyn Fn(&str, &Entry) -> bool) -> Vec<String> {
+        let now = now_ms();
+        let mut scored: Vec<((u64, u64), String)> = self.data.iter()
+            .filter(|e| eligible(e.key(), e.value()))
+            .map(|e| {
+                let entry = e.value();
+                let last = entry.access.last_ms.load(Ordering::Relaxed);
+                let score = match policy {
+                    EvictionPolicy::AllkeysLfu => (entry.access.frequency(now), last),
+                    EvictionPolicy::VolatileTtl => (entry.expiry.unwrap_or(u64::MAX), last),
+                    _ => (last, 0),
+                };
+                (score, e.key().clone())
+            })
+            .collect();
+
+        if scored.len() > EVICTION_POOL {
+            scored.select_nth_unstable(EVICTION_POOL);
+            scored.truncate(EVICTION_POOL);
+        }
+        scored.sort_unstable_by(|a, b| b.cmp(a));
+        scored.into_iter().map(|(_, path)| path).collect()
+    }
+}
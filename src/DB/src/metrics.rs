@@ -0,0 +1,412 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io::{self, ErrorKind, Read, Write as _};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::scheduler::DatabasePtr;
+use crate::slowlog::ResultSize;
+use crate::{Database, DbError};
+
+// Jak často listener kontroluje nová spojení a zastavení
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(20);
+// Scrapes are answered one at a time, so a stuck client holds the listener at most this long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+// Horní meze bucketů v mikrosekundách, exportují se v sekundách
+const BUCKET_BOUNDS_US: [u64; 16] = [
+    10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000, 1_000_000,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Op {
+    Get,
+    Set,
+    Delete,
+    Increment,
+    SetExpiry,
+    RemoveExpiry,
+    Ttl,
+    Exists,
+    FindByPath,
+    ListDirectory,
+    DeleteByPattern,
+    Query,
+    Search,
+    Aggregate,
+    // Backups, restores, imports and exports of the whole database or a pattern
+    Backup,
+    Restore,
+    Import,
+    Export,
+    // Persistence
+    Persist,
+    Compact,
+}
+
+const OPS: [Op; 20] = [
+    Op::Get,
+    Op::Set,
+    Op::Delete,
+    Op::Increment,
+    Op::SetExpiry,
+    Op::RemoveExpiry,
+    Op::Ttl,
+    Op::Exists,
+    Op::FindByPath,
+    Op::ListDirectory,
+    Op::DeleteByPattern,
+    Op::Query,
+    Op::Search,
+    Op::Aggregate,
+    Op::Backup,
+    Op::Restore,
+    Op::Import,
+    Op::Export,
+    Op::Persist,
+    Op::Compact,
+];
+
+impl Op {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Op::Get => "get",
+            Op::Set => "set",
+            Op::Delete => "delete",
+            Op::Increment => "increment",
+            Op::SetExpiry => "set_expiry",
+            Op::RemoveExpiry => "remove_expiry",
+            Op::Ttl => "ttl",
+            Op::Exists => "exists",
+            Op::FindByPath => "find_by_path",
+            Op::ListDirectory => "list_directory",
+            Op::DeleteByPattern => "delete_by_pattern",
+            Op::Query => "query",
+            Op::Search => "search",
+            Op::Aggregate => "aggregate",
+            Op::Backup => "backup",
+            Op::Restore => "restore",
+            Op::Import => "import",
+            Op::Export => "export",
+            Op::Persist => "persist",
+            Op::Compact => "compact",
+        }
+    }
+
+    fn is_persistence(self) -> bool {
+        matches!(self, Op::Persist | Op::Compact)
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    // Bucket i počítá hodnoty <= BUCKET_BOUNDS_US[i], poslední je +Inf
+    buckets: [AtomicU64; BUCKET_BOUNDS_US.len() + 1],
+    count: AtomicU64,
+    sum_us: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, elapsed: Duration) {
+        let us = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = BUCKET_BOUNDS_US.partition_point(|&bound| bound < us);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(us, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> LatencyHistogram {
+        let mut cumulative = 0;
+        let buckets = BUCKET_BOUNDS_US.iter()
+            .zip(&self.buckets)
+            .map(|(&bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                HistogramBucket {
+                    le: bound as f64 / 1_000_000.0,
+                    count: cumulative,
+                }
+            })
+            .collect();
+        LatencyHistogram {
+            count: self.count.load(Ordering::Relaxed),
+            sum_seconds: self.sum_us.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+            buckets,
+        }
+    }
+}
+
+// Operation counters and latencies, shared by all callers of one Database
+#[derive(Default)]
+pub(crate) struct OpMetrics {
+    histograms: [Histogram; OPS.len()],
+    hits: AtomicU64,
+    misses: AtomicU64,
+    expirations: AtomicU64,
+}
+
+impl OpMetrics {
+    pub(crate) fn record(&self, op: Op, elapsed: Duration) {
+        self.histograms[op as usize].observe(elapsed);
+    }
+
+    pub(crate) fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn expired(&self) {
+        self.expirations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn count(&self, op: Op) -> u64 {
+        self.histograms[op as usize].count.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    // Upper bound in seconds
+    pub le: f64,
+    // Observations <= le (cumulative, as in Prometheus)
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    pub count: u64,
+    pub sum_seconds: f64,
+    pub buckets: Vec<HistogramBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Metrics {
+    pub gets: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub sets: u64,
+    pub deletes: u64,
+    // Keys removed because their TTL ran out
+    pub expirations: u64,
+    pub evictions: u64,
+    pub keys: usize,
+    pub keys_with_expiry: usize,
+    pub used_memory: u64,
+    pub disk_bytes: u64,
+    pub revision: u64,
+    // Latency per Database method
+    pub operations: BTreeMap<String, LatencyHistogram>,
+    // save_to_disk and compaction times
+    pub persistence: BTreeMap<String, LatencyHistogram>,
+}
+
+fn write_histogram(out: &mut String, name: &str, label: &str, histogram: &LatencyHistogram) {
+    for bucket in &histogram.buckets {
+        let _ = writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, label, bucket.le, bucket.count);
+    }
+    let _ = writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, label, histogram.count);
+    let _ = writeln!(out, "{}_sum{{{}}} {}", name, label, histogram.sum_seconds);
+    let _ = writeln!(out, "{}_count{{{}}} {}", name, label, histogram.count);
+}
+
+impl Metrics {
+    // Prometheus text exposition format (version 0.0.4)
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let counters = [
+            ("rust_db_keyspace_hits_total", "Lookups that found a live key", self.hits),
+            ("rust_db_keyspace_misses_total", "Lookups of missing or expired keys", self.misses),
+            ("rust_db_expired_keys_total", "Keys removed because their TTL ran out", self.expirations),
+            ("rust_db_evicted_keys_total", "Keys removed by the maxmemory policy", self.evictions),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, value);
+        }
+
+        let gauges = [
+            ("rust_db_keys", "Keys in the database, including expired ones not yet removed", self.keys as u64),
+            ("rust_db_keys_with_expiry", "Keys with a TTL", self.keys_with_expiry as u64),
+            ("rust_db_used_memory_bytes", "Bytes used by stored entries", self.used_memory),
+            ("rust_db_disk_bytes", "Size of the storage files", self.disk_bytes),
+            ("rust_db_revision", "Latest revision", self.revision),
+        ];
+        for (name, help, value) in gauges {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge\n{} {}", name, help, name, name, value);
+        }
+
+        let name = "rust_db_operation_duration_seconds";
+        let _ = writeln!(out, "# HELP {} Duration of Database operations\n# TYPE {} histogram", name, name);
+        for (op, histogram) in &self.operations {
+            write_histogram(&mut out, name, &format!("operation=\"{}\"", op), histogram);
+        }
+
+        let name = "rust_db_persistence_duration_seconds";
+        let _ = writeln!(out, "# HELP {} Duration of writes to the storage files\n# TYPE {} histogram", name, name);
+        for (kind, histogram) in &self.persistence {
+            write_histogram(&mut out, name, &format!("kind=\"{}\"", kind), histogram);
+        }
+        out
+    }
+}
+
+impl Database {
+    // Runs one Database operation on `path`: times it for the metrics and the slowlog
+    // and traces it through the `log` crate
+    pub(crate) fn observe<T: ResultSize>(&self, op: Op, path: &str, f: impl FnOnce() -> T) -> T {
+        log::trace!(target: "rust_db", "{} path={:?} started", op.name(), path);
+        let started = Instant::now();
+        let result = f();
+        let elapsed = started.elapsed();
+        self.metrics.record(op, elapsed);
+        self.slowlog.record(op.name(), path, elapsed, &result);
+        log::debug!(
+            target: "rust_db",
+            "{} path={:?} duration_us={} result_size={} error={:?}",
+            op.name(), path, elapsed.as_micros(), result.result_size(), result.error().map(|e| e.to_string())
+        );
+        result
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        let mut operations = BTreeMap::new();
+        let mut persistence = BTreeMap::new();
+        for (op, histogram) in OPS.iter().zip(&self.metrics.histograms) {
+            let target = if op.is_persistence() { &mut persistence } else { &mut operations };
+            target.insert(op.name().to_string(), histogram.snapshot());
+        }
+
+        Metrics {
+            gets: self.metrics.count(Op::Get),
+            hits: self.metrics.hits.load(Ordering::Relaxed),
+            misses: self.metrics.misses.load(Ordering::Relaxed),
+            sets: self.metrics.count(Op::Set),
+            deletes: self.metrics.count(Op::Delete),
+            expirations: self.metrics.expirations.load(Ordering::Relaxed),
+            evictions: self.evicted_keys.load(Ordering::SeqCst),
+            keys: self.data.len(),
+            keys_with_expiry: self.data.iter().filter(|e| e.expiry.is_some()).count(),
+            used_memory: self.used_memory.load(Ordering::SeqCst),
+            disk_bytes: self.disk_usage().total_bytes,
+            revision: self.revision(),
+            operations,
+            persistence,
+        }
+    }
+
+    pub fn prometheus_metrics(&self) -> String {
+        self.metrics().to_prometheus()
+    }
+    // Safety: same contract as schedule_backups, the database must not move until it is
+    // dropped; only offered to FFI callers
+    pub(crate) unsafe fn serve_metrics(&self, addr: &str) -> Result<SocketAddr, DbError> {
+        let mut server = self.metrics_server.lock();
+        *server = None;
+        let started = MetricsServer::spawn(DatabasePtr(self as *const Database), addr)?;
+        let addr = started.addr;
+        *server = Some(started);
+        log::info!(target: "rust_db", "metrics listening on http://{}/metrics", addr);
+        Ok(addr)
+    }
+
+    pub fn stop_metrics_server(&self) {
+        *self.metrics_server.lock() = None;
+    }
+}
+
+// Reads the request head and answers GET /metrics, anything else gets 404 or 405
+fn respond(database: &Database, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let mut head = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_BYTES {
+            return Err(io::Error::new(ErrorKind::InvalidData, "request head too long"));
+        }
+        let read = stream.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(());
+        }
+        head.extend_from_slice(&chunk[..read]);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or("").split(' ');
+    let method = request_line.next().unwrap_or("");
+    let path = request_line.next().unwrap_or("").split('?').next().unwrap_or("");
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => ("200 OK", "text/plain; version=0.0.4; charset=utf-8", database.prometheus_metrics()),
+        ("GET", _) => ("404 Not Found", "text/plain; charset=utf-8", "Not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain; charset=utf-8", "Method not allowed\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body
+    );
+    stream.write_all(response.as_bytes())
+}
+
+// HTTP listener serving GET /metrics for Prometheus; stops and joins on drop
+pub(crate) struct MetricsServer {
+    addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    fn spawn(db: DatabasePtr, addr: &str) -> Result<Self, DbError> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let thread_stopped = stopped.clone();
+        let handle = thread::Builder::new()
+            .name("rust-db-metrics".to_string())
+            .spawn(move || {
+                let db = &db;
+                while !thread_stopped.load(Ordering::SeqCst) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            // SAFETY: Database::drop stops this thread before the database goes away
+                            let database = unsafe { &*db.0 };
+                            if let Err(e) = respond(database, stream) {
+                                log::debug!(target: "rust_db", "metrics request failed: {}", e);
+                            }
+                        }
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_INTERVAL),
+                        Err(e) => {
+                            log::warn!(target: "rust_db", "metrics accept failed: {}", e);
+                            thread::sleep(ACCEPT_INTERVAL);
+                        }
+                    }
+                }
+            })?;
+
+        Ok(MetricsServer {
+            addr,
+            stopped,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+
+use crate::{Database, DbError, DbStats, ReaperConfig, ReaperStatus, StorageConfig};
+
+pub const DEFAULT_NAMESPACE: &str = "default";
+const MANIFEST_FILE: &str = "namespaces.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NamespaceConfig {
+    pub storage: StorageConfig,
+    pub reaper: ReaperConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamespaceStats {
+    pub name: String,
+    pub current: bool,
+    // Storage file, empty for in-memory namespaces
+    pub path: String,
+    pub stats: DbStats,
+    pub reaper: Option<ReaperStatus>,
+}
+
+fn validate_name(name: &str) -> Result<(), DbError> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(DbError::System(format!("Invalid namespace name {:?}, use [A-Za-z0-9_-]", name)))
+    }
+}
+
+// Named logical databases in one process, like numbered Redis databases. Each namespace
+// is a separate Database with its own storage file <dir>/<name>.db and TTL reaper; the
+// list of namespaces and their configs is kept in <dir>/namespaces.json.
+pub struct Namespaces {
+    // None = namespaces jen v paměti
+    dir: Option<PathBuf>,
+    namespaces: RwLock<BTreeMap<String, Arc<Database>>>,
+    configs: Mutex<BTreeMap<String, NamespaceConfig>>,
+    current: RwLock<String>,
+}
+
+impl Namespaces {
+    // Reopens every namespace recorded in the directory, "default" always exists
+    pub fn open(dir: &str) -> Result<Self, DbError> {
+        let dir = PathBuf::from(dir);
+        fs::create_dir_all(&dir)?;
+        let manifest = dir.join(MANIFEST_FILE);
+        let configs: BTreeMap<String, NamespaceConfig> = match fs::read_to_string(&manifest) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let namespaces = Namespaces {
+            dir: Some(dir),
+            namespaces: RwLock::new(BTreeMap::new()),
+            configs: Mutex::new(BTreeMap::new()),
+            current: RwLock::new(DEFAULT_NAMESPACE.to_string()),
+        };
+        for (name, config) in configs {
+            namespaces.open_namespace(&name, config)?;
+        }
+        namespaces.open_namespace(DEFAULT_NAMESPACE, NamespaceConfig::default())?;
+        Ok(namespaces)
+    }
+
+    pub fn in_memory() -> Self {
+        let namespaces = Namespaces {
+            dir: None,
+            namespaces: RwLock::new(BTreeMap::new()),
+            configs: Mutex::new(BTreeMap::new()),
+            current: RwLock::new(DEFAULT_NAMESPACE.to_string()),
+        };
+        // Otevření paměťové databáze nemůže selhat
+        let _ = namespaces.open_namespace(DEFAULT_NAMESPACE, NamespaceConfig::default());
+        namespaces
+    }
+
+    fn storage_path(&self, name: &str) -> String {
+        match &self.dir {
+            Some(dir) => dir.join(format!("{}.db", name)).to_string_lossy().into_owned(),
+            None => String::new(),
+        }
+    }
+
+    fn save_manifest(&self, configs: &BTreeMap<String, NamespaceConfig>) -> Result<(), DbError> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let tmp_path = dir.join(format!("{}.tmp", MANIFEST_FILE));
+        fs::write(&tmp_path, serde_json::to_string_pretty(configs)?)?;
+        fs::rename(&tmp_path, dir.join(MANIFEST_FILE))?;
+        Ok(())
+    }
+
+    // Creates the namespace, or returns it unchanged (with its original config) if it exists
+    pub fn open_namespace(&self, name: &str, config: NamespaceConfig) -> Result<Arc<Database>, DbError> {
+        validate_name(name)?;
+        // configs drží zámek po celou dobu, dvě souběžná otevření stejného jména se neprolnou
+        let mut configs = self.configs.lock();
+        if let Some(db) = self.namespaces.read().get(name) {
+            return Ok(db.clone());
+        }
+
+        let db = match self.dir {
+            Some(_) => Database::open(&self.storage_path(name), &config.storage)?,
+            None => Database::in_memory(),
+        };
+        let db = Arc::new(db);
+        // SAFETY: the database lives in the Arc and does not move
+        unsafe { db.start_reaper(config.reaper.clone()) }?;
+
+        configs.insert(name.to_string(), config);
+        self.save_manifest(&configs)?;
+        self.namespaces.write().insert(name.to_string(), db.clone());
+        log::info!(target: "rust_db", "opened namespace {:?}", name);
+        Ok(db)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Database>> {
+        self.namespaces.read().get(name).cloned()
+    }
+
+    pub fn switch(&self, name: &str) -> Result<Arc<Database>, DbError> {
+        // Zámky vždy v pořadí current -> namespaces
+        let mut current = self.current.write();
+        let db = self.get(name)
+            .ok_or_else(|| DbError::System(format!("Namespace {:?} does not exist", name)))?;
+        *current = name.to_string();
+        Ok(db)
+    }
+
+    pub fn current(&self) -> Arc<Database> {
+        let current = self.current.read();
+        self.namespaces.read()[current.as_str()].clone()
+    }
+
+    pub fn current_name(&self) -> String {
+        self.current.read().clone()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.namespaces.read().keys().cloned().collect()
+    }
+
+    // Removes the namespace and its files. Refused while a handle from get(), switch() or
+    // open_namespace() is still alive, writes through it would recreate the deleted files.
+    pub fn drop_namespace(&self, name: &str) -> Result<(), DbError> {
+        if name == DEFAULT_NAMESPACE {
+            return Err(DbError::System("The default namespace can't be dropped".to_string()));
+        }
+        let mut configs = self.configs.lock();
+        let db = {
+            let mut current = self.current.write();
+            // Držený zápisový zámek brání get() v rozdání dalšího handle
+            let mut namespaces = self.namespaces.write();
+            let db = namespaces.get(name)
+                .ok_or_else(|| DbError::System(format!("Namespace {:?} does not exist", name)))?;
+            if Arc::strong_count(db) > 1 {
+                return Err(DbError::System(format!("Namespace {:?} is still in use", name)));
+            }
+            let db = namespaces.remove(name).unwrap();
+            if current.as_str() == name {
+                *current = DEFAULT_NAMESPACE.to_string();
+            }
+            db
+        };
+        db.stop_reaper();
+
+        configs.remove(name);
+        self.save_manifest(&configs)?;
+        if self.dir.is_some() {
+            let path = self.storage_path(name);
+            for suffix in ["", ".tmp", ".cdc", ".cdc.state"] {
+                match fs::remove_file(format!("{}{}", path, suffix)) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        log::info!(target: "rust_db", "dropped namespace {:?}", name);
+        Ok(())
+    }
+
+    pub fn stats(&self) -> Vec<NamespaceStats> {
+        let current = self.current_name();
+        self.namespaces.read().iter()
+            .map(|(name, db)| NamespaceStats {
+                name: name.clone(),
+                current: *name == current,
+                path: self.storage_path(name),
+                stats: db.get_stats(),
+                reaper: db.reaper_status(),
+            })
+            .collect()
+    }
+}
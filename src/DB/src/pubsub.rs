@@ -0,0 +1,311 @@
+use std::collections::BTreeSet;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+
+use crate::{ChangeEvent, Database, DbError, Entry};
+
+// Jak často vlákna kontrolují nové spojení, příkazy, události a zastavení
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+// Unfinished commands longer than this close the connection
+const MAX_COMMAND_BYTES: usize = 64 * 1024;
+// Each client has its own thread; clients beyond this get an error and are disconnected
+pub const MAX_PUBSUB_CLIENTS: usize = 128;
+
+// Parses one RESP array of bulk strings; Ok(None) while the command is incomplete
+fn parse_command(buffer: &[u8]) -> Result<Option<(Vec<String>, usize)>, String> {
+    fn line(buffer: &[u8], pos: usize) -> Option<(&str, usize)> {
+        let rest = buffer.get(pos..)?;
+        let end = rest.windows(2).position(|w| w == b"\r\n")?;
+        Some((std::str::from_utf8(&rest[..end]).ok()?, pos + end + 2))
+    }
+
+    let Some((header, mut pos)) = line(buffer, 0) else { return Ok(None) };
+    let count: usize = header.strip_prefix('*')
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(|| format!("invalid command header '{}'", header))?;
+
+    let mut args = Vec::new();
+    for _ in 0..count {
+        let Some((header, next)) = line(buffer, pos) else { return Ok(None) };
+        let len: usize = header.strip_prefix('$')
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| format!("invalid argument header '{}'", header))?;
+        let Some(arg) = next.checked_add(len).and_then(|end| buffer.get(next..end)) else { return Ok(None) };
+        if buffer.len() < next + len + 2 {
+            return Ok(None);
+        }
+        args.push(String::from_utf8_lossy(arg).into_owned());
+        pos = next + len + 2;
+    }
+    Ok(Some((args, pos)))
+}
+
+fn bulk(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(format!("${}\r\n{}\r\n", value.len(), value).as_bytes());
+}
+
+// One client connection; subscriptions are per connection as in Redis
+struct Connection {
+    stream: TcpStream,
+    receiver: broadcast::Receiver<ChangeEvent>,
+    // Přesné cesty (SUBSCRIBE) a vzory cest (PSUBSCRIBE)
+    channels: BTreeSet<String>,
+    patterns: BTreeSet<String>,
+    buffer: Vec<u8>,
+}
+
+impl Connection {
+    fn subscriptions(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    fn reply_subscription(&self, out: &mut Vec<u8>, kind: &str, name: &str) {
+        out.extend_from_slice(b"*3\r\n");
+        bulk(out, kind);
+        bulk(out, name);
+        out.extend_from_slice(format!(":{}\r\n", self.subscriptions()).as_bytes());
+    }
+
+    // Returns false when the client asked to close the connection
+    fn execute(&mut self, args: &[String], out: &mut Vec<u8>) -> bool {
+        let Some(name) = args.first() else { return true };
+        let names = &args[1..];
+        match name.to_ascii_uppercase().as_str() {
+            "SUBSCRIBE" | "PSUBSCRIBE" if names.is_empty() => {
+                out.extend_from_slice(format!("-ERR wrong number of arguments for '{}'\r\n", name).as_bytes());
+            }
+            "SUBSCRIBE" => {
+                for channel in names {
+                    self.channels.insert(channel.clone());
+                    self.reply_subscription(out, "subscribe", channel);
+                }
+            }
+            "PSUBSCRIBE" => {
+                for pattern in names {
+                    if Entry::parse_path(pattern).is_err() {
+                        out.extend_from_slice(format!("-ERR invalid pattern '{}'\r\n", pattern).as_bytes());
+                        continue;
+                    }
+                    self.patterns.insert(pattern.clone());
+                    self.reply_subscription(out, "psubscribe", pattern);
+                }
+            }
+            kind @ ("UNSUBSCRIBE" | "PUNSUBSCRIBE") => {
+                let (set, reply) = if kind == "UNSUBSCRIBE" {
+                    (&mut self.channels, "unsubscribe")
+                } else {
+                    (&mut self.patterns, "punsubscribe")
+                };
+                // Bez argumentů se ruší všechny odběry daného druhu
+                let removed: Vec<String> = if names.is_empty() {
+                    std::mem::take(set).into_iter().collect()
+                } else {
+                    names.iter().filter(|n| set.remove(*n)).cloned().collect()
+                };
+                for name in &removed {
+                    self.reply_subscription(out, reply, name);
+                }
+                if removed.is_empty() {
+                    out.extend_from_slice(b"*3\r\n");
+                    bulk(out, reply);
+                    out.extend_from_slice(format!("$-1\r\n:{}\r\n", self.subscriptions()).as_bytes());
+                }
+            }
+            "PING" => out.extend_from_slice(b"+PONG\r\n"),
+            "QUIT" => {
+                out.extend_from_slice(b"+OK\r\n");
+                return false;
+            }
+            _ => out.extend_from_slice(format!("-ERR only pub/sub commands are supported, got '{}'\r\n", name).as_bytes()),
+        }
+        true
+    }
+
+    fn publish(&self, event: &ChangeEvent, out: &mut Vec<u8>) {
+        let mut payload = None;
+        let mut payload = || payload.get_or_insert_with(|| serde_json::to_string(event).unwrap_or_default()).clone();
+
+        if self.channels.contains(&event.path) {
+            out.extend_from_slice(b"*3\r\n");
+            bulk(out, "message");
+            bulk(out, &event.path);
+            bulk(out, &payload());
+        }
+        for pattern in &self.patterns {
+            let components: Vec<String> = pattern.split('/').map(|s| s.to_string()).collect();
+            if Entry::path_matches(&event.path, &components) {
+                out.extend_from_slice(b"*4\r\n");
+                bulk(out, "pmessage");
+                bulk(out, pattern);
+                bulk(out, &event.path);
+                bulk(out, &payload());
+            }
+        }
+    }
+
+    fn run(mut self, stopped: &AtomicBool) -> std::io::Result<()> {
+        self.stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        let mut chunk = [0u8; 4096];
+        while !stopped.load(Ordering::SeqCst) {
+            let mut out = Vec::new();
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Ok(()),
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(e) => return Err(e),
+            }
+
+            loop {
+                match parse_command(&self.buffer) {
+                    Ok(Some((args, used))) => {
+                        self.buffer.drain(..used);
+                        if !self.execute(&args, &mut out) {
+                            return self.stream.write_all(&out);
+                        }
+                    }
+                    Ok(None) if self.buffer.len() > MAX_COMMAND_BYTES => {
+                        out.extend_from_slice(b"-ERR command too long\r\n");
+                        return self.stream.write_all(&out);
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        out.extend_from_slice(format!("-ERR {}\r\n", e).as_bytes());
+                        return self.stream.write_all(&out);
+                    }
+                }
+            }
+
+            loop {
+                match self.receiver.try_recv() {
+                    Ok(event) => self.publish(&event, &mut out),
+                    // Klient nestíhá; dozví se, kolik událostí přišlo nazmar
+                    Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                        out.extend_from_slice(b"*2\r\n");
+                        bulk(&mut out, "lagged");
+                        out.extend_from_slice(format!(":{}\r\n", skipped).as_bytes());
+                    }
+                    Err(broadcast::error::TryRecvError::Empty) => break,
+                    Err(broadcast::error::TryRecvError::Closed) => return Ok(()),
+                }
+            }
+            if !out.is_empty() {
+                self.stream.write_all(&out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Held by a client thread, frees the client's place when the thread ends
+struct ClientSlot(Arc<AtomicUsize>);
+
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// RESP listener for SUBSCRIBE/PSUBSCRIBE on key changes; stops with the database
+pub(crate) struct PubsubServer {
+    addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PubsubServer {
+    fn spawn(addr: &str, sender: broadcast::Sender<ChangeEvent>) -> Result<Self, DbError> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let thread_stopped = stopped.clone();
+        let handle = thread::Builder::new()
+            .name("rust-db-pubsub".to_string())
+            .spawn(move || {
+                // Spojení drží jen kopii senderu, databázi samotnou nepotřebují
+                let clients = Arc::new(AtomicUsize::new(0));
+                while !thread_stopped.load(Ordering::SeqCst) {
+                    let stream = match listener.accept() {
+                        Ok((stream, _)) => stream,
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                            thread::sleep(POLL_INTERVAL);
+                            continue;
+                        }
+                        Err(e) => {
+                            log::warn!(target: "rust_db", "pub/sub accept failed: {}", e);
+                            thread::sleep(POLL_INTERVAL);
+                            continue;
+                        }
+                    };
+                    if clients.fetch_add(1, Ordering::SeqCst) >= MAX_PUBSUB_CLIENTS {
+                        clients.fetch_sub(1, Ordering::SeqCst);
+                        let mut stream = stream;
+                        let _ = stream.set_nonblocking(false)
+                            .and_then(|_| stream.set_write_timeout(Some(POLL_INTERVAL)))
+                            .and_then(|_| stream.write_all(b"-ERR max number of clients reached\r\n"));
+                        continue;
+                    }
+                    let slot = ClientSlot(clients.clone());
+                    let connection = Connection {
+                        stream,
+                        receiver: sender.subscribe(),
+                        channels: BTreeSet::new(),
+                        patterns: BTreeSet::new(),
+                        buffer: Vec::new(),
+                    };
+                    let stopped = thread_stopped.clone();
+                    let spawned = thread::Builder::new()
+                        .name("rust-db-pubsub-client".to_string())
+                        .spawn(move || {
+                            let _slot = slot;
+                            if let Err(e) = connection.stream.set_nonblocking(false).and_then(|_| connection.run(&stopped)) {
+                                log::debug!(target: "rust_db", "pub/sub connection closed: {}", e);
+                            }
+                        });
+                    if let Err(e) = spawned {
+                        log::warn!(target: "rust_db", "pub/sub connection thread failed: {}", e);
+                    }
+                }
+            })?;
+
+        Ok(PubsubServer {
+            addr,
+            stopped,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for PubsubServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Database {
+    // Starts the pub/sub listener (e.g. "127.0.0.1:6380", port 0 picks a free one) and
+    // returns its address; a running listener is replaced
+    pub fn serve_pubsub(&self, addr: &str) -> Result<SocketAddr, DbError> {
+        let mut pubsub = self.pubsub.lock();
+        *pubsub = None;
+        let server = PubsubServer::spawn(addr, self.changes.sender())?;
+        let addr = server.addr;
+        *pubsub = Some(server);
+        log::info!(target: "rust_db", "pub/sub listening on {}", addr);
+        Ok(addr)
+    }
+
+    pub fn stop_pubsub(&self) {
+        *self.pubsub.lock() = None;
+    }
+}
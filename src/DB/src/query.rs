@@ -0,0 +1,519 @@
+use std::cmp::Ordering;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use glob::Pattern;
+use serde::Serialize;
+
+use crate::metrics;
+use crate::{Database, DbError, Entry, Value};
+
+// Query syntax:
+//   <pattern> [WHERE <expr>] [ORDER BY <field> [ASC|DESC]] [LIMIT <n>]
+//
+// e.g. `article/*/meta WHERE status = published AND updated_at > 2024-01-01 ORDER BY updated_at DESC LIMIT 20`
+//
+// Fields: value, type, ttl, path, created_at, updated_at; any other identifier
+// is looked up as a field of a Map value.
+// Operators: = != < <= > >= ~ (glob match), combined with AND, OR, NOT and parentheses.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryResult {
+    pub path: String,
+    pub value: Value,
+    pub ttl: Option<u64>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Field {
+    Value,
+    Type,
+    Ttl,
+    Path,
+    CreatedAt,
+    UpdatedAt,
+    Map(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Glob,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Scalar {
+    Str(String),
+    Num(f64),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Compare(Field, Op, Scalar),
+    Match(Field, Pattern),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+#[derive(Debug)]
+pub struct Query {
+    pattern: Vec<String>,
+    filter: Option<Expr>,
+    order_by: Option<(Field, bool)>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(Op),
+    LParen,
+    RParen,
+}
+
+fn query_error(msg: impl Into<String>) -> DbError {
+    DbError::Query(msg.into())
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, DbError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => s.push(escaped),
+                            None => return Err(query_error("unterminated string")),
+                        },
+                        Some(ch) if ch == c => break,
+                        Some(ch) => s.push(ch),
+                        None => return Err(query_error("unterminated string")),
+                    }
+                }
+                tokens.push(Token::Quoted(s));
+            }
+            '=' | '!' | '<' | '>' | '~' => {
+                chars.next();
+                let followed_by_eq = chars.peek() == Some(&'=');
+                let op = match (c, followed_by_eq) {
+                    ('=', _) => Op::Eq,
+                    ('!', true) => Op::Ne,
+                    ('<', true) => Op::Le,
+                    ('<', false) => Op::Lt,
+                    ('>', true) => Op::Ge,
+                    ('>', false) => Op::Gt,
+                    ('~', _) => Op::Glob,
+                    _ => return Err(query_error("unexpected '!'")),
+                };
+                if followed_by_eq && c != '~' {
+                    chars.next();
+                }
+                tokens.push(Token::Op(op));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_whitespace() || "()=!<>~\"'".contains(ch) {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), DbError> {
+        if self.peek_keyword(keyword) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(query_error(format!("expected {}", keyword)))
+        }
+    }
+
+    fn parse_query(&mut self) -> Result<Query, DbError> {
+        let pattern = match self.next() {
+            Some(Token::Word(w)) | Some(Token::Quoted(w)) => w,
+            _ => return Err(query_error("expected path pattern")),
+        };
+        Entry::parse_path(&pattern)?;
+
+        let mut query = Query {
+            pattern: pattern.split('/').map(|s| s.to_string()).collect(),
+            filter: None,
+            order_by: None,
+            limit: None,
+        };
+
+        if self.peek_keyword("where") {
+            self.pos += 1;
+            query.filter = Some(self.parse_or()?);
+        }
+
+        if self.peek_keyword("order") {
+            self.pos += 1;
+            self.expect_keyword("by")?;
+            let field = self.parse_field()?;
+            let descending = if self.peek_keyword("desc") {
+                self.pos += 1;
+                true
+            } else {
+                if self.peek_keyword("asc") {
+                    self.pos += 1;
+                }
+                false
+            };
+            query.order_by = Some((field, descending));
+        }
+
+        if self.peek_keyword("limit") {
+            self.pos += 1;
+            query.limit = match self.next() {
+                Some(Token::Word(w)) => Some(w.parse().map_err(|_| query_error("invalid limit"))?),
+                _ => return Err(query_error("expected number after LIMIT")),
+            };
+        }
+
+        match self.peek() {
+            None => Ok(query),
+            Some(token) => Err(query_error(format!("unexpected token {:?}", token))),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, DbError> {
+        let mut expr = self.parse_and()?;
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, DbError> {
+        let mut expr = self.parse_unary()?;
+        while self.peek_keyword("and") {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, DbError> {
+        if self.peek_keyword("not") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let expr = self.parse_or()?;
+            if self.next() != Some(Token::RParen) {
+                return Err(query_error("expected ')'"));
+            }
+            return Ok(expr);
+        }
+
+        self.parse_predicate()
+    }
+
+    fn parse_predicate(&mut self) -> Result<Expr, DbError> {
+        let field = self.parse_field()?;
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            _ => return Err(query_error("expected comparison operator")),
+        };
+        let literal = match self.next() {
+            Some(Token::Quoted(s)) => Scalar::Str(s),
+            Some(Token::Word(w)) => parse_literal(&w),
+            _ => return Err(query_error("expected value")),
+        };
+
+        if op == Op::Glob {
+            let Scalar::Str(pattern) = literal else {
+                return Err(query_error("'~' expects a string pattern"));
+            };
+            let pattern = Pattern::new(&pattern).map_err(|e| query_error(e.to_string()))?;
+            return Ok(Expr::Match(field, pattern));
+        }
+
+        let literal = match (&field, literal) {
+            (Field::CreatedAt | Field::UpdatedAt, Scalar::Str(s)) => Scalar::Num(parse_timestamp(&s)?),
+            (_, literal) => literal,
+        };
+
+        Ok(Expr::Compare(field, op, literal))
+    }
+
+    fn parse_field(&mut self) -> Result<Field, DbError> {
+        let name = match self.next() {
+            Some(Token::Word(w)) => w,
+            _ => return Err(query_error("expected field name")),
+        };
+
+        Ok(match name.to_ascii_lowercase().as_str() {
+            "value" => Field::Value,
+            "type" => Field::Type,
+            "ttl" => Field::Ttl,
+            "path" => Field::Path,
+            "created_at" => Field::CreatedAt,
+            "updated_at" => Field::UpdatedAt,
+            _ => Field::Map(name.strip_prefix("value.").unwrap_or(&name).to_string()),
+        })
+    }
+}
+
+fn parse_literal(word: &str) -> Scalar {
+    match word.to_ascii_lowercase().as_str() {
+        "true" => Scalar::Bool(true),
+        "false" => Scalar::Bool(false),
+        "null" => Scalar::Null,
+        _ => match word.parse::<f64>() {
+            Ok(n) => Scalar::Num(n),
+            Err(_) => Scalar::Str(word.to_string()),
+        },
+    }
+}
+
+fn parse_timestamp(s: &str) -> Result<f64, DbError> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.timestamp() as f64);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        if let Some(dt) = date.and_hms_opt(0, 0, 0) {
+            return Ok(dt.and_utc().timestamp() as f64);
+        }
+    }
+    Err(query_error(format!("invalid timestamp '{}'", s)))
+}
+
+pub(crate) fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::String(_) => "string",
+        Value::Integer(_) => "integer",
+        Value::Float(_) => "float",
+        Value::Bool(_) => "bool",
+        Value::Array(_) => "array",
+        Value::Map(_) => "map",
+        Value::Null => "null",
+    }
+}
+
+fn resolve(field: &Field, path: &str, entry: &Entry, now: u64) -> Option<Scalar> {
+    match field {
+        Field::Value => match &entry.value {
+            Value::String(s) => Some(Scalar::Str(s.clone())),
+            Value::Integer(n) => Some(Scalar::Num(*n as f64)),
+            Value::Float(f) => Some(Scalar::Num(*f)),
+            Value::Bool(b) => Some(Scalar::Bool(*b)),
+            Value::Null => Some(Scalar::Null),
+            Value::Array(_) | Value::Map(_) => None,
+        },
+        Field::Type => Some(Scalar::Str(type_name(&entry.value).to_string())),
+        Field::Ttl => Some(match entry.expiry {
+            Some(expiry) => Scalar::Num(expiry.saturating_sub(now) as f64),
+            None => Scalar::Null,
+        }),
+        Field::Path => Some(Scalar::Str(path.to_string())),
+        Field::CreatedAt => Some(Scalar::Num(entry.created_at.timestamp() as f64)),
+        Field::UpdatedAt => Some(Scalar::Num(entry.updated_at.timestamp() as f64)),
+        Field::Map(name) => match &entry.value {
+            Value::Map(map) => map.get(name).map(|s| Scalar::Str(s.clone())),
+            _ => None,
+        },
+    }
+}
+
+fn compare(left: &Scalar, right: &Scalar) -> Option<Ordering> {
+    match (left, right) {
+        (Scalar::Num(a), Scalar::Num(b)) => a.partial_cmp(b),
+        (Scalar::Str(a), Scalar::Num(b)) => a.parse::<f64>().ok()?.partial_cmp(b),
+        (Scalar::Num(a), Scalar::Str(b)) => a.partial_cmp(&b.parse::<f64>().ok()?),
+        (Scalar::Str(a), Scalar::Str(b)) => Some(a.cmp(b)),
+        (Scalar::Bool(a), Scalar::Bool(b)) => Some(a.cmp(b)),
+        (Scalar::Null, Scalar::Null) => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+// Total order for ORDER BY: numbers (including numeric strings) < strings < booleans
+// < null. compare() is only partial and would let sort_by panic or shuffle entries.
+fn sort_order(left: &Scalar, right: &Scalar) -> Ordering {
+    fn rank(scalar: &Scalar) -> (u8, Option<f64>) {
+        match scalar {
+            Scalar::Num(n) => (0, Some(*n)),
+            Scalar::Str(s) => match s.parse::<f64>() {
+                Ok(n) => (0, Some(n)),
+                Err(_) => (1, None),
+            },
+            Scalar::Bool(_) => (2, None),
+            Scalar::Null => (3, None),
+        }
+    }
+
+    let (left_rank, left_num) = rank(left);
+    let (right_rank, right_num) = rank(right);
+    left_rank.cmp(&right_rank).then_with(|| match (left, right, left_num, right_num) {
+        (_, _, Some(a), Some(b)) => a.total_cmp(&b),
+        (Scalar::Str(a), Scalar::Str(b), _, _) => a.cmp(b),
+        (Scalar::Bool(a), Scalar::Bool(b), _, _) => a.cmp(b),
+        _ => Ordering::Equal,
+    })
+}
+
+impl Expr {
+    fn eval(&self, path: &str, entry: &Entry, now: u64) -> bool {
+        match self {
+            Expr::Compare(field, op, literal) => {
+                let Some(actual) = resolve(field, path, entry, now) else {
+                    return *op == Op::Ne;
+                };
+                match compare(&actual, literal) {
+                    Some(ordering) => match op {
+                        Op::Eq => ordering == Ordering::Equal,
+                        Op::Ne => ordering != Ordering::Equal,
+                        Op::Lt => ordering == Ordering::Less,
+                        Op::Le => ordering != Ordering::Greater,
+                        Op::Gt => ordering == Ordering::Greater,
+                        Op::Ge => ordering != Ordering::Less,
+                        Op::Glob => false,
+                    },
+                    None => *op == Op::Ne,
+                }
+            }
+            Expr::Match(field, pattern) => match resolve(field, path, entry, now) {
+                Some(Scalar::Str(s)) => pattern.matches(&s),
+                Some(Scalar::Num(n)) => pattern.matches(&n.to_string()),
+                _ => false,
+            },
+            Expr::And(a, b) => a.eval(path, entry, now) && b.eval(path, entry, now),
+            Expr::Or(a, b) => a.eval(path, entry, now) || b.eval(path, entry, now),
+            Expr::Not(e) => !e.eval(path, entry, now),
+        }
+    }
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, DbError> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            pos: 0,
+        };
+        parser.parse_query()
+    }
+}
+
+impl Database {
+    pub fn query(&self, query: &str) -> Result<Vec<QueryResult>, DbError> {
+        self.observe(metrics::Op::Query, query, || self.execute_query(query))
+    }
+
+    fn execute_query(&self, query: &str) -> Result<Vec<QueryResult>, DbError> {
+        let query = Query::parse(query)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let mut matched: Vec<(Option<Scalar>, QueryResult)> = Vec::new();
+
+        for entry in self.data.iter() {
+            let (path, item) = (entry.key(), entry.value());
+            if !item.matches_pattern(&query.pattern) {
+                continue;
+            }
+            if item.expiry.map(|exp| exp < now).unwrap_or(false) {
+                continue;
+            }
+            if let Some(filter) = &query.filter {
+                if !filter.eval(path, item, now) {
+                    continue;
+                }
+            }
+
+            let sort_key = query.order_by.as_ref()
+                .and_then(|(field, _)| resolve(field, path, item, now));
+
+            matched.push((sort_key, QueryResult {
+                path: path.clone(),
+                value: item.value.clone(),
+                ttl: item.expiry.map(|exp| exp.saturating_sub(now)),
+                created_at: item.created_at,
+                updated_at: item.updated_at,
+            }));
+        }
+
+        match &query.order_by {
+            Some((_, descending)) => matched.sort_by(|(a, pa), (b, pb)| {
+                // Entries without the sort field always go last
+                let ordering = match (a, b) {
+                    (Some(a), Some(b)) => {
+                        let ordering = sort_order(a, b);
+                        if *descending { ordering.reverse() } else { ordering }
+                    }
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                };
+                ordering.then_with(|| pa.path.cmp(&pb.path))
+            }),
+            None => matched.sort_by(|(_, a), (_, b)| a.path.cmp(&b.path)),
+        }
+
+        let limit = query.limit.unwrap_or(usize::MAX);
+        Ok(matched.into_iter().take(limit).map(|(_, result)| result).collect())
+    }
+}
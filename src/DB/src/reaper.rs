@@ -0,0 +1,172 @@
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+
+use crate::scheduler::DatabasePtr;
+use crate::{ChangeKind, Database, DbError};
+
+// Expired keys are otherwise only removed when they are read
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReaperConfig {
+    // 0 = no background reaping
+    pub interval_ms: u64,
+    // Upper bound of keys removed per run, the rest waits for the next one
+    pub max_keys: usize,
+}
+
+impl Default for ReaperConfig {
+    fn default() -> Self {
+        ReaperConfig {
+            interval_ms: 1000,
+            max_keys: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReaperStatus {
+    pub interval_ms: u64,
+    pub max_keys: usize,
+    pub runs: u64,
+    pub expired_keys: u64,
+    pub last_run: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+struct ReaperShared {
+    stopped: Mutex<bool>,
+    wakeup: Condvar,
+    status: Mutex<ReaperStatus>,
+}
+
+// Background thread removing expired keys; stops and joins on drop
+pub(crate) struct TtlReaper {
+    shared: Arc<ReaperShared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TtlReaper {
+    fn spawn(db: DatabasePtr, config: ReaperConfig) -> Result<Self, DbError> {
+        let shared = Arc::new(ReaperShared {
+            stopped: Mutex::new(false),
+            wakeup: Condvar::new(),
+            status: Mutex::new(ReaperStatus {
+                interval_ms: config.interval_ms,
+                max_keys: config.max_keys,
+                ..Default::default()
+            }),
+        });
+
+        let thread_shared = shared.clone();
+        let interval = Duration::from_millis(config.interval_ms);
+        let handle = thread::Builder::new()
+            .name("rust-db-reaper".to_string())
+            .spawn(move || {
+                let db = &db;
+                let shared = thread_shared;
+                loop {
+                    let mut stopped = shared.stopped.lock();
+                    if !*stopped {
+                        shared.wakeup.wait_for(&mut stopped, interval);
+                    }
+                    if *stopped {
+                        break;
+                    }
+                    drop(stopped);
+
+                    // SAFETY: Database::drop stops this thread before the database goes away
+                    let database = unsafe { &*db.0 };
+                    let result = database.reap_expired(config.max_keys);
+
+                    let mut status = shared.status.lock();
+                    status.runs += 1;
+                    status.last_run = Some(Utc::now());
+                    match result {
+                        Ok(expired) => {
+                            status.expired_keys += expired as u64;
+                            status.last_error = None;
+                        }
+                        Err(e) => status.last_error = Some(e.to_string()),
+                    }
+                }
+            })?;
+
+        Ok(TtlReaper {
+            shared,
+            handle: Some(handle),
+        })
+    }
+
+    fn status(&self) -> ReaperStatus {
+        self.shared.status.lock().clone()
+    }
+}
+
+impl Drop for TtlReaper {
+    fn drop(&mut self) {
+        *self.shared.stopped.lock() = true;
+        self.shared.wakeup.notify_all();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Database {
+    // Removes up to `max_keys` expired keys, returns how many were removed
+    pub fn reap_expired(&self, max_keys: usize) -> Result<usize, DbError> {
+        self.cdc_batch(|| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let is_expired = |expiry: Option<u64>| expiry.map(|exp| exp < now).unwrap_or(false);
+
+            let expired: Vec<String> = self.data.iter()
+                .filter(|entry| is_expired(entry.value().expiry))
+                .map(|entry| entry.key().clone())
+                .take(max_keys)
+                .collect();
+
+            let mut removed = 0;
+            for key in expired {
+                // Klíč mohl mezitím dostat novou hodnotu nebo TTL
+                if self.commit_remove(ChangeKind::Expire, &key, |entry| is_expired(entry.expiry))?.is_some() {
+                    self.metrics.expired();
+                    removed += 1;
+                }
+            }
+
+            if removed > 0 {
+                log::debug!(target: "rust_db", "reaped {} expired keys", removed);
+                self.save_to_disk()?;
+            }
+            Ok(removed)
+        })
+    }
+
+    // Safety: same contract as schedule_backups, the database must not move until it is
+    // dropped (FFI boxes, namespaces in an Arc)
+    pub(crate) unsafe fn start_reaper(&self, config: ReaperConfig) -> Result<(), DbError> {
+        let mut reaper = self.reaper.lock();
+        *reaper = None;
+        if config.interval_ms == 0 {
+            return Ok(());
+        }
+        *reaper = Some(TtlReaper::spawn(DatabasePtr(self as *const Database), config)?);
+        Ok(())
+    }
+
+    pub fn stop_reaper(&self) {
+        *self.reaper.lock() = None;
+    }
+
+    pub fn reaper_status(&self) -> Option<ReaperStatus> {
+        self.reaper.lock().as_ref().map(|r| r.status())
+    }
+}
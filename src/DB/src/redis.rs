@@ -0,0 +1,910 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::metrics::Op;
+use crate::{Database, DbError, Entry, ImportOptions, ImportReport, Value};
+
+// Opcodes and value types of the RDB format (up to Redis 7.2)
+const OP_SLOT_INFO: u8 = 0xF4;
+const OP_FUNCTION2: u8 = 0xF5;
+const OP_MODULE_AUX: u8 = 0xF7;
+const OP_IDLE: u8 = 0xF8;
+const OP_FREQ: u8 = 0xF9;
+const OP_AUX: u8 = 0xFA;
+const OP_RESIZEDB: u8 = 0xFB;
+const OP_EXPIRETIME_MS: u8 = 0xFC;
+const OP_EXPIRETIME: u8 = 0xFD;
+const OP_SELECTDB: u8 = 0xFE;
+const OP_EOF: u8 = 0xFF;
+
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_SET: u8 = 2;
+const TYPE_ZSET: u8 = 3;
+const TYPE_HASH: u8 = 4;
+const TYPE_ZSET_2: u8 = 5;
+const TYPE_HASH_ZIPLIST: u8 = 13;
+const TYPE_LIST_ZIPLIST: u8 = 10;
+const TYPE_SET_INTSET: u8 = 11;
+const TYPE_ZSET_ZIPLIST: u8 = 12;
+const TYPE_LIST_QUICKLIST: u8 = 14;
+const TYPE_HASH_LISTPACK: u8 = 16;
+const TYPE_ZSET_LISTPACK: u8 = 17;
+const TYPE_LIST_QUICKLIST_2: u8 = 18;
+const TYPE_SET_LISTPACK: u8 = 20;
+
+fn default_separator() -> String {
+    ":".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedisImportConfig {
+    // Redis key segments are split on this and joined with "/"
+    #[serde(default = "default_separator")]
+    pub separator: String,
+    // Path prepended to every imported key, e.g. "redis"
+    #[serde(default)]
+    pub prefix: String,
+    // Import only this Redis database number
+    pub db: Option<u64>,
+    #[serde(default)]
+    pub options: ImportOptions,
+}
+
+impl Default for RedisImportConfig {
+    fn default() -> Self {
+        RedisImportConfig {
+            separator: default_separator(),
+            prefix: String::new(),
+            db: None,
+            options: ImportOptions::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RedisImportSummary {
+    pub keys: usize,
+    // Keys whose TTL already passed are not imported
+    pub expired: usize,
+    // Keys that cannot be mapped to a path (empty after splitting)
+    pub invalid_keys: Vec<String>,
+    // AOF commands that were not replayed, by name
+    pub unsupported_commands: BTreeMap<String, usize>,
+    pub report: ImportReport,
+}
+
+#[derive(Debug, Clone)]
+enum RedisValue {
+    String(String),
+    List(VecDeque<String>),
+    Set(BTreeSet<String>),
+    ZSet(HashMap<String, f64>),
+    Hash(HashMap<String, String>),
+}
+
+#[derive(Debug, Clone)]
+struct RedisKey {
+    value: RedisValue,
+    // unix time in milliseconds
+    expiry_ms: Option<u64>,
+}
+
+// Keys per Redis database
+#[derive(Default)]
+struct Keyspace {
+    dbs: BTreeMap<u64, HashMap<String, RedisKey>>,
+}
+
+fn redis_error(message: impl Into<String>) -> DbError {
+    DbError::System(format!("Redis import: {}", message.into()))
+}
+
+fn text(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+fn format_score(score: f64) -> String {
+    if score.is_infinite() {
+        if score > 0.0 { "inf".to_string() } else { "-inf".to_string() }
+    } else {
+        score.to_string()
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn remaining(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], DbError> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len())
+            .ok_or_else(|| redis_error("unexpected end of data"))?;
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, DbError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn le<const N: usize>(&mut self) -> Result<[u8; N], DbError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn be_u32(&mut self) -> Result<u32, DbError> {
+        Ok(u32::from_be_bytes(self.le::<4>()?))
+    }
+
+    fn be_u64(&mut self) -> Result<u64, DbError> {
+        Ok(u64::from_be_bytes(self.le::<8>()?))
+    }
+}
+
+// Length or special string encoding as written by rdbSaveLen
+enum Length {
+    Len(u64),
+    Encoded(u8),
+}
+
+fn read_length_raw(r: &mut Reader) -> Result<Length, DbError> {
+    let first = r.u8()?;
+    match first >> 6 {
+        0 => Ok(Length::Len((first & 0x3F) as u64)),
+        1 => Ok(Length::Len((((first & 0x3F) as u64) << 8) | r.u8()? as u64)),
+        2 => match first {
+            0x80 => Ok(Length::Len(r.be_u32()? as u64)),
+            0x81 => Ok(Length::Len(r.be_u64()?)),
+            _ => Err(redis_error(format!("invalid length encoding {:#x}", first))),
+        },
+        _ => Ok(Length::Encoded(first & 0x3F)),
+    }
+}
+
+fn read_length(r: &mut Reader) -> Result<u64, DbError> {
+    match read_length_raw(r)? {
+        Length::Len(len) => Ok(len),
+        Length::Encoded(_) => Err(redis_error("expected a length, found an encoded string")),
+    }
+}
+
+// Délky v souboru jsou nedůvěryhodné, víc se dopředu nealokuje
+const MAX_PREALLOC: usize = 1024;
+
+fn lzf_decompress(input: &[u8], expected: usize) -> Result<Vec<u8>, DbError> {
+    let mut out = Vec::with_capacity(expected.min(MAX_PREALLOC));
+    let mut i = 0;
+    while i < input.len() {
+        let ctrl = input[i] as usize;
+        i += 1;
+        if ctrl < 32 {
+            let len = ctrl + 1;
+            let literal = input.get(i..i + len).ok_or_else(|| redis_error("corrupt LZF data"))?;
+            out.extend_from_slice(literal);
+            i += len;
+        } else {
+            let mut len = ctrl >> 5;
+            if len == 7 {
+                len += *input.get(i).ok_or_else(|| redis_error("corrupt LZF data"))? as usize;
+                i += 1;
+            }
+            let low = *input.get(i).ok_or_else(|| redis_error("corrupt LZF data"))? as usize;
+            i += 1;
+            let back = ((ctrl & 0x1F) << 8) + low + 1;
+            if back > out.len() {
+                return Err(redis_error("corrupt LZF back reference"));
+            }
+            // Kopie se může překrývat, proto po bajtech
+            let start = out.len() - back;
+            for k in 0..len + 2 {
+                out.push(out[start + k]);
+            }
+        }
+        if out.len() > expected {
+            return Err(redis_error("LZF length mismatch"));
+        }
+    }
+    if out.len() != expected {
+        return Err(redis_error("LZF length mismatch"));
+    }
+    Ok(out)
+}
+
+fn read_string(r: &mut Reader) -> Result<Vec<u8>, DbError> {
+    match read_length_raw(r)? {
+        Length::Len(len) => Ok(r.bytes(len as usize)?.to_vec()),
+        Length::Encoded(0) => Ok((r.u8()? as i8).to_string().into_bytes()),
+        Length::Encoded(1) => Ok(i16::from_le_bytes(r.le()?).to_string().into_bytes()),
+        Length::Encoded(2) => Ok(i32::from_le_bytes(r.le()?).to_string().into_bytes()),
+        Length::Encoded(3) => {
+            let compressed = read_length(r)? as usize;
+            let len = read_length(r)? as usize;
+            lzf_decompress(r.bytes(compressed)?, len)
+        }
+        Length::Encoded(other) => Err(redis_error(format!("unknown string encoding {}", other))),
+    }
+}
+
+fn read_text(r: &mut Reader) -> Result<String, DbError> {
+    read_string(r).map(text)
+}
+
+// Score of the old ZSET type: length prefixed ASCII
+fn read_double_text(r: &mut Reader) -> Result<f64, DbError> {
+    match r.u8()? {
+        253 => Ok(f64::NAN),
+        254 => Ok(f64::INFINITY),
+        255 => Ok(f64::NEG_INFINITY),
+        len => {
+            let raw = text(r.bytes(len as usize)?.to_vec());
+            raw.parse().map_err(|_| redis_error(format!("invalid score '{}'", raw)))
+        }
+    }
+}
+
+fn ziplist_entries(blob: &[u8]) -> Result<Vec<String>, DbError> {
+    let mut r = Reader::new(blob);
+    r.bytes(10)?; // zlbytes, zltail, zllen
+    let mut entries = Vec::new();
+
+    loop {
+        let prev = r.u8()?;
+        if prev == 0xFF {
+            break;
+        }
+        if prev == 0xFE {
+            r.bytes(4)?;
+        }
+
+        let encoding = r.u8()?;
+        let entry = match encoding >> 6 {
+            0 => text(r.bytes((encoding & 0x3F) as usize)?.to_vec()),
+            1 => {
+                let len = (((encoding & 0x3F) as usize) << 8) | r.u8()? as usize;
+                text(r.bytes(len)?.to_vec())
+            }
+            2 => {
+                let len = r.be_u32()? as usize;
+                text(r.bytes(len)?.to_vec())
+            }
+            _ => match encoding {
+                0xC0 => i16::from_le_bytes(r.le()?).to_string(),
+                0xD0 => i32::from_le_bytes(r.le()?).to_string(),
+                0xE0 => i64::from_le_bytes(r.le()?).to_string(),
+                0xF0 => {
+                    let b: [u8; 3] = r.le()?;
+                    (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8).to_string()
+                }
+                0xFE => (r.u8()? as i8).to_string(),
+                0xF1..=0xFD => ((encoding & 0x0F) - 1).to_string(),
+                _ => return Err(redis_error(format!("invalid ziplist encoding {:#x}", encoding))),
+            },
+        };
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn listpack_entries(blob: &[u8]) -> Result<Vec<String>, DbError> {
+    let mut r = Reader::new(blob);
+    r.bytes(6)?; // total bytes, element count
+    let mut entries = Vec::new();
+
+    loop {
+        let start = r.pos;
+        let encoding = r.u8()?;
+        let entry = if encoding == 0xFF {
+            break;
+        } else if encoding & 0x80 == 0 {
+            (encoding & 0x7F).to_string()
+        } else if encoding & 0xC0 == 0x80 {
+            text(r.bytes((encoding & 0x3F) as usize)?.to_vec())
+        } else if encoding & 0xE0 == 0xC0 {
+            let raw = (((encoding & 0x1F) as i32) << 8) | r.u8()? as i32;
+            // 13bitové číslo se znaménkem
+            ((raw << 19) >> 19).to_string()
+        } else if encoding & 0xF0 == 0xE0 {
+            let len = (((encoding & 0x0F) as usize) << 8) | r.u8()? as usize;
+            text(r.bytes(len)?.to_vec())
+        } else {
+            match encoding {
+                0xF0 => {
+                    let len = u32::from_le_bytes(r.le()?) as usize;
+                    text(r.bytes(len)?.to_vec())
+                }
+                0xF1 => i16::from_le_bytes(r.le()?).to_string(),
+                0xF2 => {
+                    let b: [u8; 3] = r.le()?;
+                    (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8).to_string()
+                }
+                0xF3 => i32::from_le_bytes(r.le()?).to_string(),
+                0xF4 => i64::from_le_bytes(r.le()?).to_string(),
+                _ => return Err(redis_error(format!("invalid listpack encoding {:#x}", encoding))),
+            }
+        };
+
+        // backlen zabírá 1-5 bajtů podle délky záznamu
+        let size = r.pos - start;
+        let backlen = match size {
+            0..=127 => 1,
+            128..=16383 => 2,
+            16384..=2097151 => 3,
+            2097152..=268435455 => 4,
+            _ => 5,
+        };
+        r.bytes(backlen)?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn intset_entries(blob: &[u8]) -> Result<Vec<String>, DbError> {
+    let mut r = Reader::new(blob);
+    let width = u32::from_le_bytes(r.le()?) as usize;
+    let count = u32::from_le_bytes(r.le()?) as usize;
+    (0..count)
+        .map(|_| {
+            Ok(match width {
+                2 => i16::from_le_bytes(r.le()?).to_string(),
+                4 => i32::from_le_bytes(r.le()?).to_string(),
+                8 => i64::from_le_bytes(r.le()?).to_string(),
+                _ => return Err(redis_error(format!("invalid intset width {}", width))),
+            })
+        })
+        .collect()
+}
+
+fn pairs(entries: Vec<String>) -> Vec<(String, String)> {
+    let mut iter = entries.into_iter();
+    let mut out = Vec::new();
+    while let (Some(a), Some(b)) = (iter.next(), iter.next()) {
+        out.push((a, b));
+    }
+    out
+}
+
+fn zset_from_pairs(entries: Vec<String>) -> Result<RedisValue, DbError> {
+    pairs(entries).into_iter()
+        .map(|(member, score)| {
+            let score = score.parse().map_err(|_| redis_error(format!("invalid score '{}'", score)))?;
+            Ok((member, score))
+        })
+        .collect::<Result<_, DbError>>()
+        .map(RedisValue::ZSet)
+}
+
+fn read_value(r: &mut Reader, kind: u8) -> Result<RedisValue, DbError> {
+    let value = match kind {
+        TYPE_STRING => RedisValue::String(read_text(r)?),
+        TYPE_LIST => {
+            let len = read_length(r)?;
+            RedisValue::List((0..len).map(|_| read_text(r)).collect::<Result<_, _>>()?)
+        }
+        TYPE_SET => {
+            let len = read_length(r)?;
+            RedisValue::Set((0..len).map(|_| read_text(r)).collect::<Result<_, _>>()?)
+        }
+        TYPE_ZSET | TYPE_ZSET_2 => {
+            let len = read_length(r)?;
+            let mut members = HashMap::new();
+            for _ in 0..len {
+                let member = read_text(r)?;
+                let score = if kind == TYPE_ZSET_2 {
+                    f64::from_le_bytes(r.le()?)
+                } else {
+                    read_double_text(r)?
+                };
+                members.insert(member, score);
+            }
+            RedisValue::ZSet(members)
+        }
+        TYPE_HASH => {
+            let len = read_length(r)?;
+            let mut fields = HashMap::new();
+            for _ in 0..len {
+                let field = read_text(r)?;
+                fields.insert(field, read_text(r)?);
+            }
+            RedisValue::Hash(fields)
+        }
+        TYPE_LIST_ZIPLIST => RedisValue::List(ziplist_entries(&read_string(r)?)?.into()),
+        TYPE_SET_INTSET => RedisValue::Set(intset_entries(&read_string(r)?)?.into_iter().collect()),
+        TYPE_SET_LISTPACK => RedisValue::Set(listpack_entries(&read_string(r)?)?.into_iter().collect()),
+        TYPE_ZSET_ZIPLIST => zset_from_pairs(ziplist_entries(&read_string(r)?)?)?,
+        TYPE_ZSET_LISTPACK => zset_from_pairs(listpack_entries(&read_string(r)?)?)?,
+        TYPE_HASH_ZIPLIST => RedisValue::Hash(pairs(ziplist_entries(&read_string(r)?)?).into_iter().collect()),
+        TYPE_HASH_LISTPACK => RedisValue::Hash(pairs(listpack_entries(&read_string(r)?)?).into_iter().collect()),
+        TYPE_LIST_QUICKLIST => {
+            let nodes = read_length(r)?;
+            let mut items = VecDeque::new();
+            for _ in 0..nodes {
+                items.extend(ziplist_entries(&read_string(r)?)?);
+            }
+            RedisValue::List(items)
+        }
+        TYPE_LIST_QUICKLIST_2 => {
+            let nodes = read_length(r)?;
+            let mut items = VecDeque::new();
+            for _ in 0..nodes {
+                // 1 = plain node s jedinou hodnotou, 2 = listpack
+                let container = read_length(r)?;
+                let blob = read_string(r)?;
+                if container == 1 {
+                    items.push_back(text(blob));
+                } else {
+                    items.extend(listpack_entries(&blob)?);
+                }
+            }
+            RedisValue::List(items)
+        }
+        other => return Err(redis_error(format!("unsupported RDB value type {} (streams and modules are not imported)", other))),
+    };
+    Ok(value)
+}
+
+// Parses an RDB file into the keyspace and returns the bytes that follow it
+// (an AOF with an RDB preamble continues with RESP commands)
+fn read_rdb<'a>(data: &'a [u8], keyspace: &mut Keyspace) -> Result<&'a [u8], DbError> {
+    let mut r = Reader::new(data);
+    let header = r.bytes(9)?;
+    if &header[..5] != b"REDIS" {
+        return Err(redis_error("not an RDB file"));
+    }
+    let version: u32 = std::str::from_utf8(&header[5..]).ok()
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| redis_error("invalid RDB version"))?;
+
+    let mut db = 0;
+    let mut expiry_ms = None;
+    loop {
+        let opcode = r.u8()?;
+        match opcode {
+            OP_EOF => {
+                // CRC64 od verze 5
+                if version >= 5 {
+                    r.bytes(8)?;
+                }
+                return Ok(r.remaining());
+            }
+            OP_SELECTDB => db = read_length(&mut r)?,
+            OP_RESIZEDB => {
+                read_length(&mut r)?;
+                read_length(&mut r)?;
+            }
+            OP_AUX => {
+                read_string(&mut r)?;
+                read_string(&mut r)?;
+            }
+            OP_EXPIRETIME_MS => expiry_ms = Some(u64::from_le_bytes(r.le()?)),
+            OP_EXPIRETIME => expiry_ms = Some((u32::from_le_bytes(r.le()?) as u64).saturating_mul(1000)),
+            OP_IDLE => {
+                read_length(&mut r)?;
+            }
+            OP_FREQ => {
+                r.u8()?;
+            }
+            OP_FUNCTION2 => {
+                read_string(&mut r)?;
+            }
+            OP_SLOT_INFO => {
+                for _ in 0..3 {
+                    read_length(&mut r)?;
+                }
+            }
+            OP_MODULE_AUX => return Err(redis_error("module data is not supported")),
+            kind => {
+                let key = read_text(&mut r)?;
+                let value = read_value(&mut r, kind)?;
+                keyspace.dbs.entry(db).or_default().insert(key, RedisKey {
+                    value,
+                    expiry_ms: expiry_ms.take(),
+                });
+            }
+        }
+    }
+}
+
+// None when the data ends before the line does
+fn read_resp_line<'a>(r: &mut Reader<'a>) -> Result<Option<&'a str>, DbError> {
+    let rest = r.remaining();
+    let Some(end) = rest.windows(2).position(|w| w == b"\r\n") else { return Ok(None) };
+    let line = std::str::from_utf8(&rest[..end]).map_err(|_| redis_error("invalid AOF header"))?;
+    r.pos += end + 2;
+    Ok(Some(line))
+}
+
+// None when the data ends in the middle of the command, malformed commands are errors
+fn read_resp_command(r: &mut Reader) -> Result<Option<Vec<String>>, DbError> {
+    let Some(header) = read_resp_line(r)? else { return Ok(None) };
+    let count: usize = header.strip_prefix('*')
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(|| redis_error(format!("invalid AOF command header '{}'", header)))?;
+
+    let mut args = Vec::with_capacity(count.min(MAX_PREALLOC));
+    for _ in 0..count {
+        let Some(header) = read_resp_line(r)? else { return Ok(None) };
+        let len: usize = header.strip_prefix('$')
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| redis_error(format!("invalid AOF argument header '{}'", header)))?;
+        if len.checked_add(2).is_none_or(|needed| r.remaining().len() < needed) {
+            return Ok(None);
+        }
+        args.push(text(r.bytes(len)?.to_vec()));
+        if r.bytes(2)? != b"\r\n" {
+            return Err(redis_error("AOF argument is not terminated by CRLF"));
+        }
+    }
+    Ok(Some(args))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+fn parse_int(value: &str) -> Result<i64, DbError> {
+    value.parse().map_err(|_| redis_error(format!("'{}' is not an integer", value)))
+}
+
+fn time_out_of_range(value: &str) -> DbError {
+    redis_error(format!("expire time '{}' is out of range", value))
+}
+
+// Time argument in `unit_ms` units converted to milliseconds; negative times are in the past
+fn parse_ms(value: &str, unit_ms: u64) -> Result<u64, DbError> {
+    (parse_int(value)?.max(0) as u64).checked_mul(unit_ms)
+        .ok_or_else(|| time_out_of_range(value))
+}
+
+// Expiry `value` units from now
+fn expire_in(value: &str, unit_ms: u64) -> Result<u64, DbError> {
+    now_ms().checked_add(parse_ms(value, unit_ms)?)
+        .ok_or_else(|| time_out_of_range(value))
+}
+
+impl Keyspace {
+    fn db(&mut self, db: u64) -> &mut HashMap<String, RedisKey> {
+        self.dbs.entry(db).or_default()
+    }
+
+    // Replays one AOF command; returns false for commands that are not understood
+    fn apply(&mut self, db: &mut u64, args: &[String]) -> Result<bool, DbError> {
+        let Some(name) = args.first() else { return Ok(true) };
+        let name = name.to_ascii_uppercase();
+        let arg = |i: usize| args.get(i).map(|s| s.as_str()).ok_or_else(|| redis_error(format!("{} is missing arguments", name)));
+        let keys = self.db(*db);
+
+        match name.as_str() {
+            "SELECT" => *db = parse_int(arg(1)?)? as u64,
+            "MULTI" | "EXEC" | "PING" => {}
+            "FLUSHDB" => keys.clear(),
+            "FLUSHALL" => self.dbs.clear(),
+            "SET" | "SETNX" => {
+                let key = arg(1)?.to_string();
+                let mut expiry_ms = None;
+                let mut keep_ttl = false;
+                let mut only_new = name == "SETNX";
+                let mut i = 3;
+                while i < args.len() {
+                    match args[i].to_ascii_uppercase().as_str() {
+                        "EX" => { i += 1; expiry_ms = Some(expire_in(arg(i)?, 1000)?) }
+                        "PX" => { i += 1; expiry_ms = Some(expire_in(arg(i)?, 1)?) }
+                        "EXAT" => { i += 1; expiry_ms = Some(parse_ms(arg(i)?, 1000)?) }
+                        "PXAT" => { i += 1; expiry_ms = Some(parse_ms(arg(i)?, 1)?) }
+                        "KEEPTTL" => keep_ttl = true,
+                        "NX" => only_new = true,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                if only_new && keys.contains_key(&key) {
+                    return Ok(true);
+                }
+                if keep_ttl {
+                    expiry_ms = keys.get(&key).and_then(|k| k.expiry_ms);
+                }
+                keys.insert(key, RedisKey { value: RedisValue::String(arg(2)?.to_string()), expiry_ms });
+            }
+            "SETEX" | "PSETEX" => {
+                let unit_ms = if name == "SETEX" { 1000 } else { 1 };
+                keys.insert(arg(1)?.to_string(), RedisKey {
+                    value: RedisValue::String(arg(3)?.to_string()),
+                    expiry_ms: Some(expire_in(arg(2)?, unit_ms)?),
+                });
+            }
+            "MSET" => {
+                for pair in args[1..].chunks(2) {
+                    if let [key, value] = pair {
+                        keys.insert(key.clone(), RedisKey { value: RedisValue::String(value.clone()), expiry_ms: None });
+                    }
+                }
+            }
+            "APPEND" | "INCR" | "DECR" | "INCRBY" | "DECRBY" => {
+                let key = arg(1)?.to_string();
+                let entry = keys.entry(key).or_insert(RedisKey { value: RedisValue::String(String::new()), expiry_ms: None });
+                let RedisValue::String(current) = &mut entry.value else {
+                    return Err(redis_error(format!("{} on a non-string key", name)));
+                };
+                if name == "APPEND" {
+                    current.push_str(arg(2)?);
+                } else {
+                    let by = match name.as_str() {
+                        "INCR" => 1,
+                        "DECR" => -1,
+                        "INCRBY" => parse_int(arg(2)?)?,
+                        _ => parse_int(arg(2)?)?.checked_neg()
+                            .ok_or_else(|| redis_error(format!("{} is out of range", name)))?,
+                    };
+                    let base = if current.is_empty() { 0 } else { parse_int(current)? };
+                    *current = base.checked_add(by)
+                        .ok_or_else(|| redis_error(format!("{} overflows the value", name)))?
+                        .to_string();
+                }
+            }
+            "DEL" | "UNLINK" => {
+                for key in &args[1..] {
+                    keys.remove(key);
+                }
+            }
+            "RENAME" => {
+                if let Some(value) = keys.remove(arg(1)?) {
+                    keys.insert(arg(2)?.to_string(), value);
+                }
+            }
+            "EXPIRE" | "PEXPIRE" | "EXPIREAT" | "PEXPIREAT" => {
+                let at = match name.as_str() {
+                    "EXPIRE" => expire_in(arg(2)?, 1000)?,
+                    "PEXPIRE" => expire_in(arg(2)?, 1)?,
+                    "EXPIREAT" => parse_ms(arg(2)?, 1000)?,
+                    _ => parse_ms(arg(2)?, 1)?,
+                };
+                if let Some(key) = keys.get_mut(arg(1)?) {
+                    key.expiry_ms = Some(at);
+                }
+            }
+            "PERSIST" => {
+                if let Some(key) = keys.get_mut(arg(1)?) {
+                    key.expiry_ms = None;
+                }
+            }
+            "HSET" | "HMSET" | "HSETNX" => {
+                let entry = keys.entry(arg(1)?.to_string())
+                    .or_insert(RedisKey { value: RedisValue::Hash(HashMap::new()), expiry_ms: None });
+                let RedisValue::Hash(fields) = &mut entry.value else {
+                    return Err(redis_error(format!("{} on a non-hash key", name)));
+                };
+                for pair in args[2..].chunks(2) {
+                    if let [field, value] = pair {
+                        if name == "HSETNX" && fields.contains_key(field) {
+                            continue;
+                        }
+                        fields.insert(field.clone(), value.clone());
+                    }
+                }
+            }
+            "HDEL" => {
+                if let Some(RedisKey { value: RedisValue::Hash(fields), .. }) = keys.get_mut(arg(1)?) {
+                    for field in &args[2..] {
+                        fields.remove(field);
+                    }
+                }
+            }
+            "RPUSH" | "LPUSH" => {
+                let entry = keys.entry(arg(1)?.to_string())
+                    .or_insert(RedisKey { value: RedisValue::List(VecDeque::new()), expiry_ms: None });
+                let RedisValue::List(items) = &mut entry.value else {
+                    return Err(redis_error(format!("{} on a non-list key", name)));
+                };
+                for item in &args[2..] {
+                    if name == "RPUSH" { items.push_back(item.clone()) } else { items.push_front(item.clone()) }
+                }
+            }
+            "LPOP" | "RPOP" => {
+                if let Some(RedisKey { value: RedisValue::List(items), .. }) = keys.get_mut(arg(1)?) {
+                    let count = args.get(2).map(|c| parse_int(c)).transpose()?.unwrap_or(1);
+                    for _ in 0..count {
+                        if name == "LPOP" { items.pop_front(); } else { items.pop_back(); }
+                    }
+                }
+            }
+            "SADD" => {
+                let entry = keys.entry(arg(1)?.to_string())
+                    .or_insert(RedisKey { value: RedisValue::Set(BTreeSet::new()), expiry_ms: None });
+                let RedisValue::Set(members) = &mut entry.value else {
+                    return Err(redis_error("SADD on a non-set key"));
+                };
+                members.extend(args[2..].iter().cloned());
+            }
+            "SREM" => {
+                if let Some(RedisKey { value: RedisValue::Set(members), .. }) = keys.get_mut(arg(1)?) {
+                    for member in &args[2..] {
+                        members.remove(member);
+                    }
+                }
+            }
+            "ZADD" => {
+                let entry = keys.entry(arg(1)?.to_string())
+                    .or_insert(RedisKey { value: RedisValue::ZSet(HashMap::new()), expiry_ms: None });
+                let RedisValue::ZSet(members) = &mut entry.value else {
+                    return Err(redis_error("ZADD on a non-sorted-set key"));
+                };
+                // Přepínače NX/XX/GT/LT/CH/INCR AOF běžně neobsahuje
+                let start = args[2..].iter()
+                    .position(|a| a.parse::<f64>().is_ok() || a.eq_ignore_ascii_case("inf") || a.eq_ignore_ascii_case("-inf"))
+                    .map(|p| p + 2)
+                    .unwrap_or(args.len());
+                for pair in args[start..].chunks(2) {
+                    if let [score, member] = pair {
+                        let score = score.parse().map_err(|_| redis_error(format!("invalid score '{}'", score)))?;
+                        members.insert(member.clone(), score);
+                    }
+                }
+            }
+            "ZREM" => {
+                if let Some(RedisKey { value: RedisValue::ZSet(members), .. }) = keys.get_mut(arg(1)?) {
+                    for member in &args[2..] {
+                        members.remove(member);
+                    }
+                }
+            }
+            _ => return Ok(false),
+        }
+
+        // Prázdné kolekce Redis maže
+        if let Some(key) = args.get(1) {
+            let empty = match self.db(*db).get(key).map(|k| &k.value) {
+                Some(RedisValue::List(v)) => v.is_empty(),
+                Some(RedisValue::Set(v)) => v.is_empty(),
+                Some(RedisValue::ZSet(v)) => v.is_empty(),
+                Some(RedisValue::Hash(v)) => v.is_empty(),
+                _ => false,
+            };
+            if empty {
+                self.db(*db).remove(key);
+            }
+        }
+        Ok(true)
+    }
+
+    fn replay_aof(&mut self, data: &[u8], summary: &mut RedisImportSummary) -> Result<(), DbError> {
+        let mut r = Reader::new(data);
+        let mut db = 0;
+        while !r.remaining().is_empty() {
+            // Useknutý poslední příkaz po pádu Redisu se ignoruje (jako aof-load-truncated),
+            // poškozený příkaz uprostřed je chyba
+            let Some(args) = read_resp_command(&mut r)? else { break };
+            if !self.apply(&mut db, &args)? {
+                let name = args.first().map(|n| n.to_ascii_uppercase()).unwrap_or_default();
+                *summary.unsupported_commands.entry(name).or_insert(0) += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn load_file(path: &Path, keyspace: &mut Keyspace, summary: &mut RedisImportSummary) -> Result<(), DbError> {
+    let data = fs::read(path)?;
+    let rest = if data.starts_with(b"REDIS") {
+        read_rdb(&data, keyspace)?
+    } else {
+        &data[..]
+    };
+    keyspace.replay_aof(rest, summary)
+}
+
+// Redis 7 multi part AOF: a manifest listing the base file and the incremental files
+fn load_aof_dir(dir: &Path, keyspace: &mut Keyspace, summary: &mut RedisImportSummary) -> Result<(), DbError> {
+    let manifest = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| p.extension().map(|ext| ext == "manifest").unwrap_or(false))
+        .ok_or_else(|| redis_error("no AOF manifest in directory"))?;
+
+    let mut base = Vec::new();
+    let mut incremental = Vec::new();
+    for line in fs::read_to_string(&manifest)?.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let value = |name: &str| fields.chunks(2).find(|kv| kv[0] == name).and_then(|kv| kv.get(1).copied());
+        let (Some(file), Some(kind)) = (value("file"), value("type")) else { continue };
+        let seq: u64 = value("seq").and_then(|s| s.parse().ok()).unwrap_or(0);
+        match kind {
+            "b" => base.push((seq, file.to_string())),
+            "i" => incremental.push((seq, file.to_string())),
+            _ => {}
+        }
+    }
+    incremental.sort();
+
+    for (_, file) in base.into_iter().chain(incremental) {
+        load_file(&dir.join(file), keyspace, summary)?;
+    }
+    Ok(())
+}
+
+fn to_value(value: RedisValue) -> Value {
+    match value {
+        RedisValue::String(s) => Value::String(s),
+        RedisValue::List(items) => Value::Array(items.into()),
+        RedisValue::Set(members) => Value::Array(members.into_iter().collect()),
+        // member -> score, pořadí jde z score obnovit
+        RedisValue::ZSet(members) => Value::Map(members.into_iter().map(|(m, s)| (m, format_score(s))).collect()),
+        RedisValue::Hash(fields) => Value::Map(fields),
+    }
+}
+
+pub fn map_redis_key(key: &str, separator: &str, prefix: &str) -> Option<String> {
+    let mut segments: Vec<&str> = prefix.split('/').filter(|s| !s.is_empty()).collect();
+    if separator.is_empty() {
+        segments.extend(key.split('/').filter(|s| !s.is_empty()));
+    } else {
+        segments.extend(key.split(separator).flat_map(|s| s.split('/')).filter(|s| !s.is_empty()));
+    }
+    if segments.is_empty() {
+        None
+    } else {
+        Some(segments.join("/"))
+    }
+}
+
+impl Database {
+    // Imports an RDB dump, an AOF file (optionally with an RDB preamble)
+    // or a Redis 7 appendonly directory with a manifest
+    pub fn import_redis(&self, path: &str, config: &RedisImportConfig) -> Result<RedisImportSummary, DbError> {
+        self.observe(Op::Import, path, || self.load_redis_dump(path, config))
+    }
+
+    fn load_redis_dump(&self, path: &str, config: &RedisImportConfig) -> Result<RedisImportSummary, DbError> {
+        let mut summary = RedisImportSummary::default();
+        let mut keyspace = Keyspace::default();
+
+        let path = Path::new(path);
+        if path.is_dir() {
+            load_aof_dir(path, &mut keyspace, &mut summary)?;
+        } else {
+            load_file(path, &mut keyspace, &mut summary)?;
+        }
+
+        let now = now_ms();
+        let mut entries = Vec::new();
+        for (db, keys) in keyspace.dbs {
+            if config.db.map(|only| only != db).unwrap_or(false) {
+                continue;
+            }
+            for (key, redis_key) in keys {
+                if redis_key.expiry_ms.map(|exp| exp <= now).unwrap_or(false) {
+                    summary.expired += 1;
+                    continue;
+                }
+                let Some(path) = map_redis_key(&key, &config.separator, &config.prefix) else {
+                    summary.invalid_keys.push(key);
+                    continue;
+                };
+
+                let mut entry = Entry::new(to_value(redis_key.value), &path)?;
+                // rust_db má expiraci v sekundách, zaokrouhlujeme nahoru
+                entry.expiry = redis_key.expiry_ms.map(|ms| ms.div_ceil(1000));
+                entries.push(Ok((path, entry)));
+                summary.keys += 1;
+            }
+        }
+
+        summary.report = self.import_iter(entries, &config.options)?;
+        Ok(summary)
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDateTime, TimeZone, Timelike, Utc};
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+
+use crate::backup::manifest_name;
+use crate::{BackupTarget, Database, DbError, LocalTarget, S3Config, S3Target};
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+fn default_prefix() -> String {
+    "backup".to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    // newest N backups are always kept
+    #[serde(default)]
+    pub keep_last: usize,
+    // newest backup of each of the last D days
+    #[serde(default)]
+    pub keep_daily: usize,
+    // newest backup of each of the last W ISO weeks
+    #[serde(default)]
+    pub keep_weekly: usize,
+}
+
+impl RetentionPolicy {
+    fn is_unlimited(&self) -> bool {
+        self.keep_last == 0 && self.keep_daily == 0 && self.keep_weekly == 0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    pub interval_secs: Option<u64>,
+    // "minute hour day-of-month month day-of-week", evaluated in UTC
+    pub cron: Option<String>,
+    #[serde(default = "default_prefix")]
+    pub prefix: String,
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+    // local backup directory, or an S3 bucket when `s3` is set
+    pub dir: Option<String>,
+    pub s3: Option<S3Config>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScheduleStatus {
+    pub runs: u64,
+    pub last_backup: Option<String>,
+    pub last_run: Option<DateTime<Utc>>,
+    pub next_run: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub pruned: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct CronField {
+    allowed: Vec<bool>,
+    wildcard: bool,
+}
+
+impl CronField {
+    fn parse(field: &str, min: u32, max: u32) -> Result<Self, DbError> {
+        let invalid = || DbError::System(format!("Invalid cron field '{}'", field));
+        let mut allowed = vec![false; max as usize + 1];
+
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+                None => (part, 1),
+            };
+            if step == 0 {
+                return Err(invalid());
+            }
+
+            let (start, end) = if range == "*" {
+                (min, max)
+            } else if let Some((a, b)) = range.split_once('-') {
+                (a.parse().map_err(|_| invalid())?, b.parse().map_err(|_| invalid())?)
+            } else {
+                let value: u32 = range.parse().map_err(|_| invalid())?;
+                // "5/15" znamená od 5 do konce rozsahu
+                (value, if part.contains('/') { max } else { value })
+            };
+            if start < min || end > max || start > end {
+                return Err(invalid());
+            }
+
+            for value in (start..=end).step_by(step as usize) {
+                allowed[value as usize] = true;
+            }
+        }
+
+        Ok(CronField {
+            allowed,
+            wildcard: field == "*",
+        })
+    }
+
+    fn matches(&self, value: u32) -> bool {
+        self.allowed.get(value as usize).copied().unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    minute: CronField,
+    hour: CronField,
+    day: CronField,
+    month: CronField,
+    weekday: CronField,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, DbError> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(DbError::System(format!("Cron expression '{}' must have 5 fields", expression)));
+        }
+
+        let mut weekday = CronField::parse(fields[4], 0, 7)?;
+        // 7 is Sunday as well
+        if weekday.allowed[7] {
+            weekday.allowed[0] = true;
+        }
+
+        Ok(CronSchedule {
+            minute: CronField::parse(fields[0], 0, 59)?,
+            hour: CronField::parse(fields[1], 0, 23)?,
+            day: CronField::parse(fields[2], 1, 31)?,
+            month: CronField::parse(fields[3], 1, 12)?,
+            weekday,
+        })
+    }
+
+    fn day_matches(&self, time: &DateTime<Utc>) -> bool {
+        let day = self.day.matches(time.day());
+        let weekday = self.weekday.matches(time.weekday().num_days_from_sunday());
+        // Stejně jako cron: když jsou omezené oba dny, stačí shoda jednoho z nich
+        match (self.day.wildcard, self.weekday.wildcard) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    // First matching minute strictly after `after`
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let mut time = start;
+        let limit = start + ChronoDuration::days(366 * 5);
+
+        while time < limit {
+            if !self.month.matches(time.month()) {
+                let (year, month) = if time.month() == 12 { (time.year() + 1, 1) } else { (time.year(), time.month() + 1) };
+                time = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+                continue;
+            }
+            if !self.day_matches(&time) {
+                time = time.date_naive().and_hms_opt(0, 0, 0)?.and_utc() + ChronoDuration::days(1);
+                continue;
+            }
+            if !self.hour.matches(time.hour()) {
+                time = time.with_minute(0)? + ChronoDuration::hours(1);
+                continue;
+            }
+            if !self.minute.matches(time.minute()) {
+                time += ChronoDuration::minutes(1);
+                continue;
+            }
+            return Some(time);
+        }
+        None
+    }
+}
+
+enum Trigger {
+    Interval(Duration),
+    Cron(CronSchedule),
+}
+
+impl Trigger {
+    fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Trigger::Interval(interval) => Some(after + ChronoDuration::from_std(*interval).ok()?),
+            Trigger::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+pub fn backup_name(prefix: &str, time: DateTime<Utc>) -> String {
+    format!("{}-{}.json", prefix, time.format(TIMESTAMP_FORMAT))
+}
+
+fn parse_backup_name(prefix: &str, name: &str) -> Option<DateTime<Utc>> {
+    let stamp = name.strip_prefix(prefix)?.strip_prefix('-')?.strip_suffix(".json")?;
+    NaiveDateTime::parse_from_str(stamp, TIMESTAMP_FORMAT).ok().map(|t| t.and_utc())
+}
+
+// Deletes timestamped backups (and their manifests) that no retention rule keeps.
+// Returns the names of the removed backups.
+pub fn prune_backups(
+    target: &dyn BackupTarget,
+    prefix: &str,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> Result<Vec<String>, DbError> {
+    if policy.is_unlimited() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups: Vec<(DateTime<Utc>, String)> = target.list(prefix)?
+        .into_iter()
+        .filter_map(|name| parse_backup_name(prefix, &name).map(|time| (time, name)))
+        .collect();
+    // Nejnovější první
+    backups.sort_by(|a, b| b.cmp(a));
+
+    let mut keep: HashSet<&str> = HashSet::new();
+    for (_, name) in backups.iter().take(policy.keep_last) {
+        keep.insert(name);
+    }
+
+    let today = now.date_naive();
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    for (time, name) in &backups {
+        let date = time.date_naive();
+        let age_days = (today - date).num_days();
+
+        if age_days < policy.keep_daily as i64 && days.insert(date) {
+            keep.insert(name);
+        }
+        let week = date.iso_week();
+        if age_days < policy.keep_weekly as i64 * 7 && weeks.insert((week.year(), week.week())) {
+            keep.insert(name);
+        }
+    }
+
+    let mut pruned = Vec::new();
+    for (_, name) in &backups {
+        if keep.contains(name.as_str()) {
+            continue;
+        }
+        target.delete(name)?;
+        // Starší zálohy nemusí manifest mít
+        let _ = target.delete(&manifest_name(name));
+        pruned.push(name.clone());
+    }
+    Ok(pruned)
+}
+
+struct SchedulerShared {
+    stopped: Mutex<bool>,
+    wakeup: Condvar,
+    status: Mutex<ScheduleStatus>,
+}
+
+// Background thread taking timestamped backups; stops and joins on drop
+pub struct BackupScheduler {
+    shared: Arc<SchedulerShared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+fn build_target(config: &ScheduleConfig) -> Result<Box<dyn BackupTarget>, DbError> {
+    match (&config.s3, &config.dir) {
+        (Some(s3), _) => Ok(Box::new(S3Target::new(s3.clone())?)),
+        (None, Some(dir)) => Ok(Box::new(LocalTarget::new(dir))),
+        (None, None) => Err(DbError::System("Backup schedule needs a dir or an s3 target".to_string())),
+    }
+}
+
+fn build_trigger(config: &ScheduleConfig) -> Result<Trigger, DbError> {
+    match (&config.cron, config.interval_secs) {
+        (Some(cron), _) => Ok(Trigger::Cron(CronSchedule::parse(cron)?)),
+        (None, Some(secs)) if secs > 0 => Ok(Trigger::Interval(Duration::from_secs(secs))),
+        _ => Err(DbError::System("Backup schedule needs interval_secs or cron".to_string())),
+    }
+}
+
+impl BackupScheduler {
+    pub fn start(db: Arc<Database>, config: ScheduleConfig) -> Result<Self, DbError> {
+        Self::spawn(config, move |target, name| db.create_backup_to(target, name).map(|_| ()))
+    }
+
+    pub(crate) fn spawn<F>(config: ScheduleConfig, backup: F) -> Result<Self, DbError>
+    where
+        F: Fn(&dyn BackupTarget, &str) -> Result<(), DbError> + Send + 'static,
+    {
+        let target = build_target(&config)?;
+        let trigger = build_trigger(&config)?;
+        let shared = Arc::new(SchedulerShared {
+            stopped: Mutex::new(false),
+            wakeup: Condvar::new(),
+            status: Mutex::new(ScheduleStatus::default()),
+        });
+
+        let thread_shared = shared.clone();
+        let handle = thread::Builder::new()
+            .name("rust-db-backup".to_string())
+            .spawn(move || {
+                let shared = thread_shared;
+                let mut next = trigger.next_after(Utc::now());
+
+                loop {
+                    shared.status.lock().next_run = next;
+                    let Some(due) = next else { break };
+
+                    let mut stopped = shared.stopped.lock();
+                    while !*stopped && Utc::now() < due {
+                        let wait = (due - Utc::now()).to_std().unwrap_or_default();
+                        shared.wakeup.wait_for(&mut stopped, wait);
+                    }
+                    if *stopped {
+                        break;
+                    }
+                    drop(stopped);
+
+                    let now = Utc::now();
+                    let name = backup_name(&config.prefix, now);
+                    let result = backup(target.as_ref(), &name)
+                        .and_then(|_| prune_backups(target.as_ref(), &config.prefix, &config.retention, now));
+
+                    let mut status = shared.status.lock();
+                    status.runs += 1;
+                    status.last_run = Some(now);
+                    match result {
+                        Ok(pruned) => {
+                            status.last_backup = Some(name);
+                            status.last_error = None;
+                            status.pruned = pruned;
+                        }
+                        Err(e) => status.last_error = Some(e.to_string()),
+                    }
+                    drop(status);
+
+                    // Interval se počítá od konce zálohy, aby se běhy nepřekrývaly
+                    next = trigger.next_after(Utc::now());
+                }
+            })?;
+
+        Ok(BackupScheduler {
+            shared,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn status(&self) -> ScheduleStatus {
+        self.shared.status.lock().clone()
+    }
+
+    pub fn stop(&mut self) {
+        *self.shared.stopped.lock() = true;
+        self.shared.wakeup.notify_all();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for BackupScheduler {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// Raw pointer handed to a background thread; only created by the unsafe starters below,
+// whose callers guarantee the database stays at its address while the thread runs
+pub(crate) struct DatabasePtr(pub(crate) *const Database);
+
+// SAFETY: Database is Sync, the pointer is only dereferenced as a shared reference
+unsafe impl Send for DatabasePtr {}
+
+impl Database {
+    // Rust callers use BackupScheduler::start with an Arc<Database>.
+    //
+    // Safety: the scheduler thread borrows the database through a raw pointer, so the
+    // database must not move until it is dropped (a Box or an Arc, as for FFI handles);
+    // Database::drop stops the thread.
+    pub(crate) unsafe fn schedule_backups(&self, config: ScheduleConfig) -> Result<(), DbError> {
+        let mut scheduler = self.scheduler.lock();
+        // Starý plánovač zastavíme dřív, než spustíme nový
+        *scheduler = None;
+
+        let db = DatabasePtr(self as *const Database);
+        *scheduler = Some(BackupScheduler::spawn(config, move |target, name| {
+            let db = &db;
+            // SAFETY: Database::drop stops this thread before the database goes away
+            let database = unsafe { &*db.0 };
+            database.create_backup_to(target, name).map(|_| ())
+        })?);
+        Ok(())
+    }
+
+    pub fn stop_backups(&self) {
+        *self.scheduler.lock() = None;
+    }
+
+    pub fn backup_schedule_status(&self) -> Option<ScheduleStatus> {
+        self.scheduler.lock().as_ref().map(|s| s.status())
+    }
+}
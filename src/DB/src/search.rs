@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::metrics::Op;
+use crate::{Database, DbError, Entry, Value};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub path: String,
+    pub score: f64,
+}
+
+// Inverted index over String values and Map fields of entries under the configured prefixes.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    prefixes: Vec<Vec<String>>,
+    postings: HashMap<String, HashMap<String, u32>>,
+    doc_terms: HashMap<String, Vec<String>>,
+    total_terms: usize,
+}
+
+// BM25 parameters
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+fn fold_diacritics(c: char) -> char {
+    match c {
+        'á' | 'à' | 'â' | 'ä' | 'å' | 'ã' => 'a',
+        'č' | 'ç' | 'ć' => 'c',
+        'ď' => 'd',
+        'é' | 'ě' | 'è' | 'ê' | 'ë' => 'e',
+        'í' | 'ì' | 'î' | 'ï' => 'i',
+        'ľ' | 'ĺ' | 'ł' => 'l',
+        'ň' | 'ñ' | 'ń' => 'n',
+        'ó' | 'ò' | 'ô' | 'ö' | 'õ' => 'o',
+        'ř' | 'ŕ' => 'r',
+        'š' | 'ś' => 's',
+        'ť' => 't',
+        'ú' | 'ů' | 'ù' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ž' | 'ź' | 'ż' => 'z',
+        _ => c,
+    }
+}
+
+// Very small suffix stripper, good enough for English plurals and verb forms.
+fn stem(word: &str) -> String {
+    const SUFFIXES: [&str; 5] = ["ations", "ation", "ing", "ies", "ed"];
+
+    for suffix in SUFFIXES {
+        if let Some(stripped) = word.strip_suffix(suffix) {
+            if stripped.chars().count() >= 3 {
+                return if suffix == "ies" {
+                    format!("{}y", stripped)
+                } else {
+                    stripped.to_string()
+                };
+            }
+        }
+    }
+
+    match word.strip_suffix('s') {
+        Some(stripped) if stripped.chars().count() >= 3 && !stripped.ends_with('s') => stripped.to_string(),
+        _ => word.to_string(),
+    }
+}
+
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let folded: String = word.chars()
+                .flat_map(char::to_lowercase)
+                .map(fold_diacritics)
+                .collect();
+            stem(&folded)
+        })
+        .collect()
+}
+
+fn entry_text(entry: &Entry) -> Option<String> {
+    match &entry.value {
+        Value::String(s) => Some(s.clone()),
+        Value::Map(map) => Some(map.values().cloned().collect::<Vec<_>>().join(" ")),
+        _ => None,
+    }
+}
+
+fn prefix_components(prefix: &str) -> Vec<String> {
+    if prefix.is_empty() {
+        return Vec::new();
+    }
+    prefix.split('/').map(|s| s.to_string()).collect()
+}
+
+impl SearchIndex {
+    pub fn new(prefixes: &[&str]) -> Self {
+        SearchIndex {
+            prefixes: prefixes.iter().map(|p| prefix_components(p)).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn covers(&self, entry: &Entry) -> bool {
+        self.prefixes.is_empty() || self.prefixes.iter().any(|p| entry.matches_pattern(p))
+    }
+
+    pub(crate) fn index(&mut self, path: &str, entry: &Entry) {
+        self.remove(path);
+
+        if !self.covers(entry) {
+            return;
+        }
+        let Some(text) = entry_text(entry) else {
+            return;
+        };
+
+        let terms = tokenize(&text);
+        for term in &terms {
+            *self.postings.entry(term.clone())
+                .or_default()
+                .entry(path.to_string())
+                .or_insert(0) += 1;
+        }
+        self.total_terms += terms.len();
+        self.doc_terms.insert(path.to_string(), terms);
+    }
+
+    pub(crate) fn remove(&mut self, path: &str) {
+        if let Some(terms) = self.doc_terms.remove(path) {
+            self.total_terms -= terms.len();
+            for term in terms {
+                if let Some(docs) = self.postings.get_mut(&term) {
+                    docs.remove(path);
+                    if docs.is_empty() {
+                        self.postings.remove(&term);
+                    }
+                }
+            }
+        }
+    }
+
+    fn score(&self, query: &str) -> HashMap<String, f64> {
+        let doc_count = self.doc_terms.len() as f64;
+        let avg_len = if self.doc_terms.is_empty() {
+            0.0
+        } else {
+            self.total_terms as f64 / doc_count
+        };
+
+        let mut scores: HashMap<String, f64> = HashMap::new();
+        for term in tokenize(query) {
+            let Some(docs) = self.postings.get(&term) else {
+                continue;
+            };
+            let idf = ((doc_count - docs.len() as f64 + 0.5) / (docs.len() as f64 + 0.5) + 1.0).ln();
+
+            for (path, tf) in docs {
+                let tf = *tf as f64;
+                let len = self.doc_terms.get(path).map(|t| t.len()).unwrap_or(0) as f64;
+                let norm = tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * len / avg_len));
+                *scores.entry(path.clone()).or_insert(0.0) += idf * norm;
+            }
+        }
+        scores
+    }
+}
+
+impl Database {
+    // Builds the full-text index for entries under `prefixes` (all entries when empty)
+    pub fn enable_search(&self, prefixes: &[&str]) {
+        let mut index = SearchIndex::new(prefixes);
+        for entry in self.data.iter() {
+            index.index(entry.key(), entry.value());
+        }
+        *self.search_index.write() = Some(index);
+    }
+
+    pub fn disable_search(&self) {
+        *self.search_index.write() = None;
+    }
+
+    pub fn search(&self, query: &str, prefix: &str, limit: usize) -> Result<Vec<SearchHit>, DbError> {
+        self.observe(Op::Search, query, || self.rank_matches(query, prefix, limit))
+    }
+
+    fn rank_matches(&self, query: &str, prefix: &str, limit: usize) -> Result<Vec<SearchHit>, DbError> {
+        let guard = self.search_index.read();
+        let index = guard.as_ref()
+            .ok_or_else(|| DbError::System("Search index is not enabled".to_string()))?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let prefix = prefix_components(prefix);
+
+        let mut hits: Vec<SearchHit> = index.score(query)
+            .into_iter()
+            .filter(|(path, _)| match self.data.get(path) {
+                Some(entry) => entry.matches_pattern(&prefix)
+                    && !entry.expiry.map(|exp| exp < now).unwrap_or(false),
+                None => false,
+            })
+            .map(|(path, score)| SearchHit { path, score })
+            .collect();
+
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    pub(crate) fn search_index_update(&self, path: &str) {
+        if let Some(index) = self.search_index.write().as_mut() {
+            match self.data.get(path) {
+                Some(entry) => index.index(path, &entry),
+                None => index.remove(path),
+            }
+        }
+    }
+}
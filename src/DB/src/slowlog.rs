@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Aggregation, BackupManifest, BackupRecord, BtrfsSnapshotInfo, Database, DbError, ExportSummary, ImportReport, RedisImportSummary,
+    SqliteExportSummary, Value,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlowlogConfig {
+    // Operations taking at least this long are recorded (0 = all of them)
+    pub threshold_us: u64,
+    // Oldest entries are dropped beyond this, 0 disables the slowlog
+    pub max_len: usize,
+}
+
+impl Default for SlowlogConfig {
+    fn default() -> Self {
+        SlowlogConfig {
+            threshold_us: 10_000,
+            max_len: 128,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowlogEntry {
+    pub id: u64,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub timestamp: DateTime<Utc>,
+    pub operation: String,
+    pub path: String,
+    pub duration_us: u64,
+    // Returned values or keys, 0 for errors
+    pub result_size: usize,
+    pub error: Option<String>,
+}
+
+// Items an operation returned, for the slowlog and trace output
+pub(crate) trait ResultSize {
+    fn result_size(&self) -> usize;
+
+    fn error(&self) -> Option<&DbError> {
+        None
+    }
+}
+
+impl ResultSize for bool {
+    fn result_size(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl ResultSize for () {
+    fn result_size(&self) -> usize {
+        0
+    }
+}
+
+impl ResultSize for usize {
+    fn result_size(&self) -> usize {
+        *self
+    }
+}
+
+impl ResultSize for i64 {
+    fn result_size(&self) -> usize {
+        1
+    }
+}
+
+impl ResultSize for Value {
+    fn result_size(&self) -> usize {
+        match self {
+            Value::Array(items) => items.len(),
+            Value::Map(map) => map.len(),
+            _ => 1,
+        }
+    }
+}
+
+impl<T> ResultSize for Option<T> {
+    fn result_size(&self) -> usize {
+        self.is_some() as usize
+    }
+}
+
+impl<T> ResultSize for Vec<T> {
+    fn result_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<K, V> ResultSize for HashMap<K, V> {
+    fn result_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<K, V> ResultSize for BTreeMap<K, V> {
+    fn result_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl ResultSize for Aggregation {
+    fn result_size(&self) -> usize {
+        self.count
+    }
+}
+
+// Zálohy a importy/exporty vrací počet zapsaných nebo načtených klíčů
+impl ResultSize for BackupManifest {
+    fn result_size(&self) -> usize {
+        self.key_count
+    }
+}
+
+impl ResultSize for BackupRecord {
+    fn result_size(&self) -> usize {
+        self.key_count
+    }
+}
+
+impl ResultSize for BtrfsSnapshotInfo {
+    fn result_size(&self) -> usize {
+        1
+    }
+}
+
+impl ResultSize for ImportReport {
+    fn result_size(&self) -> usize {
+        self.imported
+    }
+}
+
+impl ResultSize for RedisImportSummary {
+    fn result_size(&self) -> usize {
+        self.report.imported
+    }
+}
+
+impl ResultSize for ExportSummary {
+    fn result_size(&self) -> usize {
+        self.exported
+    }
+}
+
+impl ResultSize for SqliteExportSummary {
+    fn result_size(&self) -> usize {
+        self.entries
+    }
+}
+
+impl<T: ResultSize> ResultSize for Result<T, DbError> {
+    fn result_size(&self) -> usize {
+        self.as_ref().map(|value| value.result_size()).unwrap_or(0)
+    }
+
+    fn error(&self) -> Option<&DbError> {
+        self.as_ref().err()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Slowlog {
+    config: RwLock<SlowlogConfig>,
+    entries: Mutex<VecDeque<SlowlogEntry>>,
+    next_id: AtomicU64,
+}
+
+impl Slowlog {
+    pub(crate) fn record(&self, operation: &str, path: &str, elapsed: Duration, result: &impl ResultSize) {
+        let config = self.config.read();
+        let duration_us = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        if config.max_len == 0 || duration_us < config.threshold_us {
+            return;
+        }
+
+        let error = result.error().map(|e| e.to_string());
+        log::warn!(
+            target: "rust_db::slowlog",
+            "slow {} path={:?} duration_us={} result_size={} error={:?}",
+            operation, path, duration_us, result.result_size(), error
+        );
+
+        let entry = SlowlogEntry {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp: Utc::now(),
+            operation: operation.to_string(),
+            path: path.to_string(),
+            duration_us,
+            result_size: result.result_size(),
+            error,
+        };
+        let mut entries = self.entries.lock();
+        while entries.len() >= config.max_len {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+impl Database {
+    pub fn set_slowlog_config(&self, config: SlowlogConfig) {
+        // Stejné pořadí zámků jako v record()
+        let mut current = self.slowlog.config.write();
+        let mut entries = self.slowlog.entries.lock();
+        while entries.len() > config.max_len {
+            entries.pop_front();
+        }
+        *current = config;
+    }
+
+    pub fn slowlog_config(&self) -> SlowlogConfig {
+        self.slowlog.config.read().clone()
+    }
+
+    // Newest first, at most `limit` entries
+    pub fn slowlog(&self, limit: usize) -> Vec<SlowlogEntry> {
+        self.slowlog.entries.lock().iter().rev().take(limit).cloned().collect()
+    }
+
+    pub fn slowlog_len(&self) -> usize {
+        self.slowlog.entries.lock().len()
+    }
+
+    pub fn slowlog_reset(&self) {
+        self.slowlog.entries.lock().clear();
+    }
+}
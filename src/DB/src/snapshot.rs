@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+
+use crate::{Database, DbError, Entry, SerializableDb, Value};
+
+struct SnapshotState {
+    revision: u64,
+    // Entries visible at `revision` that writers replaced or removed after the snapshot was taken
+    preserved: Mutex<HashMap<String, Entry>>,
+}
+
+#[derive(Default)]
+pub(crate) struct SnapshotRegistry {
+    next_id: AtomicU64,
+    active: RwLock<HashMap<u64, Arc<SnapshotState>>>,
+}
+
+impl SnapshotRegistry {
+    // Called by writers (under the write gate) before `old` is replaced or removed
+    pub(crate) fn preserve(&self, path: &str, old: &Entry) {
+        let active = self.active.read();
+        for state in active.values() {
+            if old.revision <= state.revision {
+                state.preserved.lock()
+                    .entry(path.to_string())
+                    .or_insert_with(|| old.clone());
+            }
+        }
+    }
+}
+
+// Consistent read-only view of the database at a revision. Writers keep
+// going; entries they overwrite are copied into the snapshot on demand.
+pub struct Snapshot {
+    id: u64,
+    state: Arc<SnapshotState>,
+    registry: Arc<SnapshotRegistry>,
+    data: Arc<DashMap<String, Entry>>,
+    created_at: DateTime<Utc>,
+    last_backup: Option<DateTime<Utc>>,
+    version: String,
+}
+
+impl Snapshot {
+    pub fn revision(&self) -> u64 {
+        self.state.revision
+    }
+
+    // Live entry first, preserved copy second: writers preserve before they
+    // modify the live map, so a newer live revision always has a preserved copy.
+    pub(crate) fn entry(&self, path: &str) -> Option<Entry> {
+        if let Some(entry) = self.data.get(path) {
+            if entry.revision <= self.state.revision {
+                return Some(entry.clone());
+            }
+        }
+        self.state.preserved.lock().get(path).cloned()
+    }
+
+    // Sorted paths that may be visible in the snapshot, without cloning any values;
+    // `entry` decides whether each one actually is
+    pub(crate) fn paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self.data.iter()
+            .map(|entry| entry.key().clone())
+            .collect();
+        paths.extend(self.state.preserved.lock().keys().cloned());
+        paths.sort_unstable();
+        paths.dedup();
+        paths
+    }
+
+    pub(crate) fn entries(&self) -> HashMap<String, Entry> {
+        let mut entries: HashMap<String, Entry> = self.data.iter()
+            .filter(|entry| entry.value().revision <= self.state.revision)
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+
+        // Preserved copies win over anything read from the live map
+        for (path, entry) in self.state.preserved.lock().iter() {
+            entries.insert(path.clone(), entry.clone());
+        }
+        entries
+    }
+
+    pub fn get(&self, path: &str) -> Result<Value, DbError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        match self.entry(path) {
+            Some(entry) if !entry.expiry.map(|exp| exp < now).unwrap_or(false) => Ok(entry.value),
+            _ => Err(DbError::KeyNotFound),
+        }
+    }
+
+    pub fn find_by_path(&self, pattern: &str) -> Result<HashMap<String, Value>, DbError> {
+        let pattern_components: Vec<String> = pattern.split('/')
+            .map(|s| s.to_string())
+            .collect();
+
+        Ok(self.entries()
+            .into_iter()
+            .filter(|(_, entry)| entry.matches_pattern(&pattern_components))
+            .map(|(path, entry)| (path, entry.value))
+            .collect())
+    }
+
+    pub(crate) fn to_serializable(&self) -> SerializableDb {
+        SerializableDb {
+            data: self.entries(),
+            created_at: self.created_at,
+            last_backup: self.last_backup,
+            version: self.version.clone(),
+            revision: self.revision(),
+        }
+    }
+
+    pub fn export_json(&self, export_path: &str) -> Result<(), DbError> {
+        let json = serde_json::to_string_pretty(&self.to_serializable())?;
+        fs::write(export_path, json)?;
+        Ok(())
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        self.registry.active.write().remove(&self.id);
+    }
+}
+
+impl Database {
+    pub fn snapshot(&self) -> Snapshot {
+        let id = self.snapshots.next_id.fetch_add(1, Ordering::SeqCst);
+
+        // Krátké zastavení zápisů, aby revize snapshotu byla čistý řez
+        let state = {
+            let _gate = self.write_gate.write();
+            let state = Arc::new(SnapshotState {
+                revision: self.revision(),
+                preserved: Mutex::new(HashMap::new()),
+            });
+            self.snapshots.active.write().insert(id, state.clone());
+            state
+        };
+
+        Snapshot {
+            id,
+            state,
+            registry: self.snapshots.clone(),
+            data: self.data.clone(),
+            created_at: *self.created_at.read(),
+            last_backup: *self.last_backup.read(),
+            version: self.version.clone(),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::Utc;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::metrics::Op;
+use crate::query::type_name;
+use crate::{Database, DbError, Entry, Value};
+
+// Map values under `pattern` become rows of table `name`, one column per field
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapTableSpec {
+    pub name: String,
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SqliteExportConfig {
+    // Only entries matching this pattern are exported
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub map_tables: Vec<MapTableSpec>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SqliteExportSummary {
+    pub entries: usize,
+    pub map_fields: usize,
+    // rows per configured map table
+    pub tables: BTreeMap<String, usize>,
+}
+
+const SCHEMA: &str = "
+CREATE TABLE meta (
+    key TEXT PRIMARY KEY,
+    value TEXT
+);
+CREATE TABLE entries (
+    path TEXT PRIMARY KEY,
+    components TEXT NOT NULL,
+    depth INTEGER NOT NULL,
+    type TEXT NOT NULL,
+    value,
+    expiry INTEGER,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+CREATE INDEX entries_type ON entries(type);
+CREATE TABLE map_fields (
+    path TEXT NOT NULL REFERENCES entries(path),
+    field TEXT NOT NULL,
+    value TEXT,
+    PRIMARY KEY (path, field)
+);
+CREATE INDEX map_fields_field ON map_fields(field, value);
+";
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+// Map fields become columns. SQLite compares column names case-insensitively, so fields
+// clashing with the path key column or with each other ("Name" and "name") get a numeric suffix.
+fn column_names(fields: &BTreeSet<String>) -> Vec<String> {
+    let mut used = HashSet::from(["path".to_string()]);
+    fields.iter()
+        .map(|field| {
+            let base = match field.as_str() {
+                "" => "field".to_string(),
+                f if f.eq_ignore_ascii_case("path") => format!("{}_field", f),
+                f => f.to_string(),
+            };
+            let mut name = base.clone();
+            let mut suffix = 2;
+            while !used.insert(name.to_ascii_lowercase()) {
+                name = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            name
+        })
+        .collect()
+}
+
+fn pattern_components(pattern: &str) -> Vec<String> {
+    pattern.split('/').map(|s| s.to_string()).collect()
+}
+
+// Scalars keep their SQLite type so analysts can compare and sum them directly
+fn sql_value(value: &Value) -> Result<SqlValue, DbError> {
+    Ok(match value {
+        Value::String(s) => SqlValue::Text(s.clone()),
+        Value::Integer(i) => SqlValue::Integer(*i),
+        Value::Float(f) => SqlValue::Real(*f),
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Null => SqlValue::Null,
+        Value::Array(items) => SqlValue::Text(serde_json::to_string(items)?),
+        Value::Map(map) => SqlValue::Text(serde_json::to_string(&map.iter().collect::<BTreeMap<_, _>>())?),
+    })
+}
+
+impl Database {
+    // Writes a consistent snapshot into a new SQLite file (replaced atomically)
+    pub fn export_sqlite(&self, path: &str, config: &SqliteExportConfig) -> Result<SqliteExportSummary, DbError> {
+        self.observe(Op::Export, path, || self.write_sqlite(path, config))
+    }
+
+    fn write_sqlite(&self, path: &str, config: &SqliteExportConfig) -> Result<SqliteExportSummary, DbError> {
+        for table in &config.map_tables {
+            let valid = !table.name.is_empty()
+                && table.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !["meta", "entries", "map_fields"].contains(&table.name.as_str());
+            if !valid {
+                return Err(DbError::System(format!("Invalid map table name '{}'", table.name)));
+            }
+        }
+
+        let snapshot = self.snapshot();
+        let filter = config.pattern.as_deref().map(pattern_components);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let live = |path: &str| -> Option<Entry> {
+            if let Some(filter) = &filter {
+                if !Entry::path_matches(path, filter) {
+                    return None;
+                }
+            }
+            snapshot.entry(path).filter(|entry| !entry.expiry.map(|exp| exp < now).unwrap_or(false))
+        };
+
+        // První průchod jen zjistí sloupce tabulek pro Mapy
+        let table_patterns: Vec<Vec<String>> = config.map_tables.iter()
+            .map(|t| pattern_components(&t.pattern))
+            .collect();
+        let mut columns: Vec<BTreeSet<String>> = vec![BTreeSet::new(); config.map_tables.len()];
+        let paths = snapshot.paths();
+        if !config.map_tables.is_empty() {
+            for path in &paths {
+                if let Some(Entry { value: Value::Map(map), .. }) = live(path) {
+                    for (i, pattern) in table_patterns.iter().enumerate() {
+                        if Entry::path_matches(path, pattern) {
+                            columns[i].extend(map.keys().cloned());
+                        }
+                    }
+                }
+            }
+        }
+
+        let tmp_path = format!("{}.tmp", path);
+        let _ = fs::remove_file(&tmp_path);
+        let mut conn = Connection::open(&tmp_path)?;
+        conn.execute_batch("PRAGMA journal_mode = OFF; PRAGMA synchronous = OFF;")?;
+        let tx = conn.transaction()?;
+        tx.execute_batch(SCHEMA)?;
+
+        let names: Vec<Vec<String>> = columns.iter().map(column_names).collect();
+        for (table, table_names) in config.map_tables.iter().zip(&names) {
+            let mut ddl = format!("CREATE TABLE {} (path TEXT PRIMARY KEY REFERENCES entries(path)", quote_ident(&table.name));
+            for name in table_names {
+                ddl.push_str(&format!(", {} TEXT", quote_ident(name)));
+            }
+            ddl.push(')');
+            tx.execute(&ddl, [])?;
+        }
+
+        let mut summary = SqliteExportSummary::default();
+        {
+            let mut insert_entry = tx.prepare(
+                "INSERT INTO entries (path, components, depth, type, value, expiry, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            let mut insert_field = tx.prepare("INSERT INTO map_fields (path, field, value) VALUES (?1, ?2, ?3)")?;
+            let mut insert_rows: Vec<_> = config.map_tables.iter()
+                .zip(&names)
+                .map(|(table, table_names)| {
+                    let names: Vec<String> = std::iter::once("path".to_string())
+                        .chain(table_names.iter().map(|name| quote_ident(name)))
+                        .collect();
+                    let placeholders = vec!["?"; names.len()].join(", ");
+                    tx.prepare(&format!(
+                        "INSERT INTO {} ({}) VALUES ({})",
+                        quote_ident(&table.name), names.join(", "), placeholders
+                    ))
+                })
+                .collect::<Result<_, _>>()?;
+
+            for path in &paths {
+                let Some(entry) = live(path) else { continue };
+
+                insert_entry.execute(params![
+                    path,
+                    serde_json::to_string(&entry.path_components)?,
+                    entry.path_components.len() as i64,
+                    type_name(&entry.value),
+                    sql_value(&entry.value)?,
+                    entry.expiry.map(|e| e as i64),
+                    entry.created_at.timestamp(),
+                    entry.updated_at.timestamp(),
+                ])?;
+                summary.entries += 1;
+
+                let Value::Map(map) = &entry.value else { continue };
+                for (field, value) in map {
+                    insert_field.execute(params![path, field, value])?;
+                    summary.map_fields += 1;
+                }
+
+                for (i, table) in config.map_tables.iter().enumerate() {
+                    if !Entry::path_matches(path, &table_patterns[i]) {
+                        continue;
+                    }
+                    let row = std::iter::once(Some(path.clone()))
+                        .chain(columns[i].iter().map(|field| map.get(field).cloned()));
+                    insert_rows[i].execute(params_from_iter(row))?;
+                    *summary.tables.entry(table.name.clone()).or_insert(0) += 1;
+                }
+            }
+
+            let mut insert_meta = tx.prepare("INSERT INTO meta (key, value) VALUES (?1, ?2)")?;
+            insert_meta.execute(params!["version", self.version])?;
+            insert_meta.execute(params!["revision", snapshot.revision().to_string()])?;
+            insert_meta.execute(params!["exported_at", Utc::now().to_rfc3339()])?;
+            insert_meta.execute(params!["pattern", config.pattern])?;
+        }
+        tx.commit()?;
+        drop(conn);
+
+        fs::rename(&tmp_path, path)?;
+        Ok(summary)
+    }
+}
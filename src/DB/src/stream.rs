@@ -0,0 +1,247 @@
+use std::io::{BufRead, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::metrics::Op;
+use crate::query::type_name;
+use crate::{Database, DbError, Entry, ImportOptions, ImportReport, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamFormat {
+    Ndjson,
+    Csv,
+}
+
+impl StreamFormat {
+    pub fn parse(name: &str) -> Result<Self, DbError> {
+        match name.to_ascii_lowercase().as_str() {
+            "ndjson" | "jsonl" => Ok(StreamFormat::Ndjson),
+            "csv" => Ok(StreamFormat::Csv),
+            _ => Err(DbError::System(format!("Unknown stream format '{}'", name))),
+        }
+    }
+}
+
+// One NDJSON line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamRecord {
+    pub path: String,
+    pub value: Value,
+    pub expiry: Option<u64>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub exported: usize,
+    // Arrays and maps have no CSV representation
+    pub skipped: usize,
+}
+
+const CSV_HEADER: &str = "path,type,value,expiry,created_at,updated_at";
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_row(path: &str, entry: &Entry) -> Option<String> {
+    let value = match &entry.value {
+        Value::String(s) => s.clone(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => String::new(),
+        Value::Array(_) | Value::Map(_) => return None,
+    };
+    Some(format!(
+        "{},{},{},{},{},{}\n",
+        csv_field(path),
+        type_name(&entry.value),
+        csv_field(&value),
+        entry.expiry.map(|e| e.to_string()).unwrap_or_default(),
+        entry.created_at.timestamp(),
+        entry.updated_at.timestamp(),
+    ))
+}
+
+// Reads one CSV record, which may span several lines inside quotes
+fn read_csv_record<R: BufRead>(reader: &mut R) -> Result<Option<Vec<String>>, DbError> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            if in_quotes {
+                return Err(DbError::System("Unterminated quoted CSV field".to_string()));
+            }
+            if fields.is_empty() && field.is_empty() {
+                return Ok(None);
+            }
+            fields.push(field);
+            return Ok(Some(fields));
+        }
+
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, in_quotes) {
+                ('"', true) if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                ('"', true) => in_quotes = false,
+                ('"', false) if field.is_empty() => in_quotes = true,
+                (',', false) => fields.push(std::mem::take(&mut field)),
+                ('\n', false) => {
+                    fields.push(field);
+                    return Ok(Some(fields));
+                }
+                ('\r', false) if chars.peek() == Some(&'\n') => {}
+                _ => field.push(c),
+            }
+        }
+    }
+}
+
+fn parse_csv_value(kind: &str, raw: &str) -> Result<Value, DbError> {
+    let invalid = || DbError::System(format!("Invalid {} value '{}' in CSV", kind, raw));
+    match kind {
+        "string" => Ok(Value::String(raw.to_string())),
+        "integer" => raw.parse().map(Value::Integer).map_err(|_| invalid()),
+        "float" => raw.parse().map(Value::Float).map_err(|_| invalid()),
+        "bool" => raw.parse().map(Value::Bool).map_err(|_| invalid()),
+        "null" => Ok(Value::Null),
+        _ => Err(DbError::System(format!("Unsupported CSV value type '{}'", kind))),
+    }
+}
+
+fn parse_timestamp(raw: &str) -> Result<DateTime<Utc>, DbError> {
+    if raw.is_empty() {
+        return Ok(Utc::now());
+    }
+    raw.parse::<i64>()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .ok_or_else(|| DbError::System(format!("Invalid timestamp '{}' in CSV", raw)))
+}
+
+fn csv_entry(fields: Vec<String>) -> Result<(String, Entry), DbError> {
+    let [path, kind, value, expiry, created_at, updated_at]: [String; 6] = fields.try_into()
+        .map_err(|fields: Vec<String>| DbError::System(format!("Expected 6 CSV columns, got {}", fields.len())))?;
+
+    let mut entry = Entry::new(parse_csv_value(&kind, &value)?, &path)?;
+    if !expiry.is_empty() {
+        entry.expiry = Some(expiry.parse().map_err(|_| DbError::System(format!("Invalid expiry '{}' in CSV", expiry)))?);
+    }
+    entry.created_at = parse_timestamp(&created_at)?;
+    entry.updated_at = parse_timestamp(&updated_at)?;
+    Ok((path, entry))
+}
+
+fn ndjson_entry(line: &str) -> Result<(String, Entry), DbError> {
+    let record: StreamRecord = serde_json::from_str(line)?;
+    let mut entry = Entry::new(record.value, &record.path)?;
+    entry.expiry = record.expiry;
+    entry.created_at = record.created_at;
+    entry.updated_at = record.updated_at;
+    Ok((record.path, entry))
+}
+
+impl Database {
+    // Writes entries one by one from a snapshot; only the list of paths is held in memory
+    pub fn export_stream<W: Write>(&self, writer: W, format: StreamFormat, pattern: Option<&str>) -> Result<ExportSummary, DbError> {
+        self.observe(Op::Export, pattern.unwrap_or(""), || self.write_stream(writer, format, pattern))
+    }
+
+    fn write_stream<W: Write>(&self, mut writer: W, format: StreamFormat, pattern: Option<&str>) -> Result<ExportSummary, DbError> {
+        let snapshot = self.snapshot();
+        let pattern: Option<Vec<String>> = pattern.map(|p| p.split('/').map(|s| s.to_string()).collect());
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut summary = ExportSummary::default();
+
+        if format == StreamFormat::Csv {
+            writeln!(writer, "{}", CSV_HEADER)?;
+        }
+
+        for path in snapshot.paths() {
+            if let Some(pattern) = &pattern {
+                if !Entry::path_matches(&path, pattern) {
+                    continue;
+                }
+            }
+            let entry = match snapshot.entry(&path) {
+                Some(entry) if !entry.expiry.map(|exp| exp < now).unwrap_or(false) => entry,
+                _ => continue,
+            };
+
+            match format {
+                StreamFormat::Ndjson => {
+                    let record = StreamRecord {
+                        path,
+                        value: entry.value,
+                        expiry: entry.expiry,
+                        created_at: entry.created_at,
+                        updated_at: entry.updated_at,
+                    };
+                    serde_json::to_writer(&mut writer, &record)?;
+                    writer.write_all(b"\n")?;
+                }
+                StreamFormat::Csv => match csv_row(&path, &entry) {
+                    Some(row) => writer.write_all(row.as_bytes())?,
+                    None => {
+                        summary.skipped += 1;
+                        continue;
+                    }
+                },
+            }
+            summary.exported += 1;
+        }
+
+        writer.flush()?;
+        Ok(summary)
+    }
+
+    // Reads and applies one record at a time using the same modes as import_json_with
+    pub fn import_stream<R: BufRead>(&self, reader: R, format: StreamFormat, options: &ImportOptions) -> Result<ImportReport, DbError> {
+        self.observe(Op::Import, "", || self.read_stream(reader, format, options))
+    }
+
+    fn read_stream<R: BufRead>(&self, mut reader: R, format: StreamFormat, options: &ImportOptions) -> Result<ImportReport, DbError> {
+        match format {
+            StreamFormat::Ndjson => {
+                let entries = reader.lines()
+                    .filter(|line| !matches!(line, Ok(l) if l.trim().is_empty()))
+                    .map(|line| ndjson_entry(&line?));
+                self.import_iter(entries, options)
+            }
+            StreamFormat::Csv => {
+                // Hlavička je volitelná
+                let first = read_csv_record(&mut reader)?;
+                let header_skipped = first.as_ref().map(|f| f.join(",") == CSV_HEADER).unwrap_or(true);
+                let first = if header_skipped { None } else { first.map(Ok) };
+
+                let rest = std::iter::from_fn(move || read_csv_record(&mut reader).transpose());
+                let entries = first.into_iter()
+                    .chain(rest)
+                    .filter(|record| !matches!(record, Ok(fields) if fields.len() == 1 && fields[0].trim().is_empty()))
+                    .map(|record| csv_entry(record?));
+                self.import_iter(entries, options)
+            }
+        }
+    }
+}
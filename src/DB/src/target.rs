@@ -0,0 +1,379 @@
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::storage::write_atomic;
+use crate::DbError;
+
+// Where backups and exports are written to
+pub trait BackupTarget: Send + Sync {
+    fn put(&self, name: &str, data: &[u8]) -> Result<(), DbError>;
+    fn get(&self, name: &str) -> Result<Vec<u8>, DbError>;
+    fn list(&self, prefix: &str) -> Result<Vec<String>, DbError>;
+    fn delete(&self, name: &str) -> Result<(), DbError>;
+}
+
+pub struct LocalTarget {
+    root: PathBuf,
+}
+
+impl LocalTarget {
+    pub fn new(root: &str) -> Self {
+        LocalTarget {
+            root: PathBuf::from(root),
+        }
+    }
+}
+
+impl BackupTarget for LocalTarget {
+    fn put(&self, name: &str, data: &[u8]) -> Result<(), DbError> {
+        let path = self.root.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Přepsání zálohy na místě by po pádu nechalo jen její začátek
+        write_atomic(&path, data)
+    }
+
+    fn get(&self, name: &str) -> Result<Vec<u8>, DbError> {
+        Ok(fs::read(self.root.join(name))?)
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>, DbError> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<String> = fs::read_dir(&self.root)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            // Pozůstatky přerušeného put()
+            .filter(|name| name.starts_with(prefix) && !name.ends_with(".tmp"))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn delete(&self, name: &str) -> Result<(), DbError> {
+        fs::remove_file(self.root.join(name))?;
+        Ok(())
+    }
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+// S3 rejects smaller parts except for the last one
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+fn default_part_size() -> usize {
+    8 * 1024 * 1024
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3Config {
+    // e.g. http://minio:9000 or https://gateway/s3, buckets are addressed path-style
+    // below the endpoint's path
+    pub endpoint: String,
+    pub bucket: String,
+    pub access_key: String,
+    pub secret_key: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    // key prefix inside the bucket, e.g. "rust-db/backups"
+    #[serde(default)]
+    pub prefix: String,
+    // objects larger than this are sent as multipart uploads (S3 minimum part size is 5 MiB)
+    #[serde(default = "default_part_size")]
+    pub part_size: usize,
+}
+
+pub struct S3Target {
+    config: S3Config,
+    scheme: String,
+    // Authority as sent in the Host header
+    host: String,
+    // Path of the endpoint, empty or starting with '/'
+    base_path: String,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// RFC 3986 encoding as required by SigV4
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+// &amp; goes last, otherwise "&amp;lt;" would turn into "<" instead of "&lt;"
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// Pulls the text of every <tag>...</tag> out of an S3 XML response
+fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut values = Vec::new();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        match rest.find(&close) {
+            Some(end) => {
+                values.push(xml_unescape(&rest[..end]));
+                rest = &rest[end + close.len()..];
+            }
+            None => break,
+        }
+    }
+    values
+}
+
+// Splits "scheme://host[:port][/path]" into its parts; the default port is dropped so the
+// signed host matches the Host header ureq sends
+fn parse_endpoint(endpoint: &str) -> Result<(String, String, String), DbError> {
+    let invalid = |reason: &str| DbError::System(format!("Invalid S3 endpoint '{}': {}", endpoint, reason));
+    let (scheme, rest) = endpoint.split_once("://").ok_or_else(|| invalid("expected http:// or https://"))?;
+    let scheme = scheme.to_ascii_lowercase();
+    let default_port = match scheme.as_str() {
+        "http" => ":80",
+        "https" => ":443",
+        _ => return Err(invalid("expected http:// or https://")),
+    };
+    if rest.contains(['?', '#', '@']) {
+        return Err(invalid("credentials, query and fragment are not supported"));
+    }
+    let (authority, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    let host = authority.strip_suffix(default_port).unwrap_or(authority);
+    if host.is_empty() || host.starts_with(':') {
+        return Err(invalid("missing host"));
+    }
+    let path = path.trim_end_matches('/');
+    if path.split('/').skip(1).any(|segment| segment.is_empty() || segment == "." || segment == "..") {
+        return Err(invalid("malformed path"));
+    }
+    Ok((scheme, host.to_string(), path.to_string()))
+}
+
+fn s3_error(e: ureq::Error) -> DbError {
+    match e {
+        ureq::Error::Status(status, response) => {
+            let body = response.into_string().unwrap_or_default();
+            let code = xml_values(&body, "Code").into_iter().next().unwrap_or_default();
+            DbError::System(format!("S3 request failed with status {}: {}", status, code))
+        }
+        ureq::Error::Transport(transport) => DbError::System(format!("S3 transport error: {}", transport)),
+    }
+}
+
+impl S3Target {
+    pub fn new(config: S3Config) -> Result<Self, DbError> {
+        if config.bucket.is_empty() {
+            return Err(DbError::System("S3 bucket is required".to_string()));
+        }
+        let (scheme, host, base_path) = parse_endpoint(&config.endpoint)?;
+        if config.part_size < MIN_PART_SIZE {
+            return Err(DbError::System(format!(
+                "S3 part_size must be at least {} bytes, got {}",
+                MIN_PART_SIZE, config.part_size
+            )));
+        }
+        Ok(S3Target {
+            config,
+            scheme,
+            host,
+            base_path,
+        })
+    }
+
+    fn object_key(&self, name: &str) -> String {
+        let prefix = self.config.prefix.trim_matches('/');
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", prefix, name)
+        }
+    }
+
+    fn request(&self, method: &str, key: Option<&str>, query: &[(&str, &str)], body: &[u8]) -> Result<ureq::Response, DbError> {
+        let path = match key {
+            Some(key) => format!("{}/{}/{}", self.base_path, self.config.bucket, key),
+            None => format!("{}/{}", self.base_path, self.config.bucket),
+        };
+        let canonical_uri = uri_encode(&path, false);
+
+        let mut query: Vec<(String, String)> = query.iter()
+            .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+            .collect();
+        query.sort();
+        let canonical_query = query.iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = sha256_hex(body);
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+            method, canonical_uri, canonical_query, self.host, payload_hash, amz_date, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date, scope, sha256_hex(canonical_request.as_bytes())
+        );
+
+        let k_date = hmac_sha256(format!("AWS4{}", self.config.secret_key).as_bytes(), &date);
+        let k_region = hmac_sha256(&k_date, &self.config.region);
+        let k_service = hmac_sha256(&k_region, "s3");
+        let k_signing = hmac_sha256(&k_service, "aws4_request");
+        let signature = hex(&hmac_sha256(&k_signing, &string_to_sign));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+            self.config.access_key, scope, signature
+        );
+
+        let mut url = format!("{}://{}{}", self.scheme, self.host, canonical_uri);
+        if !canonical_query.is_empty() {
+            url.push('?');
+            url.push_str(&canonical_query);
+        }
+
+        ureq::request(method, &url)
+            .set("x-amz-date", &amz_date)
+            .set("x-amz-content-sha256", &payload_hash)
+            .set("Authorization", &authorization)
+            .send_bytes(body)
+            .map_err(s3_error)
+    }
+
+    fn put_multipart(&self, key: &str, data: &[u8]) -> Result<(), DbError> {
+        let response = self.request("POST", Some(key), &[("uploads", "")], &[])?;
+        let body = response.into_string()?;
+        let upload_id = xml_values(&body, "UploadId").into_iter().next()
+            .ok_or_else(|| DbError::System("S3 did not return an UploadId".to_string()))?;
+
+        let mut parts = Vec::new();
+        for (index, chunk) in data.chunks(self.config.part_size).enumerate() {
+            let part_number = (index + 1).to_string();
+            let result = self.request(
+                "PUT",
+                Some(key),
+                &[("partNumber", &part_number), ("uploadId", &upload_id)],
+                chunk,
+            );
+            match result {
+                Ok(response) => {
+                    let etag = response.header("ETag").unwrap_or_default().to_string();
+                    parts.push(format!("<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>", part_number, etag));
+                }
+                Err(e) => {
+                    // Nedokončený upload by v bucketu zabíral místo
+                    let _ = self.request("DELETE", Some(key), &[("uploadId", &upload_id)], &[]);
+                    return Err(e);
+                }
+            }
+        }
+
+        let complete = format!(
+            "<CompleteMultipartUpload>{}</CompleteMultipartUpload>",
+            parts.concat()
+        );
+        let response = self.request("POST", Some(key), &[("uploadId", &upload_id)], complete.as_bytes())?;
+
+        // S3 can report a failed completion with status 200 and an <Error> body
+        let body = response.into_string()?;
+        if body.contains("<Error>") {
+            let code = xml_values(&body, "Code").into_iter().next().unwrap_or_default();
+            return Err(DbError::System(format!("S3 multipart upload failed: {}", code)));
+        }
+        Ok(())
+    }
+}
+
+impl BackupTarget for S3Target {
+    fn put(&self, name: &str, data: &[u8]) -> Result<(), DbError> {
+        let key = self.object_key(name);
+        if data.len() > self.config.part_size {
+            return self.put_multipart(&key, data);
+        }
+        self.request("PUT", Some(&key), &[], data)?;
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<Vec<u8>, DbError> {
+        let response = self.request("GET", Some(&self.object_key(name)), &[], &[])?;
+        let mut data = Vec::new();
+        response.into_reader().read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>, DbError> {
+        let full_prefix = self.object_key(prefix);
+        let strip = self.object_key("");
+        let mut names = Vec::new();
+        let mut token: Option<String> = None;
+
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", full_prefix.as_str())];
+            if let Some(token) = &token {
+                query.push(("continuation-token", token.as_str()));
+            }
+            let body = self.request("GET", None, &query, &[])?.into_string()?;
+
+            names.extend(xml_values(&body, "Key")
+                .into_iter()
+                .map(|key| key.strip_prefix(&strip).unwrap_or(&key).to_string()));
+
+            token = xml_values(&body, "NextContinuationToken").into_iter().next();
+            if token.is_none() {
+                break;
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn delete(&self, name: &str) -> Result<(), DbError> {
+        self.request("DELETE", Some(&self.object_key(name)), &[], &[])?;
+        Ok(())
+    }
+}
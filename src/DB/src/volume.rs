@@ -0,0 +1,513 @@
+use std::fs::{self, File, OpenOptions};
+use std::os::raw::{c_int, c_long};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Component, Path, PathBuf};
+
+use nix::errno::Errno;
+use nix::mount::{mount, umount, MsFlags};
+use nix::sys::statvfs::statvfs;
+use nix::unistd::geteuid;
+use serde::{Deserialize, Serialize};
+
+use crate::btrfs::{run_command, DEFAULT_BTRFS_BIN};
+use crate::{Database, DbError};
+
+const MIB: u64 = 1024 * 1024;
+
+// linux/loop.h
+const LO_FLAGS_AUTOCLEAR: u32 = 4;
+const LO_NAME_SIZE: usize = 64;
+// linux/fs.h
+const FS_NOCOW_FL: c_int = 0x0080_0000;
+
+#[repr(C)]
+struct LoopInfo64 {
+    lo_device: u64,
+    lo_inode: u64,
+    lo_rdevice: u64,
+    lo_offset: u64,
+    lo_sizelimit: u64,
+    lo_number: u32,
+    lo_encrypt_type: u32,
+    lo_encrypt_key_size: u32,
+    lo_flags: u32,
+    lo_file_name: [u8; LO_NAME_SIZE],
+    lo_crypt_name: [u8; LO_NAME_SIZE],
+    lo_encrypt_key: [u8; 32],
+    lo_init: [u64; 2],
+}
+
+nix::ioctl_none_bad!(loop_ctl_get_free, 0x4C82);
+nix::ioctl_write_int_bad!(loop_set_fd, 0x4C00);
+nix::ioctl_none_bad!(loop_clr_fd, 0x4C01);
+nix::ioctl_write_ptr_bad!(loop_set_status64, 0x4C04, LoopInfo64);
+nix::ioctl_none_bad!(loop_set_capacity, 0x4C07);
+// Jádro s příznaky pracuje jako s int, i když číslo ioctl počítá s long
+nix::ioctl_read_bad!(fs_ioc_getflags, nix::request_code_read!(b'f', 1, std::mem::size_of::<c_long>()), c_int);
+nix::ioctl_write_ptr_bad!(fs_ioc_setflags, nix::request_code_write!(b'f', 2, std::mem::size_of::<c_long>()), c_int);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VolumeConfig {
+    pub size_mb: u64,
+    // zlib, lzo or zstd, optionally with a level (zstd:3)
+    pub compression: Option<String>,
+    // compress-force instead of compress
+    pub force_compression: bool,
+    pub noatime: bool,
+    // Files or directories (relative to the mount point) created with copy-on-write
+    // disabled, e.g. the database file; directories pass it on to new files
+    pub nodatacow: Vec<String>,
+    // Passed to mount as they are
+    pub extra_options: Vec<String>,
+    // btrfs-progs executable used for resizing
+    pub btrfs_bin: String,
+}
+
+impl Default for VolumeConfig {
+    fn default() -> Self {
+        VolumeConfig {
+            size_mb: 256,
+            compression: None,
+            force_compression: false,
+            noatime: true,
+            nodatacow: Vec::new(),
+            extra_options: Vec::new(),
+            btrfs_bin: DEFAULT_BTRFS_BIN.to_string(),
+        }
+    }
+}
+
+impl VolumeConfig {
+    pub fn mount_options(&self) -> Result<Option<String>, DbError> {
+        let mut options = Vec::new();
+        if let Some(compression) = &self.compression {
+            let (algorithm, level) = match compression.split_once(':') {
+                Some((algorithm, level)) => (algorithm, Some(level)),
+                None => (compression.as_str(), None),
+            };
+            let valid = ["zlib", "lzo", "zstd", "no"].contains(&algorithm)
+                && level.map(|l| l.parse::<u8>().is_ok() && algorithm != "lzo").unwrap_or(true);
+            if !valid {
+                return Err(DbError::System(format!("Unsupported compression '{}'", compression)));
+            }
+            let option = if self.force_compression { "compress-force" } else { "compress" };
+            options.push(format!("{}={}", option, compression));
+        }
+        options.extend(self.extra_options.iter().cloned());
+        Ok(if options.is_empty() { None } else { Some(options.join(",")) })
+    }
+
+    fn flags(&self) -> MsFlags {
+        if self.noatime {
+            MsFlags::MS_NOATIME
+        } else {
+            MsFlags::empty()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeKind {
+    // Loop mounted Btrfs image
+    Btrfs,
+    // Fallback without privileges: plain directory on the host filesystem
+    Directory,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolumeUsage {
+    pub kind: VolumeKind,
+    pub mounted: bool,
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub free_bytes: u64,
+    // Bytes the image really occupies on the host (it may be sparse)
+    pub image_bytes: Option<u64>,
+}
+
+// Storage volume behind a mount point; the image lives next to it as `<mount point>.img`
+#[derive(Debug, Clone)]
+pub struct Volume {
+    mount_point: PathBuf,
+    image: PathBuf,
+    kind: VolumeKind,
+    config: VolumeConfig,
+}
+
+fn volume_bytes(size_mb: u64) -> Result<u64, DbError> {
+    size_mb.checked_mul(MIB)
+        .ok_or_else(|| DbError::System(format!("Volume size {} MB is too large", size_mb)))
+}
+
+fn path_str(path: &Path) -> Result<&str, DbError> {
+    path.to_str().ok_or_else(|| DbError::System("Invalid path".to_string()))
+}
+
+fn image_path(mount_point: &Path) -> Result<PathBuf, DbError> {
+    let name = mount_point.file_name().ok_or(DbError::InvalidPath)?;
+    Ok(mount_point.with_file_name(format!("{}.img", name.to_string_lossy())))
+}
+
+// mountinfo escapes spaces and friends as \040 style octal sequences
+fn unescape_mountinfo(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() && bytes[i + 1..i + 4].iter().all(|b| (b'0'..=b'7').contains(b)) {
+            out.push((bytes[i + 1] - b'0') * 64 + (bytes[i + 2] - b'0') * 8 + (bytes[i + 3] - b'0'));
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// (fstype, source) of the filesystem mounted exactly at `path`, if any
+pub(crate) fn mount_entry(path: &Path) -> Option<(String, String)> {
+    let path = fs::canonicalize(path).ok()?;
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo").ok()?;
+    // Poslední záznam vyhrává, mounty se mohou překrývat
+    mountinfo.lines().rev().find_map(|line| {
+        let (left, right) = line.split_once(" - ")?;
+        let mount_point = left.split(' ').nth(4)?;
+        if Path::new(&unescape_mountinfo(mount_point)) != path {
+            return None;
+        }
+        let mut right = right.split(' ');
+        let fstype = right.next()?.to_string();
+        let source = unescape_mountinfo(right.next()?);
+        Some((fstype, source))
+    })
+}
+
+fn is_privileged() -> bool {
+    geteuid().is_root()
+}
+
+fn open_rw(path: &Path) -> Result<File, DbError> {
+    Ok(OpenOptions::new().read(true).write(true).open(path)?)
+}
+
+// Image file a loop device is bound to, None for a free device
+fn loop_backing_file(device: &Path) -> Option<PathBuf> {
+    let name = device.file_name()?.to_str()?;
+    if !name.starts_with("loop") {
+        return None;
+    }
+    let backing = fs::read_to_string(format!("/sys/block/{}/loop/backing_file", name)).ok()?;
+    let backing = backing.trim_end_matches('\n');
+    Some(PathBuf::from(backing.strip_suffix(" (deleted)").unwrap_or(backing)))
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+// Binds the image to a free loop device; it detaches itself once the last user
+// (the mount) goes away. The returned handle is that user until mount(2) takes over,
+// so it has to stay open until the mount returns.
+fn attach_loop(image: &Path) -> Result<(PathBuf, File), DbError> {
+    let control = open_rw(Path::new("/dev/loop-control"))?;
+    let backing = open_rw(image)?;
+
+    let mut info: LoopInfo64 = unsafe { std::mem::zeroed() };
+    info.lo_flags = LO_FLAGS_AUTOCLEAR;
+    let name = image.as_os_str().as_encoded_bytes();
+    let len = name.len().min(LO_NAME_SIZE - 1);
+    info.lo_file_name[..len].copy_from_slice(&name[..len]);
+
+    // Volné zařízení může mezitím zabrat někdo jiný
+    for _ in 0..8 {
+        let number = unsafe { loop_ctl_get_free(control.as_raw_fd()) }?;
+        let device = PathBuf::from(format!("/dev/loop{}", number));
+        let loop_file = open_rw(&device)?;
+        match unsafe { loop_set_fd(loop_file.as_raw_fd(), backing.as_raw_fd()) } {
+            Ok(_) => {}
+            Err(Errno::EBUSY) => continue,
+            Err(e) => return Err(e.into()),
+        }
+        if let Err(e) = unsafe { loop_set_status64(loop_file.as_raw_fd(), &info) } {
+            let _ = unsafe { loop_clr_fd(loop_file.as_raw_fd()) };
+            return Err(e.into());
+        }
+        return Ok((device, loop_file));
+    }
+    Err(DbError::System("No free loop device".to_string()))
+}
+
+// chattr +C; Btrfs only honours it on empty files and on directories
+fn set_nodatacow(path: &Path) -> Result<(), DbError> {
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        File::create(path)?;
+    }
+    let file = File::open(path)?;
+    let mut flags: c_int = 0;
+    unsafe { fs_ioc_getflags(file.as_raw_fd(), &mut flags) }?;
+    if flags & FS_NOCOW_FL != 0 {
+        return Ok(());
+    }
+    let metadata = file.metadata()?;
+    if metadata.is_file() && metadata.len() > 0 {
+        return Err(DbError::System(format!(
+            "nodatacow can only be set on an empty file or a directory: {}",
+            path.display()
+        )));
+    }
+    flags |= FS_NOCOW_FL;
+    unsafe { fs_ioc_setflags(file.as_raw_fd(), &flags) }?;
+    Ok(())
+}
+
+impl Volume {
+    pub fn create(mount_point: &str, size_mb: u64) -> Result<Volume, DbError> {
+        Self::create_with(mount_point, &VolumeConfig {
+            size_mb,
+            ..Default::default()
+        })
+    }
+
+    // Creates and mounts the volume, reusing an existing image or mount. Without root
+    // a fresh volume falls back to a plain directory.
+    pub fn create_with(mount_point: &str, config: &VolumeConfig) -> Result<Volume, DbError> {
+        let mount_point = PathBuf::from(mount_point);
+        let image = image_path(&mount_point)?;
+        let mut volume = Volume {
+            mount_point,
+            image,
+            kind: VolumeKind::Btrfs,
+            config: config.clone(),
+        };
+        // Chybnou konfiguraci odmítneme dřív, než vznikne image
+        config.mount_options()?;
+        let nodatacow = config.nodatacow.iter()
+            .map(|relative| {
+                let path = Path::new(relative);
+                if relative.is_empty() || path.components().any(|c| !matches!(c, Component::Normal(_))) {
+                    return Err(DbError::InvalidPath);
+                }
+                Ok(volume.mount_point.join(path))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if volume.mounted_device()?.is_none() {
+            if !volume.image.exists() {
+                if !is_privileged() {
+                    fs::create_dir_all(&volume.mount_point)?;
+                    volume.kind = VolumeKind::Directory;
+                    return Ok(volume);
+                }
+                if config.size_mb == 0 {
+                    return Err(DbError::System("Volume size must be positive".to_string()));
+                }
+                volume.create_image(config.size_mb)?;
+            }
+            volume.mount()?;
+        }
+
+        for path in &nodatacow {
+            set_nodatacow(path)?;
+        }
+        Ok(volume)
+    }
+
+    // Describes an existing volume without mounting or creating anything
+    pub fn open(mount_point: &str) -> Result<Volume, DbError> {
+        Self::open_with(mount_point, &VolumeConfig::default())
+    }
+
+    // Like open(); the config only supplies settings used later, e.g. btrfs_bin for resize
+    pub fn open_with(mount_point: &str, config: &VolumeConfig) -> Result<Volume, DbError> {
+        let mount_point = PathBuf::from(mount_point);
+        let image = image_path(&mount_point)?;
+        let mut volume = Volume {
+            mount_point,
+            image,
+            kind: VolumeKind::Btrfs,
+            config: config.clone(),
+        };
+        // Cizí mount se nesmí vydávat za svazek, unmount by ho jinak odpojil
+        let mounted = volume.mounted_device()?.is_some();
+        if !mounted && !volume.image.exists() {
+            if !volume.mount_point.is_dir() {
+                return Err(DbError::System(format!("No volume at {}", volume.mount_point.display())));
+            }
+            volume.kind = VolumeKind::Directory;
+        }
+        Ok(volume)
+    }
+
+    // Loop device of our image mounted at the mount point. Any other filesystem mounted
+    // there is an error rather than a volume.
+    fn mounted_device(&self) -> Result<Option<PathBuf>, DbError> {
+        let Some((fstype, source)) = mount_entry(&self.mount_point) else {
+            return Ok(None);
+        };
+        let device = PathBuf::from(source);
+        let ours = fstype == "btrfs"
+            && loop_backing_file(&device).is_some_and(|backing| same_file(&backing, &self.image));
+        if !ours {
+            return Err(DbError::System(format!(
+                "{} is mounted from {} ({}), not from {}",
+                self.mount_point.display(), device.display(), fstype, self.image.display()
+            )));
+        }
+        Ok(Some(device))
+    }
+
+    pub fn kind(&self) -> VolumeKind {
+        self.kind
+    }
+
+    pub fn mount_point(&self) -> &Path {
+        &self.mount_point
+    }
+
+    pub fn image(&self) -> Option<&Path> {
+        match self.kind {
+            VolumeKind::Btrfs => Some(&self.image),
+            VolumeKind::Directory => None,
+        }
+    }
+
+    pub fn is_mounted(&self) -> bool {
+        match self.kind {
+            VolumeKind::Btrfs => matches!(self.mounted_device(), Ok(Some(_))),
+            VolumeKind::Directory => true,
+        }
+    }
+
+    // Formats into a temporary file first so a failed mkfs never leaves a reusable image
+    fn create_image(&self, size_mb: u64) -> Result<(), DbError> {
+        let size = volume_bytes(size_mb)?;
+        if let Some(parent) = self.image.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.image.with_extension("img.tmp");
+        let tmp_str = path_str(&tmp)?;
+        let _ = fs::remove_file(&tmp);
+
+        // Řídký soubor, místo na disku se přiděluje až při zápisu
+        let result = File::create(&tmp)
+            .and_then(|file| file.set_len(size))
+            .map_err(DbError::from)
+            .and_then(|_| run_command("mkfs.btrfs", &["-q", tmp_str]));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+        fs::rename(&tmp, &self.image)?;
+        Ok(())
+    }
+
+    fn mount(&self) -> Result<(), DbError> {
+        fs::create_dir_all(&self.mount_point)?;
+        let options = self.config.mount_options()?;
+        let (device, loop_file) = attach_loop(&self.image)?;
+        let result = mount(
+            Some(&device),
+            &self.mount_point,
+            Some("btrfs"),
+            self.config.flags(),
+            options.as_deref(),
+        );
+        if let Err(e) = result {
+            let _ = unsafe { loop_clr_fd(loop_file.as_raw_fd()) };
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    // The image and its data stay in place, create() mounts it again. The loop device
+    // was attached with autoclear and detaches itself after the umount.
+    pub fn unmount(&self) -> Result<(), DbError> {
+        if self.kind == VolumeKind::Directory {
+            return Ok(());
+        }
+        if self.mounted_device()?.is_some() {
+            umount(&self.mount_point)?;
+        }
+        Ok(())
+    }
+
+    // Grows or shrinks the image together with the mounted filesystem
+    pub fn resize(&self, size_mb: u64) -> Result<(), DbError> {
+        if self.kind == VolumeKind::Directory {
+            return Err(DbError::System("A directory volume has no fixed size".to_string()));
+        }
+        let Some(device) = self.mounted_device()? else {
+            return Err(DbError::System(format!("{} is not mounted", self.mount_point.display())));
+        };
+        if size_mb == 0 {
+            return Err(DbError::System("Volume size must be positive".to_string()));
+        }
+
+        let new_size = volume_bytes(size_mb)?;
+        let current = fs::metadata(&self.image)?.len();
+        let mount_point = path_str(&self.mount_point)?;
+        let image = OpenOptions::new().write(true).open(&self.image)?;
+        let refresh_loop = || -> Result<(), DbError> {
+            let loop_file = open_rw(&device)?;
+            unsafe { loop_set_capacity(loop_file.as_raw_fd()) }?;
+            Ok(())
+        };
+
+        if new_size >= current {
+            image.set_len(new_size)?;
+            refresh_loop()?;
+            run_command(&self.config.btrfs_bin, &["filesystem", "resize", "max", mount_point])?;
+        } else {
+            // Filesystem first, the image only afterwards
+            run_command(&self.config.btrfs_bin, &["filesystem", "resize", &new_size.to_string(), mount_point])?;
+            image.set_len(new_size)?;
+            refresh_loop()?;
+        }
+        Ok(())
+    }
+
+    pub fn usage(&self) -> Result<VolumeUsage, DbError> {
+        let mounted = self.is_mounted();
+        let (total_bytes, used_bytes, free_bytes) = if mounted {
+            let stat = statvfs(&self.mount_point)?;
+            let fragment = stat.fragment_size() as u64;
+            let blocks = stat.blocks() as u64;
+            (
+                blocks * fragment,
+                (blocks - stat.blocks_free() as u64) * fragment,
+                stat.blocks_available() as u64 * fragment,
+            )
+        } else {
+            (0, 0, 0)
+        };
+        let image_bytes = match self.image() {
+            Some(image) => Some(fs::metadata(image)?.blocks() * 512),
+            None => None,
+        };
+
+        Ok(VolumeUsage {
+            kind: self.kind,
+            mounted,
+            total_bytes,
+            used_bytes,
+            free_bytes,
+            image_bytes,
+        })
+    }
+}
+
+impl Database {
+    pub fn init_btrfs_volume(mount_path: &str, size_mb: u64) -> Result<Volume, DbError> {
+        Volume::create(mount_path, size_mb)
+    }
+}
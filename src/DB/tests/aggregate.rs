@@ -0,0 +1,35 @@
+mod common;
+
+use rust_db::{Database, Value};
+
+#[test]
+fn aggregates_numbers_types_and_groups() {
+    let dir = common::temp_dir("aggregate");
+    let db = Database::new(dir.join("db.json").to_str().unwrap());
+    db.set("user/1/orders", Value::Integer(3)).unwrap();
+    db.set("user/1/spent", Value::Float(120.5)).unwrap();
+    db.set("user/2/orders", Value::String("7".to_string())).unwrap();
+    db.set("user/2/spent", Value::Float(-20.5)).unwrap();
+    db.set("user/3/orders", Value::String("none".to_string())).unwrap();
+    db.set("user/4/orders", Value::Integer(100)).unwrap();
+    db.set_expiry("user/4/orders", 0).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+
+    let orders = db.aggregate("user/*/orders").unwrap();
+    // Expired keys are skipped, numeric strings count as numbers
+    assert_eq!(orders.count, 3);
+    assert_eq!(orders.numeric_count, 2);
+    assert_eq!(orders.sum, 10.0);
+    assert_eq!((orders.min, orders.max, orders.avg), (Some(3.0), Some(7.0), Some(5.0)));
+    assert_eq!(orders.types.get("string"), Some(&2));
+    assert_eq!(orders.types.get("integer"), Some(&1));
+
+    let by_user = db.aggregate_by("user/*/*", 1).unwrap();
+    assert_eq!(by_user.keys().collect::<Vec<_>>(), vec!["1", "2", "3"]);
+    assert_eq!(by_user["1"].sum, 123.5);
+    assert_eq!(by_user["2"].min, Some(-20.5));
+    assert_eq!(by_user["3"].avg, None);
+
+    let empty = db.aggregate("missing/*").unwrap();
+    assert_eq!((empty.count, empty.avg), (0, None));
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use rust_db::{
+    backup_name, prune_backups, BackupScheduler, BackupTarget, CronSchedule, Database, LocalTarget,
+    RetentionPolicy, ScheduleConfig, Value,
+};
+
+#[test]
+fn cron_finds_next_matching_minute() {
+    let from = Utc.with_ymd_and_hms(2024, 3, 15, 10, 30, 20).unwrap();
+
+    let nightly = CronSchedule::parse("0 3 * * *").unwrap();
+    assert_eq!(nightly.next_after(from), Utc.with_ymd_and_hms(2024, 3, 16, 3, 0, 0).single());
+
+    let quarter = CronSchedule::parse("*/15 * * * *").unwrap();
+    assert_eq!(quarter.next_after(from), Utc.with_ymd_and_hms(2024, 3, 15, 10, 45, 0).single());
+
+    // 2024-03-15 is a Friday, the next Sunday is the 17th
+    let sunday = CronSchedule::parse("30 1 * * 7").unwrap();
+    assert_eq!(sunday.next_after(from), Utc.with_ymd_and_hms(2024, 3, 17, 1, 30, 0).single());
+
+    let yearly = CronSchedule::parse("0 0 1 1 *").unwrap();
+    assert_eq!(yearly.next_after(from), Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).single());
+
+    assert!(CronSchedule::parse("61 * * * *").is_err());
+    assert!(CronSchedule::parse("* * *").is_err());
+}
+
+#[test]
+fn local_target_replaces_objects_atomically() {
+    let dir = common::temp_dir("schedule_local");
+    let target = LocalTarget::new(dir.join("backups").to_str().unwrap());
+    target.put("nightly_1.json", b"{\"old\": true}").unwrap();
+    target.put("nightly_1.json", b"{}").unwrap();
+    assert_eq!(target.get("nightly_1.json").unwrap(), b"{}");
+
+    // Left behind by a put() interrupted by a crash
+    std::fs::write(dir.join("backups/nightly_2.json.tmp"), b"{\"trunc").unwrap();
+    assert_eq!(target.list("nightly").unwrap(), vec!["nightly_1.json"]);
+}
+
+#[test]
+fn retention_keeps_last_daily_and_weekly() {
+    let dir = common::temp_dir("schedule_prune");
+    let target = LocalTarget::new(dir.to_str().unwrap());
+    let now = Utc.with_ymd_and_hms(2024, 3, 31, 12, 0, 0).unwrap();
+
+    // Two backups a day for the last 40 days
+    for day in 0..40 {
+        for hour in [1, 13] {
+            let time = now - chrono::Duration::days(day) - chrono::Duration::hours(12) + chrono::Duration::hours(hour);
+            target.put(&backup_name("nightly", time), b"{}").unwrap();
+        }
+    }
+    target.put("unrelated.json", b"{}").unwrap();
+
+    let policy = RetentionPolicy {
+        keep_last: 3,
+        keep_daily: 7,
+        keep_weekly: 4,
+    };
+    let pruned = prune_backups(&target, "nightly", &policy, now).unwrap();
+    assert!(!pruned.is_empty());
+
+    let left = target.list("nightly").unwrap();
+    assert!(left.contains(&backup_name("nightly", Utc.with_ymd_and_hms(2024, 3, 31, 1, 0, 0).unwrap())));
+    assert!(left.contains(&backup_name("nightly", Utc.with_ymd_and_hms(2024, 3, 30, 13, 0, 0).unwrap())));
+    assert!(!left.contains(&backup_name("nightly", Utc.with_ymd_and_hms(2024, 2, 25, 13, 0, 0).unwrap())));
+    // 3 last + one per remaining day of the week + one per older week
+    assert!(left.len() <= 3 + 7 + 4, "{:?}", left);
+    assert!(target.list("unrelated").unwrap().len() == 1);
+}
+
+#[test]
+fn interval_scheduler_writes_timestamped_backups() {
+    let dir = common::temp_dir("schedule_interval");
+    let db = Arc::new(Database::new(dir.join("db.json").to_str().unwrap()));
+    db.set("user/1/name", Value::String("Alice".to_string())).unwrap();
+
+    let backups = dir.join("backups");
+    let mut scheduler = BackupScheduler::start(db, ScheduleConfig {
+        interval_secs: Some(1),
+        cron: None,
+        prefix: "auto".to_string(),
+        retention: RetentionPolicy { keep_last: 1, ..Default::default() },
+        dir: Some(backups.to_str().unwrap().to_string()),
+        s3: None,
+    })
+    .unwrap();
+
+    std::thread::sleep(Duration::from_millis(2500));
+    scheduler.stop();
+
+    let status = scheduler.status();
+    assert!(status.runs >= 1, "{:?}", status);
+    assert_eq!(status.last_error, None);
+
+    let target = LocalTarget::new(backups.to_str().unwrap());
+    let backups: Vec<String> = target.list("auto").unwrap()
+        .into_iter()
+        .filter(|name| !name.ends_with(".manifest.json"))
+        .collect();
+    assert_eq!(backups, vec![status.last_backup.unwrap()]);
+}
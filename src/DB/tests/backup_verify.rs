@@ -0,0 +1,62 @@
+mod common;
+
+use rust_db::{BackupKind, Database, Value};
+
+// Databáze žije mimo adresář se zálohami, ať ho verify nepovažuje za zálohu
+fn temp_db(name: &str) -> Database {
+    let dir = common::temp_dir(name);
+    Database::new(dir.join("db.json").to_str().unwrap())
+}
+
+#[test]
+fn verify_detects_tampered_and_incomplete_backups() {
+    let dir = common::temp_dir("verify_file");
+    let db = temp_db("verify_file_db");
+    db.set("user/1/name", Value::String("Alice".to_string())).unwrap();
+    db.set("user/2/name", Value::String("Bob".to_string())).unwrap();
+
+    let backup = dir.join("full.json");
+    let backup_str = backup.to_str().unwrap();
+    db.create_backup(backup_str).unwrap();
+    let report = Database::verify_backup(backup_str).unwrap();
+    assert_eq!(report.len(), 1);
+    assert!(report[0].valid, "{:?}", report[0].errors);
+    assert_eq!(report[0].key_count, 2);
+
+    // Same length, one value changed
+    let content = std::fs::read_to_string(&backup).unwrap().replace("Alice", "Alina");
+    std::fs::write(&backup, content).unwrap();
+    let report = Database::verify_backup(backup_str).unwrap();
+    assert!(!report[0].valid);
+    assert_eq!(report[0].errors, vec!["Checksum mismatch"]);
+
+    std::fs::write(&backup, b"{\"data\": ").unwrap();
+    let errors = &Database::verify_backup(backup_str).unwrap()[0].errors;
+    assert!(errors.iter().any(|e| e.starts_with("Cannot parse backup")), "{:?}", errors);
+
+    db.create_backup(backup_str).unwrap();
+    std::fs::remove_file(dir.join("full.json.manifest.json")).unwrap();
+    let errors = &Database::verify_backup(backup_str).unwrap()[0].errors;
+    assert_eq!(errors, &vec!["Manifest is missing"]);
+}
+
+#[test]
+fn verify_checks_every_backup_of_a_catalog() {
+    let dir = common::temp_dir("verify_catalog");
+    let dir_str = dir.to_str().unwrap();
+    let db = temp_db("verify_catalog_db");
+    db.set("a", Value::Integer(1)).unwrap();
+    db.create_catalog_backup(dir_str, BackupKind::Full).unwrap();
+    db.set("b", Value::Integer(2)).unwrap();
+    let incremental = db.create_catalog_backup(dir_str, BackupKind::Incremental).unwrap();
+
+    let report = Database::verify_backup(dir_str).unwrap();
+    assert_eq!(report.len(), 2);
+    assert!(report.iter().all(|r| r.valid), "{:?}", report);
+
+    std::fs::remove_file(dir.join(&incremental.file)).unwrap();
+    let report = Database::verify_backup(dir_str).unwrap();
+    let broken: Vec<&str> = report.iter().filter(|r| !r.valid).map(|r| r.path.as_str()).collect();
+    assert_eq!(broken.len(), 1);
+    assert!(broken[0].ends_with(&incremental.file), "{:?}", broken);
+}
@@ -0,0 +1,127 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+use rust_db::{BtrfsConfig, Database, StorageConfig, StorageEngine, Value};
+
+// Stand-in for btrfs-progs: subvolumes are directories with a marker file
+const FAKE_BTRFS: &str = r#"#!/bin/sh
+case "$1 $2" in
+  "subvolume show") test -f "$3/.subvolume" || { echo "ERROR: not a subvolume: $3" >&2; exit 1; } ;;
+  "subvolume create") mkdir -p "$3" && touch "$3/.subvolume" ;;
+  "subvolume snapshot") cp -a "$4" "$5" ;;
+  "subvolume delete") rm -rf "$3" ;;
+  "send -f") out="$3"; shift 3; echo "send $*" > "$out" ;;
+  *) echo "unsupported: $*" >&2; exit 1 ;;
+esac
+"#;
+
+// Each test gets its own fake binary, passed explicitly so parallel tests don't share state
+struct Setup {
+    root: PathBuf,
+    bin: String,
+}
+
+impl Setup {
+    fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("rust_db_btrfs_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+
+        let bin = root.join("btrfs");
+        std::fs::write(&bin, FAKE_BTRFS).unwrap();
+        std::fs::set_permissions(&bin, std::fs::Permissions::from_mode(0o755)).unwrap();
+        Setup {
+            bin: bin.to_str().unwrap().to_string(),
+            root,
+        }
+    }
+
+    fn init_subvolume(&self, dir: &std::path::Path) -> Result<(), rust_db::DbError> {
+        Database::init_btrfs_subvolume_with(dir.to_str().unwrap(), &self.bin)
+    }
+
+    fn enable(&self, db: &Database) -> Result<(), rust_db::DbError> {
+        db.enable_btrfs_snapshots_with(&BtrfsConfig {
+            snapshot_dir: self.root.join("snapshots").to_str().unwrap().to_string(),
+            btrfs_bin: self.bin.clone(),
+        })
+    }
+}
+
+#[test]
+fn snapshot_list_send_restore_and_delete() {
+    let setup = Setup::new("cycle");
+    let root = &setup.root;
+    let subvolume = root.join("data");
+    setup.init_subvolume(&subvolume).unwrap();
+    // Idempotent
+    setup.init_subvolume(&subvolume).unwrap();
+    assert!(rust_db::is_btrfs_subvolume_with(subvolume.to_str().unwrap(), &setup.bin));
+
+    let db = Database::new(subvolume.join("db.json").to_str().unwrap());
+    setup.enable(&db).unwrap();
+
+    db.set("user/1/name", Value::String("Alice".to_string())).unwrap();
+    let first = db.btrfs_snapshot(Some("first")).unwrap();
+    assert!(PathBuf::from(&first.path).join("db.json").exists());
+    assert!(db.btrfs_snapshot(Some("first")).is_err());
+
+    db.set("user/1/name", Value::String("Changed".to_string())).unwrap();
+    let second = db.btrfs_snapshot(None).unwrap();
+    assert!(second.created_at.is_some());
+
+    let names: Vec<String> = db.list_btrfs_snapshots().unwrap().into_iter().map(|s| s.name).collect();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&"first".to_string()));
+
+    let stream = root.join("second.send");
+    db.btrfs_send(&second.name, Some("first"), stream.to_str().unwrap()).unwrap();
+    assert!(std::fs::read_to_string(&stream).unwrap().contains("-p"));
+
+    db.restore_btrfs_snapshot("first").unwrap();
+    assert_eq!(db.get("user/1/name").unwrap(), Value::String("Alice".to_string()));
+
+    db.delete_btrfs_snapshot("first").unwrap();
+    assert_eq!(db.list_btrfs_snapshots().unwrap().len(), 1);
+    assert!(db.restore_btrfs_snapshot("../data").is_err());
+}
+
+#[test]
+fn restore_never_modifies_the_snapshot() {
+    let setup = Setup::new("readonly");
+    let subvolume = setup.root.join("data");
+    setup.init_subvolume(&subvolume).unwrap();
+    let config = StorageConfig {
+        engine: StorageEngine::Log,
+        ..Default::default()
+    };
+    let db = Database::open(subvolume.join("db.log").to_str().unwrap(), &config).unwrap();
+    setup.enable(&db).unwrap();
+
+    db.set("user/1/name", Value::String("Alice".to_string())).unwrap();
+    let snapshot = db.btrfs_snapshot(Some("torn")).unwrap();
+    db.set("user/1/name", Value::String("Changed".to_string())).unwrap();
+
+    // Snapshot taken in the middle of an append; restoring must not truncate it
+    let file = PathBuf::from(&snapshot.path).join("db.log");
+    let mut content = std::fs::read(&file).unwrap();
+    content.extend_from_slice(br#"{"op":"set","path":"x","#);
+    std::fs::write(&file, &content).unwrap();
+
+    db.restore_btrfs_snapshot("torn").unwrap();
+    assert_eq!(db.get("user/1/name").unwrap(), Value::String("Alice".to_string()));
+    assert!(db.get("x").is_err());
+    assert_eq!(std::fs::read(&file).unwrap(), content);
+}
+
+#[test]
+fn plain_directory_is_rejected() {
+    let setup = Setup::new("plain");
+    let dir = setup.root.join("plain");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let db = Database::new(dir.join("db.json").to_str().unwrap());
+    let err = setup.enable(&db).unwrap_err();
+    assert!(err.to_string().contains("not a Btrfs subvolume"));
+    assert!(setup.init_subvolume(&dir).is_err());
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use rust_db::{CdcRetention, ChangeKind, Database, Value};
+
+fn open(path: &str, retention: CdcRetention) -> Database {
+    let db = Database::new(path);
+    db.enable_cdc(retention).unwrap();
+    db
+}
+
+#[test]
+fn reads_from_offsets_and_resumes_consumers_after_reopen() {
+    let dir = common::temp_dir("cdc_offsets");
+    let path = dir.join("db.json").to_str().unwrap().to_string();
+
+    let db = open(&path, CdcRetention::default());
+    db.set("a", Value::Integer(1)).unwrap();
+    db.set("b", Value::Integer(2)).unwrap();
+    db.delete("a").unwrap();
+    db.set("c", Value::Integer(3)).unwrap();
+
+    let all = db.cdc_read(0, 100).unwrap();
+    assert_eq!(all.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    assert_eq!(all[2].kind, ChangeKind::Delete);
+    assert_eq!(all[2].path, "a");
+    assert_eq!(all[3].value, Some(Value::Integer(3)));
+
+    let page = db.cdc_read(1, 2).unwrap();
+    assert_eq!(page.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![2, 3]);
+    assert!(db.cdc_read(4, 10).unwrap().is_empty());
+    assert_eq!(db.cdc_read(3, usize::MAX).unwrap().len(), 1);
+
+    db.cdc_ack("search", 2).unwrap();
+    assert!(db.cdc_ack("search", 99).is_err());
+    drop(db);
+
+    let db = open(&path, CdcRetention::default());
+    assert_eq!(db.cdc_last_seq().unwrap(), 4);
+    let pending = db.cdc_read_consumer("search", 10).unwrap();
+    assert_eq!(pending.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![3, 4]);
+    db.set("d", Value::Integer(4)).unwrap();
+    assert_eq!(db.cdc_read(4, 10).unwrap()[0].seq, 5);
+    drop(db);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn bulk_operations_record_every_key() {
+    let dir = common::temp_dir("cdc_bulk");
+    let path = dir.join("db.json").to_str().unwrap().to_string();
+    let export = dir.join("export.json").to_str().unwrap().to_string();
+
+    let db = open(&path, CdcRetention::default());
+    for i in 0..50 {
+        db.set(&format!("user/{}", i), Value::Integer(i)).unwrap();
+    }
+    db.export_json(&export).unwrap();
+    let before = db.cdc_last_seq().unwrap();
+
+    // Deletes the 50 keys and inserts them again in one batch
+    db.import_json(&export).unwrap();
+    assert_eq!(db.cdc_last_seq().unwrap(), before + 100);
+    assert_eq!(db.delete_by_pattern("user/*").unwrap(), 50);
+    let records = db.cdc_read(before + 100, usize::MAX).unwrap();
+    assert_eq!(records.len(), 50);
+    assert!(records.iter().all(|r| r.kind == ChangeKind::Delete));
+    drop(db);
+
+    // Everything was written before the operations returned
+    let db = open(&path, CdcRetention::default());
+    assert_eq!(db.cdc_read(0, usize::MAX).unwrap().len(), 200);
+    drop(db);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn torn_tail_is_cut_off_and_corrupt_records_are_errors() {
+    let dir = common::temp_dir("cdc_corrupt");
+    let path = dir.join("db.json").to_str().unwrap().to_string();
+    let log = format!("{}.cdc", path);
+
+    let db = open(&path, CdcRetention::default());
+    db.set("a", Value::Integer(1)).unwrap();
+    db.set("b", Value::Integer(2)).unwrap();
+    drop(db);
+
+    // A crash in the middle of an append leaves half a line behind
+    OpenOptions::new().append(true).open(&log).unwrap().write_all(b"{\"seq\":3,\"ki").unwrap();
+    let db = open(&path, CdcRetention::default());
+    db.set("c", Value::Integer(3)).unwrap();
+    let records = db.cdc_read(0, 10).unwrap();
+    assert_eq!(records.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![1, 2, 3]);
+    drop(db);
+
+    // A damaged record followed by valid ones must not hide them silently
+    let content = std::fs::read_to_string(&log).unwrap();
+    let damaged = content.replacen("\"seq\":2", "\"seq\":\"x\"", 1);
+    std::fs::write(&log, damaged).unwrap();
+    let db = Database::new(&path);
+    assert!(db.enable_cdc(CdcRetention::default()).is_err());
+    drop(db);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn size_retention_keeps_the_newest_records_readable() {
+    let dir = common::temp_dir("cdc_retention");
+    let path = dir.join("db.json").to_str().unwrap().to_string();
+    let retention = CdcRetention {
+        max_bytes: Some(4096),
+        max_age_secs: None,
+    };
+
+    let db = open(&path, retention.clone());
+    for i in 0..200 {
+        db.set(&format!("key/{}", i), Value::String("x".repeat(20))).unwrap();
+    }
+    assert!(std::fs::metadata(format!("{}.cdc", path)).unwrap().len() <= 4096);
+
+    let kept = db.cdc_read(0, usize::MAX).unwrap();
+    assert!(!kept.is_empty() && kept.len() < 200);
+    assert_eq!(kept.last().unwrap().seq, 200);
+    assert!(kept.windows(2).all(|w| w[1].seq == w[0].seq + 1));
+    let from = kept[kept.len() / 2].seq;
+    assert_eq!(db.cdc_read(from, 3).unwrap()[0].seq, from + 1);
+    drop(db);
+
+    let db = open(&path, retention);
+    assert_eq!(db.cdc_last_seq().unwrap(), 200);
+    assert_eq!(db.cdc_read(0, usize::MAX).unwrap().len(), kept.len());
+    drop(db);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn concurrent_writes_to_one_key_replay_to_the_stored_value() {
+    let dir = common::temp_dir("cdc_concurrent");
+    let path = dir.join("db.json").to_str().unwrap().to_string();
+    let db = std::sync::Arc::new(open(&path, CdcRetention::default()));
+
+    let writers: Vec<_> = (0..4)
+        .map(|writer| {
+            let db = db.clone();
+            std::thread::spawn(move || {
+                for i in 0..50 {
+                    if i % 7 == 6 {
+                        let _ = db.delete("shared");
+                    } else {
+                        db.set("shared", Value::Integer(writer * 1000 + i)).unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    // Záznamy v pořadí seq musí skončit ve stejném stavu jako mapa
+    let records = db.cdc_read(0, usize::MAX).unwrap();
+    let revisions: Vec<u64> = records.iter().filter_map(|r| r.revision).collect();
+    assert!(revisions.windows(2).all(|w| w[0] < w[1]), "{:?}", revisions);
+    let mut replayed = None;
+    for record in &records {
+        replayed = match record.kind {
+            ChangeKind::Delete => None,
+            _ => record.value.clone(),
+        };
+    }
+    assert_eq!(replayed, db.get("shared").ok());
+    drop(db);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,69 @@
+use rust_db::{ChangeKind, Database, FeedMessage, Value};
+
+fn change(message: Option<FeedMessage>) -> (ChangeKind, String) {
+    match message {
+        Some(FeedMessage::Change(event)) => (event.kind, event.path),
+        other => panic!("expected a change, got {:?}", other),
+    }
+}
+
+#[test]
+fn subscribers_and_pollers_see_matching_changes() {
+    let db = Database::in_memory();
+    let mut articles = db.subscribe("article/*").unwrap();
+    assert!(db.subscribe("/article").is_err());
+
+    let start = db.current_change_cursor();
+    db.set("article/1", Value::Integer(1)).unwrap();
+    db.set("user/1", Value::Integer(1)).unwrap();
+    db.set_expiry("article/1", 3600).unwrap();
+    db.delete("article/1").unwrap();
+
+    assert_eq!(change(articles.try_recv()), (ChangeKind::Set, "article/1".to_string()));
+    assert_eq!(change(articles.try_recv()), (ChangeKind::Ttl, "article/1".to_string()));
+    assert_eq!(change(articles.try_recv()), (ChangeKind::Delete, "article/1".to_string()));
+    assert!(articles.try_recv().is_none());
+
+    let batch = db.poll_changes("article/*", start, 1).unwrap();
+    assert!(!batch.truncated);
+    assert_eq!(batch.events.len(), 1);
+    assert_eq!(batch.cursor, start + 1);
+    let rest = db.poll_changes("article/*", batch.cursor, 10).unwrap();
+    let kinds: Vec<ChangeKind> = rest.events.iter().map(|e| e.kind).collect();
+    assert_eq!(kinds, vec![ChangeKind::Ttl, ChangeKind::Delete]);
+    // The cursor moves past the non-matching user/1 event too
+    assert_eq!(rest.cursor, db.current_change_cursor());
+    assert_eq!(rest.events[1].new_revision, None);
+    assert!(rest.events[1].old_revision.is_some());
+}
+
+#[test]
+fn slow_subscriber_is_told_about_dropped_events() {
+    let db = Database::in_memory();
+    let mut slow = db.subscribe("key/*").unwrap();
+    for i in 0..5000 {
+        db.set(&format!("key/{}", i % 10), Value::Integer(i)).unwrap();
+    }
+
+    let Some(FeedMessage::Lagged(skipped)) = slow.try_recv() else { panic!("lag was not reported") };
+    let mut received = 0;
+    while let Some(message) = slow.try_recv() {
+        assert!(matches!(message, FeedMessage::Change(_)));
+        received += 1;
+    }
+    assert_eq!(skipped + received, 5000);
+    // Polling reports the same gap as truncated
+    assert!(db.poll_changes("key/*", 0, 10).unwrap().truncated);
+}
+
+#[tokio::test]
+async fn async_recv_waits_for_the_next_change() {
+    let db = std::sync::Arc::new(Database::in_memory());
+    let mut subscription = db.subscribe("user/*/name").unwrap();
+    let writer = {
+        let db = db.clone();
+        tokio::task::spawn_blocking(move || db.set("user/7/name", Value::String("Eve".to_string())).unwrap())
+    };
+    assert_eq!(change(subscription.recv().await), (ChangeKind::Set, "user/7/name".to_string()));
+    writer.await.unwrap();
+}
@@ -0,0 +1,65 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use rust_db::{Database, Value};
+
+fn cli(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust-db-cli"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn import_merges_by_default_and_remaps_prefixes() {
+    let dir = std::env::temp_dir().join(format!("rust_db_cli_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("source.json").to_str().unwrap().to_string();
+    let target = dir.join("target.json").to_str().unwrap().to_string();
+
+    let db = Database::new(&source);
+    db.set("user/1/name", Value::String("Alice".to_string())).unwrap();
+    db.set("user/2/name", Value::String("Bob".to_string())).unwrap();
+    drop(db);
+    let db = Database::new(&target);
+    db.set("user/1/name", Value::String("Existing".to_string())).unwrap();
+    db.set("config/theme", Value::String("dark".to_string())).unwrap();
+    drop(db);
+
+    let dump = cli(&["export", &source], b"");
+    assert!(dump.status.success());
+
+    // No --mode: nothing in the target is removed or overwritten
+    assert!(cli(&["import", &target], &dump.stdout).status.success());
+    let db = Database::new(&target);
+    assert_eq!(db.get("user/1/name").unwrap(), Value::String("Existing".to_string()));
+    assert_eq!(db.get("user/2/name").unwrap(), Value::String("Bob".to_string()));
+    assert!(db.get("config/theme").is_ok());
+    drop(db);
+
+    let output = cli(&["import", &target, "--remap", "user=archive/user", "--remap", "x=y"], &dump.stdout);
+    assert!(output.status.success());
+    let db = Database::new(&target);
+    assert_eq!(db.get("archive/user/1/name").unwrap(), Value::String("Alice".to_string()));
+    drop(db);
+
+    let output = cli(&["import", &target, "--remap", "user"], &dump.stdout);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--remap expects <from>=<to>"));
+
+    // A corrupt database is reported and left untouched instead of being treated as empty
+    std::fs::write(&target, b"{not json").unwrap();
+    let output = cli(&["import", &target], &dump.stdout);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error:"));
+    assert_eq!(std::fs::read(&target).unwrap(), b"{not json");
+    assert!(!cli(&["export", &target], b"").status.success());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
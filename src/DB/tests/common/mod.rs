@@ -0,0 +1,9 @@
+use std::path::PathBuf;
+
+// Čistý adresář pro jeden test, jméno odlišuje testy v rámci jednoho procesu
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust_db_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
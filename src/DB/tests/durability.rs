@@ -0,0 +1,126 @@
+mod common;
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use rust_db::{Database, Durability, StorageConfig, StorageEngine, Value};
+
+#[test]
+fn always_fsyncs_with_group_commit() {
+    let dir = common::temp_dir("durability_always");
+    for engine in [StorageEngine::Log, StorageEngine::Json] {
+        let path = dir.join(format!("{:?}.db", engine));
+        let config = StorageConfig {
+            engine,
+            durability: Durability::Always,
+            ..Default::default()
+        };
+        let db = Arc::new(Database::open(path.to_str().unwrap(), &config).unwrap());
+
+        let writers: Vec<_> = (0..8)
+            .map(|t| {
+                let db = db.clone();
+                thread::spawn(move || {
+                    for i in 0..25 {
+                        db.set(&format!("t{}/k{}", t, i), Value::Integer(i)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let metrics = db.write_metrics();
+        assert_eq!(metrics.durability, Durability::Always);
+        assert_eq!(metrics.writes, 200);
+        assert!(metrics.fsyncs >= 1);
+        // Každý zápis buď provedl fsync, nebo ho pokryl cizí
+        assert_eq!(metrics.fsyncs + metrics.grouped_writes, 200);
+        assert!(metrics.max_latency_us >= metrics.p50_latency_us / 2);
+        drop(db);
+
+        let db = Database::open(path.to_str().unwrap(), &config).unwrap();
+        assert_eq!(db.get("t7/k24").unwrap(), Value::Integer(24));
+    }
+}
+
+#[test]
+fn periodic_and_none_levels() {
+    let dir = common::temp_dir("durability_periodic");
+    let config = StorageConfig {
+        engine: StorageEngine::Log,
+        durability: Durability::Periodic { interval_ms: 10 },
+        ..Default::default()
+    };
+    let db = Database::open(dir.join("periodic.log").to_str().unwrap(), &config).unwrap();
+    db.set("a", Value::Integer(1)).unwrap();
+    assert_eq!(db.write_metrics().writes, 1);
+    let mut fsyncs = 0;
+    for _ in 0..100 {
+        thread::sleep(Duration::from_millis(10));
+        fsyncs = db.write_metrics().fsyncs;
+        if fsyncs > 0 {
+            break;
+        }
+    }
+    assert_eq!(fsyncs, 1);
+
+    let parsed: StorageConfig = serde_json::from_str(r#"{"durability": {"periodic": {"interval_ms": 5}}}"#).unwrap();
+    assert_eq!(parsed.durability, Durability::Periodic { interval_ms: 5 });
+
+    let config = StorageConfig {
+        engine: StorageEngine::Log,
+        ..Default::default()
+    };
+    let db = Database::open(dir.join("none.log").to_str().unwrap(), &config).unwrap();
+    db.set("a", Value::Integer(1)).unwrap();
+    db.set("b", Value::Integer(2)).unwrap();
+    let metrics = db.write_metrics();
+    assert_eq!((metrics.writes, metrics.fsyncs, metrics.grouped_writes), (2, 0, 0));
+
+    // JSON se přepisuje přes rename, fsync ale jen podle zvolené úrovně
+    let db = Database::new(dir.join("none.json").to_str().unwrap());
+    db.set("a", Value::Integer(1)).unwrap();
+    db.set("b", Value::Integer(2)).unwrap();
+    assert_eq!(db.write_metrics().fsyncs, 0);
+    drop(db);
+    assert_eq!(Database::new(dir.join("none.json").to_str().unwrap()).get("b").unwrap(), Value::Integer(2));
+
+    let config = StorageConfig {
+        engine: StorageEngine::Json,
+        durability: Durability::Periodic { interval_ms: 10 },
+        ..Default::default()
+    };
+    let db = Database::open(dir.join("periodic.json").to_str().unwrap(), &config).unwrap();
+    db.set("a", Value::Integer(1)).unwrap();
+    db.set("b", Value::Integer(2)).unwrap();
+    assert!(db.write_metrics().fsyncs <= 1);
+    let mut fsyncs = 0;
+    for _ in 0..100 {
+        thread::sleep(Duration::from_millis(10));
+        fsyncs = db.write_metrics().fsyncs;
+        if fsyncs > 0 {
+            break;
+        }
+    }
+    assert!(fsyncs >= 1);
+}
+
+#[test]
+fn json_rewrite_never_leaves_a_partial_file() {
+    let dir = common::temp_dir("durability_atomic");
+    let path = dir.join("db.json");
+    let db = Database::new(path.to_str().unwrap());
+    db.set("a", Value::Integer(1)).unwrap();
+    drop(db);
+
+    // Pád uprostřed zápisu nechá nanejvýš poškozený .tmp vedle původního souboru
+    std::fs::write(dir.join("db.json.tmp"), "{\"data\": {\"b\"").unwrap();
+    let db = Database::new(path.to_str().unwrap());
+    assert_eq!(db.get("a").unwrap(), Value::Integer(1));
+    db.set("b", Value::Integer(2)).unwrap();
+    assert!(!dir.join("db.json.tmp").exists());
+    assert_eq!(Database::new(path.to_str().unwrap()).get("b").unwrap(), Value::Integer(2));
+}
@@ -0,0 +1,157 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use rust_db::{Database, DbError, EvictionPolicy, MemoryConfig, Value};
+
+fn entry_cost(db: &Database, path: &str, value: Value) -> u64 {
+    let before = db.memory_stats().used_memory;
+    db.set(path, value).unwrap();
+    db.memory_stats().used_memory - before
+}
+
+#[test]
+fn accounting_follows_writes_and_deletes() {
+    let db = Database::in_memory();
+    assert_eq!(db.memory_stats().used_memory, 0);
+
+    let small = entry_cost(&db, "a", Value::String("x".to_string()));
+    let large = entry_cost(&db, "b", Value::String("x".repeat(1000)));
+    assert!(large >= small + 999);
+
+    // Přepsání menší hodnotou spotřebu sníží
+    db.set("b", Value::Integer(1)).unwrap();
+    db.set_expiry("b", 60).unwrap();
+    db.delete("a").unwrap();
+    db.delete("b").unwrap();
+    assert_eq!(db.memory_stats().used_memory, 0);
+    assert_eq!(db.get_stats().memory_usage, 0);
+}
+
+#[test]
+fn noeviction_rejects_and_lru_evicts_coldest() {
+    let db = Database::in_memory();
+    let cost = entry_cost(&db, "k0", Value::Integer(0));
+    db.set_memory_limit(MemoryConfig {
+        max_memory: cost * 3,
+        policy: EvictionPolicy::Noeviction,
+    })
+    .unwrap();
+    db.set("k1", Value::Integer(1)).unwrap();
+    db.set("k2", Value::Integer(2)).unwrap();
+    assert!(matches!(db.set("k3", Value::Integer(3)), Err(DbError::OutOfMemory)));
+    // Overwrites of the same size still pass
+    db.set("k2", Value::Integer(20)).unwrap();
+    assert_eq!(db.memory_stats().rejected_writes, 1);
+
+    db.set_memory_limit(MemoryConfig {
+        max_memory: cost * 3,
+        policy: EvictionPolicy::AllkeysLru,
+    })
+    .unwrap();
+    sleep(Duration::from_millis(5));
+    db.get("k0").unwrap();
+    db.get("k2").unwrap();
+    db.set("k3", Value::Integer(3)).unwrap();
+    assert!(!db.exists("k1"));
+    assert!(db.exists("k0") && db.exists("k2") && db.exists("k3"));
+    assert_eq!(db.memory_stats().evicted_keys, 1);
+}
+
+#[test]
+fn volatile_policies_only_touch_keys_with_expiry() {
+    let db = Database::in_memory();
+    let cost = entry_cost(&db, "persistent", Value::Integer(0));
+    db.set("short", Value::Integer(1)).unwrap();
+    db.set_expiry("short", 10).unwrap();
+    db.set("long", Value::Integer(2)).unwrap();
+    db.set_expiry("long", 1000).unwrap();
+
+    db.set_memory_limit(MemoryConfig {
+        max_memory: cost * 3,
+        policy: EvictionPolicy::VolatileTtl,
+    })
+    .unwrap();
+    db.set("new", Value::Integer(3)).unwrap();
+    assert!(!db.exists("short"));
+    assert!(db.exists("long"));
+
+    db.set("newer", Value::Integer(4)).unwrap();
+    assert!(!db.exists("long"));
+    assert!(matches!(db.set("newest", Value::Integer(5)), Err(DbError::OutOfMemory)));
+    assert!(db.exists("persistent"));
+}
+
+fn backup_of(name: &str, keys: &[&str]) -> String {
+    let path = std::env::temp_dir().join(format!("rust_db_eviction_{}_{}.json", name, std::process::id()));
+    let source = Database::in_memory();
+    for key in keys {
+        source.set(key, Value::Integer(7)).unwrap();
+    }
+    source.create_backup(path.to_str().unwrap()).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn restore_that_does_not_fit_is_rejected_before_anything_is_deleted() {
+    let db = Database::in_memory();
+    let cost = entry_cost(&db, "k0", Value::Integer(0));
+    db.set("k1", Value::Integer(1)).unwrap();
+    db.set_memory_limit(MemoryConfig {
+        max_memory: cost * 3,
+        policy: EvictionPolicy::Noeviction,
+    })
+    .unwrap();
+
+    let too_big = backup_of("too_big", &["r0", "r1", "r2", "r3"]);
+    assert!(matches!(db.restore_from_backup(&too_big), Err(DbError::OutOfMemory)));
+    assert!(db.exists("k0") && db.exists("k1"));
+    assert_eq!(db.memory_stats().rejected_writes, 1);
+
+    // Together with the current keys this would not fit, but it replaces them
+    let fits = backup_of("fits", &["r0", "r1", "r2"]);
+    db.restore_from_backup(&fits).unwrap();
+    assert!(!db.exists("k0"));
+    assert!(db.exists("r0") && db.exists("r1") && db.exists("r2"));
+}
+
+#[test]
+fn restore_under_lru_keeps_every_restored_key() {
+    let db = Database::in_memory();
+    let cost = entry_cost(&db, "k0", Value::Integer(0));
+    db.set("k1", Value::Integer(1)).unwrap();
+    db.set("k2", Value::Integer(2)).unwrap();
+    db.set_memory_limit(MemoryConfig {
+        max_memory: cost * 3,
+        policy: EvictionPolicy::AllkeysLru,
+    })
+    .unwrap();
+
+    let backup = backup_of("lru", &["r0", "r1", "r2"]);
+    db.restore_from_backup(&backup).unwrap();
+    assert!(db.exists("r0") && db.exists("r1") && db.exists("r2"));
+    assert_eq!(db.memory_stats().evicted_keys, 0);
+    assert_eq!(db.memory_stats().used_memory, cost * 3);
+}
+
+#[test]
+fn lowering_the_limit_persists_evictions() {
+    let path = std::env::temp_dir().join(format!("rust_db_eviction_persist_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let db = Database::new(path.to_str().unwrap());
+    let cost = entry_cost(&db, "k0", Value::Integer(0));
+    for i in 1..4 {
+        db.set(&format!("k{}", i), Value::Integer(i)).unwrap();
+    }
+    db.set_memory_limit(MemoryConfig {
+        max_memory: cost * 2,
+        policy: EvictionPolicy::AllkeysLru,
+    })
+    .unwrap();
+    assert_eq!(db.memory_stats().evicted_keys, 2);
+    drop(db);
+
+    let reopened = Database::new(path.to_str().unwrap());
+    assert_eq!(reopened.find_by_path("*").unwrap().len(), 2);
+    assert!(reopened.get("k0").is_err());
+    let _ = std::fs::remove_file(&path);
+}
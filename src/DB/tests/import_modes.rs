@@ -0,0 +1,95 @@
+use rust_db::{ConflictResolution, Database, ImportMode, ImportOptions, PrefixRemap, Value};
+
+fn temp_db(name: &str) -> (Database, std::path::PathBuf) {
+    let dir = std::env::temp_dir().join(format!("rust_db_import_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    (Database::new(dir.join("db.json").to_str().unwrap()), dir)
+}
+
+fn string(value: &str) -> Value {
+    Value::String(value.to_string())
+}
+
+// Source export with user/1, user/2 and config/theme
+fn source_export(name: &str) -> String {
+    let (source, dir) = temp_db(name);
+    source.set("user/1/name", string("Alice (import)")).unwrap();
+    source.set("user/2/name", string("Bob")).unwrap();
+    source.set("config/theme", string("dark")).unwrap();
+    let path = dir.join("export.json");
+    source.export_json(path.to_str().unwrap()).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn merge_keep_existing_reports_conflicts() {
+    let export = source_export("keep_src");
+    let (db, _) = temp_db("keep");
+    db.set("user/1/name", string("Alice")).unwrap();
+    db.set("user/9/name", string("Zed")).unwrap();
+
+    let report = db.import_json_with(&export, &ImportOptions {
+        mode: ImportMode::MergeKeepExisting,
+        ..Default::default()
+    })
+    .unwrap();
+
+    assert_eq!(report.imported, 2);
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(report.conflicts[0].path, "user/1/name");
+    assert_eq!(report.conflicts[0].resolution, ConflictResolution::KeptExisting);
+    assert_eq!(db.get("user/1/name").unwrap(), string("Alice"));
+    assert_eq!(db.get("user/9/name").unwrap(), string("Zed"));
+    assert_eq!(db.get("config/theme").unwrap(), string("dark"));
+}
+
+#[test]
+fn newest_wins_compares_update_times() {
+    let export = source_export("newest_src");
+    let (db, _) = temp_db("newest");
+    // Written after the export, so the local value is newer
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    db.set("user/2/name", string("Bobby")).unwrap();
+
+    let report = db.import_json_with(&export, &ImportOptions {
+        mode: ImportMode::MergeNewestWins,
+        ..Default::default()
+    })
+    .unwrap();
+
+    assert_eq!(report.conflicts[0].resolution, ConflictResolution::KeptExisting);
+    assert_eq!(db.get("user/2/name").unwrap(), string("Bobby"));
+    assert_eq!(db.get("user/1/name").unwrap(), string("Alice (import)"));
+}
+
+#[test]
+fn dry_run_with_prefix_filter_and_remap_changes_nothing() {
+    let export = source_export("dry_src");
+    let (db, _) = temp_db("dry");
+    db.set("legacy/1/name", string("Old")).unwrap();
+    db.set("legacy/7/name", string("Gone")).unwrap();
+
+    let options = ImportOptions {
+        mode: ImportMode::Replace,
+        dry_run: true,
+        prefix: Some("user".to_string()),
+        remap: vec![PrefixRemap {
+            from: "user".to_string(),
+            to: "legacy".to_string(),
+        }],
+    };
+    let report = db.import_json_with(&export, &options).unwrap();
+    assert_eq!(report.skipped, 1);
+    assert_eq!(report.imported, 2);
+    assert_eq!(report.deleted, 1);
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(db.get("legacy/1/name").unwrap(), string("Old"));
+
+    let report = db.import_json_with(&export, &ImportOptions { dry_run: false, ..options }).unwrap();
+    assert_eq!(report.deleted, 1);
+    assert_eq!(db.get("legacy/1/name").unwrap(), string("Alice (import)"));
+    assert_eq!(db.get("legacy/2/name").unwrap(), string("Bob"));
+    assert!(db.get("legacy/7/name").is_err());
+    assert!(db.get("config/theme").is_err());
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use rust_db::{BackupCatalog, BackupKind, Database, StorageConfig, StorageEngine, Value};
+
+fn db_in(dir: &std::path::Path, name: &str) -> Database {
+    Database::new(dir.join(name).to_str().unwrap())
+}
+
+fn integer(value: Value) -> i64 {
+    match value {
+        Value::Integer(n) => n,
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn differential_chain_and_point_in_time_restore() {
+    let dir = common::temp_dir("incremental_chain");
+    let backups = dir.join("backups");
+    std::fs::create_dir_all(&backups).unwrap();
+    let backups = backups.to_str().unwrap();
+    let second = std::time::Duration::from_millis(1100);
+
+    let db = db_in(&dir, "db.json");
+    db.set("a", Value::Integer(1)).unwrap();
+    db.set("b", Value::Integer(2)).unwrap();
+    let full = db.create_catalog_backup(backups, BackupKind::Full).unwrap();
+    std::thread::sleep(second);
+
+    db.set("a", Value::Integer(10)).unwrap();
+    let incremental = db.create_catalog_backup(backups, BackupKind::Incremental).unwrap();
+    assert_eq!(incremental.parent.as_deref(), Some(full.id.as_str()));
+    assert_eq!(incremental.key_count, 2);
+    std::thread::sleep(second);
+
+    db.delete("b").unwrap();
+    db.set("c", Value::Integer(3)).unwrap();
+    // Differential backups always build on the last full one
+    let differential = db.create_catalog_backup(backups, BackupKind::Differential).unwrap();
+    assert_eq!(differential.parent.as_deref(), Some(full.id.as_str()));
+    assert_eq!(differential.base_revision, full.revision);
+
+    let catalog = BackupCatalog::load(backups).unwrap();
+    let chain: Vec<&str> = catalog.chain(None).unwrap().iter().map(|b| b.id.as_str()).collect();
+    assert_eq!(chain, vec![full.id.as_str(), differential.id.as_str()]);
+
+    let latest = db_in(&dir, "latest.json");
+    latest.restore_catalog(backups, None).unwrap();
+    assert_eq!(integer(latest.get("a").unwrap()), 10);
+    assert_eq!(integer(latest.get("c").unwrap()), 3);
+    assert!(!latest.exists("b"));
+
+    let earlier = db_in(&dir, "earlier.json");
+    earlier.restore_catalog(backups, Some(incremental.created_at)).unwrap();
+    assert_eq!(integer(earlier.get("a").unwrap()), 10);
+    assert_eq!(integer(earlier.get("b").unwrap()), 2);
+    assert!(!earlier.exists("c"));
+
+    let before = full.created_at - chrono::Duration::seconds(1);
+    assert!(db_in(&dir, "before.json").restore_catalog(backups, Some(before)).is_err());
+}
+
+#[test]
+fn revisions_stay_monotonic_across_restarts() {
+    let dir = common::temp_dir("incremental_restart");
+    for engine in [StorageEngine::Json, StorageEngine::Log] {
+        let path = dir.join(format!("{:?}.db", engine)).to_string_lossy().into_owned();
+        let backups = dir.join(format!("{:?}-backups", engine)).to_string_lossy().into_owned();
+        let config = StorageConfig {
+            engine,
+            ..Default::default()
+        };
+
+        let db = Database::open(&path, &config).unwrap();
+        db.set("a", Value::Integer(1)).unwrap();
+        db.set("b", Value::Integer(2)).unwrap();
+        db.create_catalog_backup(&backups, BackupKind::Full).unwrap();
+        // Klíč s nejvyšší revizí zmizí, čítač se ale nesmí vrátit
+        db.delete("b").unwrap();
+        let revision = db.revision();
+        drop(db);
+
+        let db = Database::open(&path, &config).unwrap();
+        assert_eq!(db.revision(), revision);
+        db.set("c", Value::Integer(3)).unwrap();
+        assert!(db.revision() > revision);
+        db.create_catalog_backup(&backups, BackupKind::Incremental).unwrap();
+
+        let restored = Database::in_memory();
+        restored.restore_catalog(&backups, None).unwrap();
+        assert_eq!(restored.get("a").unwrap(), Value::Integer(1));
+        assert_eq!(restored.get("c").unwrap(), Value::Integer(3));
+        assert!(!restored.exists("b"), "{:?}", engine);
+    }
+}
+
+#[test]
+fn last_backup_changes_only_after_a_written_backup() {
+    let dir = common::temp_dir("incremental_last_backup");
+    let db = Database::new(dir.join("db.json").to_str().unwrap());
+    db.set("a", Value::Integer(1)).unwrap();
+
+    // The backup directory can't be created under a regular file
+    let blocker = dir.join("blocker");
+    std::fs::write(&blocker, b"").unwrap();
+    assert!(db.create_backup(blocker.join("full.json").to_str().unwrap()).is_err());
+    assert!(db.get_stats().last_backup.is_none());
+
+    let backup = dir.join("full.json");
+    db.create_backup(backup.to_str().unwrap()).unwrap();
+    let last_backup = db.get_stats().last_backup.unwrap();
+    let saved: serde_json::Value = serde_json::from_slice(&std::fs::read(&backup).unwrap()).unwrap();
+    assert_eq!(saved["last_backup"].as_i64(), Some(last_backup.timestamp()));
+}
+
+#[test]
+fn concurrent_catalog_backups_keep_every_record() {
+    let dir = common::temp_dir("incremental_concurrent");
+    let backups = dir.join("backups");
+    std::fs::create_dir_all(&backups).unwrap();
+    let backups = backups.to_str().unwrap();
+    let db = db_in(&dir, "db.json");
+
+    std::thread::scope(|scope| {
+        for t in 0..4 {
+            let db = &db;
+            scope.spawn(move || {
+                for i in 0..5 {
+                    db.set(&format!("t{}/k{}", t, i), Value::Integer(i)).unwrap();
+                    db.create_catalog_backup(backups, BackupKind::Incremental).unwrap();
+                }
+            });
+        }
+    });
+    assert_eq!(BackupCatalog::load(backups).unwrap().backups.len(), 20);
+}
@@ -0,0 +1,43 @@
+use nix::sys::resource::{getrlimit, setrlimit, Resource};
+use nix::sys::signal::{signal, SigHandler, Signal};
+use rust_db::{Database, StorageConfig, StorageEngine, Value};
+
+// RLIMIT_FSIZE is per process, so this file holds a single test
+#[test]
+fn failed_append_keeps_failing_until_compaction() {
+    let dir = std::env::temp_dir().join(format!("rust_db_log_failures_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("db.log");
+    let path_str = path.to_str().unwrap();
+    let config = StorageConfig {
+        engine: StorageEngine::Log,
+        ..Default::default()
+    };
+
+    let db = Database::open(path_str, &config).unwrap();
+    db.set("a", Value::Integer(1)).unwrap();
+
+    // Writes past the current end of the log fail with EFBIG instead of killing the process
+    unsafe { signal(Signal::SIGXFSZ, SigHandler::SigIgn) }.unwrap();
+    let (soft, hard) = getrlimit(Resource::RLIMIT_FSIZE).unwrap();
+    let len = std::fs::metadata(&path).unwrap().len();
+    setrlimit(Resource::RLIMIT_FSIZE, len, hard).unwrap();
+    assert!(db.set("b", Value::String("x".repeat(100))).is_err());
+    setrlimit(Resource::RLIMIT_FSIZE, soft, hard).unwrap();
+
+    // The disk is writable again, but the record of "c" never reached the log
+    assert!(db.set("c", Value::Integer(3)).is_err());
+    assert!(db.set("d", Value::Integer(4)).is_err());
+
+    db.compact().unwrap();
+    db.set("e", Value::Integer(5)).unwrap();
+    drop(db);
+
+    let db = Database::open(path_str, &config).unwrap();
+    for key in ["a", "b", "c", "d", "e"] {
+        assert!(db.get(key).is_ok(), "{} was lost", key);
+    }
+    drop(db);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
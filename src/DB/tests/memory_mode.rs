@@ -0,0 +1,42 @@
+use rust_db::{CdcRetention, Database, StorageConfig, StorageEngine, Value};
+
+#[test]
+fn in_memory_database_has_no_files() {
+    let db = Database::in_memory();
+    assert_eq!(db.storage_engine(), StorageEngine::Memory);
+    db.set("user/1/name", Value::String("Alice".to_string())).unwrap();
+    db.flush().unwrap();
+    assert_eq!(db.get("user/1/name").unwrap(), Value::String("Alice".to_string()));
+    assert!(db.enable_cdc(CdcRetention::default()).is_err());
+}
+
+#[test]
+fn on_demand_persists_only_on_flush() {
+    let dir = std::env::temp_dir().join(format!("rust_db_on_demand_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for engine in [StorageEngine::Json, StorageEngine::Log] {
+        let path = dir.join(format!("{:?}.db", engine));
+        let path_str = path.to_str().unwrap();
+        let config = StorageConfig {
+            engine,
+            on_demand: true,
+            ..Default::default()
+        };
+
+        let db = Database::open(path_str, &config).unwrap();
+        db.set("a", Value::Integer(1)).unwrap();
+        db.delete("a").unwrap();
+        db.set("b", Value::Integer(2)).unwrap();
+        assert!(!path.exists());
+
+        db.flush().unwrap();
+        db.set("b", Value::Integer(3)).unwrap();
+        drop(db);
+
+        let db = Database::open(path_str, &config).unwrap();
+        assert!(db.get("a").is_err());
+        assert_eq!(db.get("b").unwrap(), Value::Integer(2));
+    }
+}
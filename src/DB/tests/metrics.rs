@@ -0,0 +1,146 @@
+use std::ffi::CString;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread::sleep;
+use std::time::Duration;
+
+use rust_db::{
+    db_create_memory, db_destroy, db_metrics_serve, db_set, BackupKind, Database, ImportOptions, SqliteExportConfig,
+    StreamFormat, Value,
+};
+
+#[test]
+fn counts_operations_hits_misses_and_expirations() {
+    let db = Database::in_memory();
+    db.set("a", Value::Integer(1)).unwrap();
+    db.set("b", Value::Integer(2)).unwrap();
+    db.get("a").unwrap();
+    assert!(db.get("missing").is_err());
+    db.increment("counter").unwrap();
+    db.delete("b").unwrap();
+
+    db.set_expiry("a", 0).unwrap();
+    sleep(Duration::from_millis(1100));
+    assert!(db.get("a").is_err());
+
+    let metrics = db.metrics();
+    assert_eq!(metrics.sets, 2);
+    assert_eq!(metrics.gets, 3);
+    assert_eq!(metrics.deletes, 1);
+    // increment looks the key up too
+    assert_eq!((metrics.hits, metrics.misses), (1, 3));
+    assert_eq!(metrics.expirations, 1);
+    assert_eq!(metrics.keys, 1);
+    assert_eq!(metrics.operations["increment"].count, 1);
+    assert!(metrics.persistence["persist"].count >= 5);
+
+    let get = &metrics.operations["get"];
+    assert_eq!(get.buckets.last().unwrap().count, get.count);
+    assert!(get.buckets.windows(2).all(|w| w[0].count <= w[1].count));
+}
+
+#[test]
+fn renders_prometheus_text_format() {
+    let db = Database::in_memory();
+    db.set("a", Value::Integer(1)).unwrap();
+    db.get("a").unwrap();
+
+    let text = db.prometheus_metrics();
+    assert!(text.contains("# TYPE rust_db_keyspace_hits_total counter\nrust_db_keyspace_hits_total 1\n"));
+    assert!(text.contains("rust_db_keys 1\n"));
+    assert!(text.contains("# TYPE rust_db_operation_duration_seconds histogram\n"));
+    assert!(text.contains("rust_db_operation_duration_seconds_bucket{operation=\"get\",le=\"+Inf\"} 1\n"));
+    assert!(text.contains("rust_db_operation_duration_seconds_count{operation=\"set\"} 1\n"));
+    assert!(text.contains("rust_db_persistence_duration_seconds_count{kind=\"persist\"} 1\n"));
+    for line in text.lines().filter(|l| !l.starts_with('#')) {
+        let value = line.rsplit(' ').next().unwrap();
+        assert!(value.parse::<f64>().is_ok(), "{}", line);
+    }
+}
+
+#[test]
+fn times_queries_backups_imports_and_exports() {
+    let dir = std::env::temp_dir().join(format!("rust_db_metrics_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = |name: &str| dir.join(name).to_str().unwrap().to_string();
+
+    let db = Database::in_memory();
+    db.enable_search(&[""]);
+    db.set("user/1", Value::String("Ann".to_string())).unwrap();
+    db.set("user/2", Value::Integer(30)).unwrap();
+
+    db.query("user/* ORDER BY value").unwrap();
+    db.search("ann", "", 10).unwrap();
+    db.aggregate("user/*").unwrap();
+    db.aggregate_by("user/*", 1).unwrap();
+
+    db.create_backup(&file("backup.json")).unwrap();
+    db.create_catalog_backup(&file("catalog"), BackupKind::Full).unwrap();
+    db.restore_from_backup(&file("backup.json")).unwrap();
+    // A catalog directory is restored once, not once per delegating call
+    db.restore_from_backup(&file("catalog")).unwrap();
+
+    db.export_json(&file("export.json")).unwrap();
+    db.import_json(&file("export.json")).unwrap();
+    let mut ndjson = Vec::new();
+    db.export_stream(&mut ndjson, StreamFormat::Ndjson, None).unwrap();
+    db.import_stream(&ndjson[..], StreamFormat::Ndjson, &ImportOptions::default()).unwrap();
+    db.export_sqlite(&file("db.sqlite"), &SqliteExportConfig::default()).unwrap();
+    assert!(db.import_json(&file("missing.json")).is_err());
+
+    let metrics = db.metrics();
+    let count = |op: &str| metrics.operations[op].count;
+    assert_eq!(count("query"), 1);
+    assert_eq!(count("search"), 1);
+    assert_eq!(count("aggregate"), 2);
+    assert_eq!(count("backup"), 2);
+    assert_eq!(count("restore"), 2);
+    assert_eq!(count("export"), 3);
+    assert_eq!(count("import"), 3);
+    assert!(db.prometheus_metrics().contains("rust_db_operation_duration_seconds_count{operation=\"restore\"} 2\n"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn http_get(addr: &str, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, addr).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn serves_metrics_over_http_until_stopped() {
+    // Volný port; listener se pak naváže na stejnou adresu
+    let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let db = db_create_memory();
+    let (key, value) = (CString::new("a").unwrap(), CString::new("1").unwrap());
+    db_set(db, key.as_ptr(), value.as_ptr());
+
+    assert!(db_metrics_serve(db, CString::new(addr.clone()).unwrap().as_ptr()));
+    let response = http_get(&addr, "/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+    assert!(response.contains("\r\n\r\n# HELP rust_db_keyspace_hits_total"));
+    assert!(response.contains("rust_db_keys 1\n"));
+    let body = response.split("\r\n\r\n").nth(1).unwrap();
+    assert!(response.contains(&format!("Content-Length: {}\r\n", body.len())));
+
+    assert!(http_get(&addr, "/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    let mut stream = TcpStream::connect(&addr).unwrap();
+    stream.write_all(b"POST /metrics HTTP/1.1\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+
+    assert!(db_metrics_serve(db, CString::new("").unwrap().as_ptr()));
+    assert!(TcpStream::connect(&addr).is_err());
+
+    // Dropping the database stops a running listener too
+    assert!(db_metrics_serve(db, CString::new(addr.clone()).unwrap().as_ptr()));
+    db_destroy(db);
+    assert!(TcpStream::connect(&addr).is_err());
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use rust_db::{NamespaceConfig, Namespaces, ReaperConfig, StorageConfig, StorageEngine, Value};
+
+#[test]
+fn namespaces_are_isolated_and_reopened_with_their_config() {
+    let dir = common::temp_dir("namespaces_reopen");
+    let dir_str = dir.to_str().unwrap();
+    {
+        let namespaces = Namespaces::open(dir_str).unwrap();
+        let config = NamespaceConfig {
+            storage: StorageConfig {
+                engine: StorageEngine::Log,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let sessions = namespaces.open_namespace("sessions", config).unwrap();
+        sessions.set("user/1", Value::String("token".to_string())).unwrap();
+        namespaces.current().set("user/1", Value::Integer(1)).unwrap();
+
+        assert_eq!(namespaces.switch("sessions").unwrap().get("user/1").unwrap(), Value::String("token".to_string()));
+        assert_eq!(namespaces.current_name(), "sessions");
+        assert!(namespaces.switch("missing").is_err());
+        assert!(namespaces.open_namespace("../etc", NamespaceConfig::default()).is_err());
+    }
+
+    let namespaces = Namespaces::open(dir_str).unwrap();
+    assert_eq!(namespaces.names(), vec!["default", "sessions"]);
+    assert_eq!(namespaces.current_name(), "default");
+    let sessions = namespaces.get("sessions").unwrap();
+    assert_eq!(sessions.storage_engine(), StorageEngine::Log);
+    assert_eq!(sessions.get("user/1").unwrap(), Value::String("token".to_string()));
+    assert_eq!(namespaces.current().get("user/1").unwrap(), Value::Integer(1));
+
+    let stats = namespaces.stats();
+    assert_eq!(stats.len(), 2);
+    assert!(stats[0].current && stats[0].stats.total_keys == 1);
+
+    let current = namespaces.switch("sessions").unwrap();
+    // Handles would keep writing into the deleted files
+    assert!(namespaces.drop_namespace("sessions").is_err());
+    drop(current);
+    assert!(namespaces.drop_namespace("sessions").is_err());
+    drop(sessions);
+    namespaces.drop_namespace("sessions").unwrap();
+    assert_eq!(namespaces.current_name(), "default");
+    assert!(!dir.join("sessions.db").exists());
+    assert!(namespaces.drop_namespace("default").is_err());
+    assert_eq!(Namespaces::open(dir_str).unwrap().names(), vec!["default"]);
+}
+
+#[test]
+fn reaper_removes_expired_keys_per_namespace() {
+    let namespaces = Namespaces::in_memory();
+    let cache = namespaces.open_namespace("cache", NamespaceConfig {
+        reaper: ReaperConfig {
+            interval_ms: 20,
+            max_keys: 10,
+        },
+        ..Default::default()
+    }).unwrap();
+    let idle = namespaces.open_namespace("idle", NamespaceConfig {
+        reaper: ReaperConfig {
+            interval_ms: 0,
+            ..Default::default()
+        },
+        ..Default::default()
+    }).unwrap();
+
+    for db in [&cache, &idle] {
+        db.set("short", Value::Integer(1)).unwrap();
+        db.set_expiry("short", 0).unwrap();
+        db.set("long", Value::Integer(2)).unwrap();
+    }
+
+    let started = Instant::now();
+    while cache.get_stats().total_keys > 1 && started.elapsed() < Duration::from_secs(5) {
+        sleep(Duration::from_millis(20));
+    }
+    assert_eq!(cache.get_stats().total_keys, 1);
+    assert!(cache.exists("long"));
+    assert_eq!(cache.reaper_status().unwrap().expired_keys, 1);
+    assert_eq!(cache.metrics().expirations, 1);
+
+    assert_eq!(idle.get_stats().total_keys, 2);
+    assert!(idle.reaper_status().is_none());
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use rust_db::{Database, Value, MAX_PUBSUB_CLIENTS};
+
+fn command(args: &[&str]) -> Vec<u8> {
+    let mut out = format!("*{}\r\n", args.len());
+    for arg in args {
+        out.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    out.into_bytes()
+}
+
+// Reads one RESP reply as a flat list of its items
+fn reply(reader: &mut BufReader<TcpStream>) -> Vec<String> {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let line = line.trim_end().to_string();
+    match line.as_bytes()[0] {
+        b'*' => {
+            let count: usize = line[1..].parse().unwrap();
+            (0..count).flat_map(|_| reply(reader)).collect()
+        }
+        b'$' => {
+            let len: i64 = line[1..].parse().unwrap();
+            if len < 0 {
+                return vec!["(nil)".to_string()];
+            }
+            let mut data = vec![0u8; len as usize + 2];
+            reader.read_exact(&mut data).unwrap();
+            vec![String::from_utf8(data[..len as usize].to_vec()).unwrap()]
+        }
+        _ => vec![line],
+    }
+}
+
+fn connect(db: &Database) -> (TcpStream, BufReader<TcpStream>) {
+    let addr = db.serve_pubsub("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let reader = BufReader::new(stream.try_clone().unwrap());
+    (stream, reader)
+}
+
+#[test]
+fn subscribe_and_psubscribe_receive_changes() {
+    let dir = common::temp_dir("pubsub");
+    let db = Database::new(dir.join("db.json").to_str().unwrap());
+    let (mut stream, mut reader) = connect(&db);
+
+    stream.write_all(&command(&["SUBSCRIBE", "user/1/name"])).unwrap();
+    assert_eq!(reply(&mut reader), vec!["subscribe", "user/1/name", ":1"]);
+    stream.write_all(&command(&["PSUBSCRIBE", "user/*/session"])).unwrap();
+    assert_eq!(reply(&mut reader), vec!["psubscribe", "user/*/session", ":2"]);
+
+    db.set("user/1/name", Value::String("Ann".to_string())).unwrap();
+    db.set("user/2/name", Value::String("Bob".to_string())).unwrap();
+    db.set("user/2/session", Value::Integer(1)).unwrap();
+    db.delete("user/1/name").unwrap();
+
+    let message = reply(&mut reader);
+    assert_eq!(&message[..2], ["message", "user/1/name"]);
+    let event: serde_json::Value = serde_json::from_str(&message[2]).unwrap();
+    assert_eq!(event["kind"], "set");
+    assert_eq!(event["path"], "user/1/name");
+
+    let message = reply(&mut reader);
+    assert_eq!(&message[..3], ["pmessage", "user/*/session", "user/2/session"]);
+
+    let message = reply(&mut reader);
+    assert_eq!(&message[..2], ["message", "user/1/name"]);
+    let event: serde_json::Value = serde_json::from_str(&message[2]).unwrap();
+    assert_eq!(event["kind"], "delete");
+
+    stream.write_all(&command(&["UNSUBSCRIBE"])).unwrap();
+    assert_eq!(reply(&mut reader), vec!["unsubscribe", "user/1/name", ":1"]);
+    stream.write_all(&command(&["PING"])).unwrap();
+    assert_eq!(reply(&mut reader), vec!["+PONG"]);
+    stream.write_all(&command(&["GET", "user/1/name"])).unwrap();
+    assert!(reply(&mut reader)[0].starts_with("-ERR"));
+    stream.write_all(&command(&["QUIT"])).unwrap();
+    assert_eq!(reply(&mut reader), vec!["+OK"]);
+}
+
+#[test]
+fn commands_split_across_packets_are_assembled() {
+    let dir = common::temp_dir("pubsub_2");
+    let db = Database::new(dir.join("db.json").to_str().unwrap());
+    let (mut stream, mut reader) = connect(&db);
+
+    let subscribe = command(&["SUBSCRIBE", "a"]);
+    for byte in subscribe {
+        stream.write_all(&[byte]).unwrap();
+        stream.flush().unwrap();
+    }
+    assert_eq!(reply(&mut reader), vec!["subscribe", "a", ":1"]);
+
+    stream.write_all(b"GARBAGE\r\n").unwrap();
+    assert!(reply(&mut reader)[0].starts_with("-ERR"));
+}
+
+#[test]
+fn stopping_closes_the_listener() {
+    let dir = common::temp_dir("pubsub_3");
+    let db = Database::new(dir.join("db.json").to_str().unwrap());
+    let addr = db.serve_pubsub("127.0.0.1:0").unwrap();
+    db.stop_pubsub();
+    assert!(TcpStream::connect(addr).is_err());
+}
+
+#[test]
+fn clients_beyond_the_limit_are_rejected() {
+    let dir = common::temp_dir("pubsub_limit");
+    let db = Database::new(dir.join("db.json").to_str().unwrap());
+    let addr = db.serve_pubsub("127.0.0.1:0").unwrap();
+    let ping = |stream: &mut TcpStream| -> Vec<String> {
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let _ = stream.write_all(&command(&["PING"]));
+        reply(&mut BufReader::new(stream.try_clone().unwrap()))
+    };
+
+    let mut clients: Vec<TcpStream> = (0..MAX_PUBSUB_CLIENTS).map(|_| TcpStream::connect(addr).unwrap()).collect();
+    for client in &mut clients {
+        assert_eq!(ping(client), vec!["+PONG"]);
+    }
+    let extra = TcpStream::connect(addr).unwrap();
+    extra.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    assert_eq!(reply(&mut BufReader::new(extra)), vec!["-ERR max number of clients reached"]);
+
+    // A disconnected client frees its place
+    clients.pop();
+    let mut accepted = false;
+    for _ in 0..100 {
+        let mut client = TcpStream::connect(addr).unwrap();
+        if ping(&mut client) == vec!["+PONG"] {
+            accepted = true;
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert!(accepted);
+}
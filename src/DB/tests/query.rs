@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use rust_db::{Database, Value};
+
+fn article(status: &str, price: &str) -> Value {
+    let mut map = HashMap::new();
+    map.insert("status".to_string(), status.to_string());
+    map.insert("price".to_string(), price.to_string());
+    Value::Map(map)
+}
+
+fn paths(db: &Database, query: &str) -> Vec<String> {
+    db.query(query).unwrap().into_iter().map(|r| r.path).collect()
+}
+
+#[test]
+fn filters_orders_and_limits() {
+    let db = Database::in_memory();
+    db.set("article/1/meta", article("published", "10")).unwrap();
+    db.set("article/2/meta", article("draft", "9")).unwrap();
+    db.set("article/3/meta", article("published", "25.5")).unwrap();
+    db.set("article/4/meta", article("published", "free")).unwrap();
+    db.set("user/1/meta", article("published", "1")).unwrap();
+
+    assert_eq!(
+        paths(&db, "article/*/meta WHERE status = published ORDER BY price"),
+        vec!["article/1/meta", "article/3/meta", "article/4/meta"]
+    );
+    assert_eq!(
+        paths(&db, "article/*/meta WHERE status != published OR price > 20 ORDER BY path DESC"),
+        vec!["article/3/meta", "article/2/meta"]
+    );
+    assert_eq!(paths(&db, "article/*/meta WHERE NOT (status ~ pub*) "), vec!["article/2/meta"]);
+    assert_eq!(paths(&db, "article/*/meta ORDER BY price DESC LIMIT 2"), vec!["article/4/meta", "article/3/meta"]);
+    assert!(db.query("article/*/meta WHERE status =").is_err());
+}
+
+#[test]
+fn ordering_mixed_types_is_total() {
+    let db = Database::in_memory();
+    let values = [
+        Value::Float(f64::NAN),
+        Value::Integer(3),
+        Value::String("b".to_string()),
+        Value::Bool(true),
+        Value::Null,
+        Value::Float(-1.5),
+        Value::String("10".to_string()),
+        Value::Bool(false),
+        Value::String("a".to_string()),
+        Value::Float(f64::INFINITY),
+    ];
+    for round in 0..20 {
+        for (i, value) in values.iter().enumerate() {
+            db.set(&format!("mixed/{:02}", (i * 7 + round) % values.len()), value.clone()).unwrap();
+        }
+        let sorted: Vec<Value> = db.query("mixed/* ORDER BY value").unwrap().into_iter().map(|r| r.value).collect();
+        let rendered: Vec<String> = sorted.iter().map(|v| format!("{:?}", v)).collect();
+        // Numbers (numeric strings included), then strings, booleans and null
+        assert_eq!(rendered, vec![
+            "Float(-1.5)", "Integer(3)", "String(\"10\")", "Float(inf)", "Float(NaN)",
+            "String(\"a\")", "String(\"b\")", "Bool(false)", "Bool(true)", "Null",
+        ]);
+    }
+}
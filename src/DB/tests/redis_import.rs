@@ -0,0 +1,241 @@
+mod common;
+
+use std::collections::HashMap;
+
+use rust_db::{map_redis_key, Database, RedisImportConfig, Value};
+
+fn string(out: &mut Vec<u8>, value: &str) {
+    assert!(value.len() < 64);
+    out.push(value.len() as u8);
+    out.extend_from_slice(value.as_bytes());
+}
+
+// Listpack with short string entries only
+fn listpack(items: &[&str]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for item in items {
+        body.push(0x80 | item.len() as u8);
+        body.extend_from_slice(item.as_bytes());
+        body.push(1 + item.len() as u8);
+    }
+    body.push(0xFF);
+
+    let mut blob = ((body.len() + 6) as u32).to_le_bytes().to_vec();
+    blob.extend_from_slice(&(items.len() as u16).to_le_bytes());
+    blob.extend(body);
+    blob
+}
+
+fn blob(out: &mut Vec<u8>, data: &[u8]) {
+    assert!(data.len() < 64);
+    out.push(data.len() as u8);
+    out.extend_from_slice(data);
+}
+
+fn sample_rdb() -> Vec<u8> {
+    let far_future_ms: u64 = 4_102_444_800_000; // 2100-01-01
+    let mut rdb = b"REDIS0011".to_vec();
+    rdb.push(0xFA);
+    string(&mut rdb, "redis-ver");
+    string(&mut rdb, "7.2.0");
+    rdb.push(0xFE);
+    rdb.push(0);
+    rdb.extend_from_slice(&[0xFB, 5, 2]);
+
+    // plain string
+    rdb.push(0);
+    string(&mut rdb, "article:1:title");
+    string(&mut rdb, "Hello");
+
+    // integer encoded string with an expiry
+    rdb.push(0xFC);
+    rdb.extend_from_slice(&far_future_ms.to_le_bytes());
+    rdb.push(0);
+    string(&mut rdb, "article:1:views");
+    rdb.extend_from_slice(&[0xC0, 42]);
+
+    // already expired
+    rdb.push(0xFC);
+    rdb.extend_from_slice(&1000u64.to_le_bytes());
+    rdb.push(0);
+    string(&mut rdb, "session:old");
+    string(&mut rdb, "gone");
+
+    // hash as listpack
+    rdb.push(16);
+    string(&mut rdb, "versioned:1");
+    blob(&mut rdb, &listpack(&["current_version", "v2", "updated_at", "1700000000"]));
+
+    // sorted set with binary scores
+    rdb.push(5);
+    string(&mut rdb, "versioned:1:versions");
+    rdb.push(2);
+    string(&mut rdb, "v1");
+    rdb.extend_from_slice(&1.0f64.to_le_bytes());
+    string(&mut rdb, "v2");
+    rdb.extend_from_slice(&2.0f64.to_le_bytes());
+
+    // list as quicklist of one listpack node
+    rdb.push(18);
+    string(&mut rdb, "queue");
+    rdb.push(1);
+    rdb.push(2);
+    blob(&mut rdb, &listpack(&["a", "b", "c"]));
+
+    // set as intset of 16bit numbers
+    rdb.push(11);
+    string(&mut rdb, "ids");
+    let mut intset = 2u32.to_le_bytes().to_vec();
+    intset.extend_from_slice(&2u32.to_le_bytes());
+    intset.extend_from_slice(&7i16.to_le_bytes());
+    intset.extend_from_slice(&9i16.to_le_bytes());
+    blob(&mut rdb, &intset);
+
+    // second database
+    rdb.push(0xFE);
+    rdb.push(1);
+    rdb.push(0);
+    string(&mut rdb, "other:key");
+    string(&mut rdb, "db1");
+
+    rdb.push(0xFF);
+    rdb.extend_from_slice(&[0; 8]);
+    rdb
+}
+
+fn resp(args: &[&str]) -> String {
+    let mut out = format!("*{}\r\n", args.len());
+    for arg in args {
+        out.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    out
+}
+
+#[test]
+fn imports_rdb_types_and_expiries() {
+    let dir = common::temp_dir("redis_rdb");
+    let dump = dir.join("dump.rdb");
+    std::fs::write(&dump, sample_rdb()).unwrap();
+
+    let db = Database::new(dir.join("db.json").to_str().unwrap());
+    let config = RedisImportConfig {
+        prefix: "redis".to_string(),
+        db: Some(0),
+        ..Default::default()
+    };
+    let summary = db.import_redis(dump.to_str().unwrap(), &config).unwrap();
+
+    assert_eq!(summary.keys, 6);
+    assert_eq!(summary.expired, 1);
+    assert_eq!(db.get("redis/article/1/title").unwrap(), Value::String("Hello".to_string()));
+    assert_eq!(db.get("redis/article/1/views").unwrap(), Value::String("42".to_string()));
+    assert_eq!(db.ttl("redis/article/1/views").unwrap().map(|t| t > 0), Some(true));
+    assert_eq!(
+        db.get("redis/queue").unwrap(),
+        Value::Array(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+    );
+    assert_eq!(db.get("redis/ids").unwrap(), Value::Array(vec!["7".to_string(), "9".to_string()]));
+
+    let Value::Map(hash) = db.get("redis/versioned/1").unwrap() else { panic!("hash expected") };
+    assert_eq!(hash["current_version"], "v2");
+    let Value::Map(versions) = db.get("redis/versioned/1/versions").unwrap() else { panic!("zset expected") };
+    assert_eq!(versions, HashMap::from([("v1".to_string(), "1".to_string()), ("v2".to_string(), "2".to_string())]));
+    assert!(db.get("redis/other/key").is_err());
+}
+
+#[test]
+fn replays_aof_with_rdb_preamble() {
+    let dir = common::temp_dir("redis_aof");
+    let mut aof = sample_rdb();
+    let commands = [
+        resp(&["SELECT", "0"]),
+        resp(&["SET", "article:2:title", "Second"]),
+        resp(&["HSET", "versioned:1", "current_version", "v3"]),
+        resp(&["ZADD", "versioned:1:versions", "3", "v3"]),
+        resp(&["RPUSH", "queue", "d"]),
+        resp(&["LPOP", "queue"]),
+        resp(&["DEL", "article:1:title"]),
+        resp(&["INCRBY", "article:1:views", "8"]),
+        resp(&["OBJECT", "FREQ", "queue"]),
+    ];
+    aof.extend(commands.concat().into_bytes());
+    // Truncated last command, as left behind by a crash
+    aof.extend_from_slice(b"*3\r\n$3\r\nSET\r\n$1\r\nx");
+    let path = dir.join("appendonly.aof");
+    std::fs::write(&path, aof).unwrap();
+
+    let db = Database::new(dir.join("db.json").to_str().unwrap());
+    let config = RedisImportConfig {
+        db: Some(0),
+        ..Default::default()
+    };
+    let summary = db.import_redis(path.to_str().unwrap(), &config).unwrap();
+
+    assert_eq!(summary.unsupported_commands.get("OBJECT"), Some(&1));
+    assert!(db.get("article/1/title").is_err());
+    assert_eq!(db.get("article/2/title").unwrap(), Value::String("Second".to_string()));
+    assert_eq!(db.get("article/1/views").unwrap(), Value::String("50".to_string()));
+    assert_eq!(
+        db.get("queue").unwrap(),
+        Value::Array(vec!["b".to_string(), "c".to_string(), "d".to_string()])
+    );
+    let Value::Map(versions) = db.get("versioned/1/versions").unwrap() else { panic!("zset expected") };
+    assert_eq!(versions.len(), 3);
+    assert!(db.get("x").is_err());
+
+    // Corruption before the end is not mistaken for a truncated tail
+    for corrupt in [&b"*3\r\n$3\r\nSET\r\n$1\r\nxy\r\n$1\r\n1\r\n"[..], b"SET x 1\r\n", b"*1\r\n#4\r\nPING\r\n"] {
+        let mut aof = corrupt.to_vec();
+        aof.extend(resp(&["SET", "after", "1"]).into_bytes());
+        std::fs::write(&path, aof).unwrap();
+        assert!(db.import_redis(path.to_str().unwrap(), &config).is_err());
+        assert!(db.get("after").is_err());
+    }
+}
+
+#[test]
+fn key_mapping_uses_separator_and_prefix() {
+    assert_eq!(map_redis_key("user:1:name", ":", ""), Some("user/1/name".to_string()));
+    assert_eq!(map_redis_key("user.1", ".", "legacy/redis"), Some("legacy/redis/user/1".to_string()));
+    assert_eq!(map_redis_key("::a::b:", ":", ""), Some("a/b".to_string()));
+    assert_eq!(map_redis_key(":::", ":", ""), None);
+}
+
+#[test]
+fn hostile_input_is_rejected_without_panicking() {
+    let dir = common::temp_dir("redis_hostile");
+    let path = dir.join("dump");
+    let import = |data: &[u8]| {
+        std::fs::write(&path, data).unwrap();
+        Database::in_memory().import_redis(path.to_str().unwrap(), &RedisImportConfig::default())
+    };
+
+    // Argument count far beyond the data, once as a torn tail and once as garbage
+    assert!(import(b"*18446744073709551615\r\n").is_ok());
+    assert!(import(b"*4611686018427387904\r\n$3\r\nSET\r\n").is_ok());
+    // Times and counters that overflow
+    assert!(import(resp(&["SET", "k", "v", "EX", "9223372036854775807"]).as_bytes()).is_err());
+    assert!(import(resp(&["SETEX", "k", "9223372036854775807", "v"]).as_bytes()).is_err());
+    assert!(import(resp(&["SET", "k", "1"]).as_bytes()).is_ok());
+    let overflow = [resp(&["SET", "k", "9223372036854775807"]), resp(&["INCR", "k"])].concat();
+    assert!(import(overflow.as_bytes()).is_err());
+    assert!(import(resp(&["DECRBY", "k", "-9223372036854775808"]).as_bytes()).is_err());
+
+    // LZF string claiming a 2^64-1 byte result
+    let mut rdb = b"REDIS0011".to_vec();
+    rdb.extend_from_slice(&[0xFE, 0, 0]);
+    string(&mut rdb, "k");
+    rdb.extend_from_slice(&[0xC3, 2, 0x81]);
+    rdb.extend_from_slice(&u64::MAX.to_be_bytes());
+    rdb.extend_from_slice(&[0, b'x']);
+    rdb.push(0xFF);
+    assert!(import(&rdb).is_err());
+
+    // Every single byte of a valid dump flipped
+    let sample = sample_rdb();
+    for i in 0..sample.len() {
+        let mut mutated = sample.clone();
+        mutated[i] ^= 0xFF;
+        let _ = import(&mutated);
+    }
+}
@@ -0,0 +1,432 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use hmac::{Hmac, Mac};
+use rust_db::{BackupKind, BackupTarget, Database, S3Config, S3Target, Value};
+use sha2::{Digest, Sha256};
+
+const MIB: usize = 1024 * 1024;
+
+#[derive(Default)]
+struct MockState {
+    objects: BTreeMap<String, Vec<u8>>,
+    uploads: HashMap<String, BTreeMap<u32, Vec<u8>>>,
+    multipart_completed: usize,
+    // Requests that came in below the /gateway/s3 endpoint path
+    gateway_requests: usize,
+}
+
+struct MockS3 {
+    endpoint: String,
+    state: Arc<Mutex<MockState>>,
+}
+
+struct Request {
+    method: String,
+    // Path and query as sent, i.e. already URI-encoded
+    raw_path: String,
+    raw_query: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap();
+            out.push(u8::from_str_radix(hex, 16).unwrap());
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).unwrap()
+}
+
+fn read_request(reader: &mut BufReader<TcpStream>) -> Option<Request> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).ok()? == 0 {
+        return None;
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?.to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).ok()?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_once(':')?;
+        headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+
+    let length: usize = headers.get("content-length").and_then(|l| l.parse().ok()).unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+
+    let (path, raw_query) = target.split_once('?').unwrap_or((&target, ""));
+    let query = raw_query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(k), percent_decode(v))
+        })
+        .collect();
+
+    Some(Request {
+        method,
+        raw_path: path.to_string(),
+        raw_query: raw_query.to_string(),
+        path: percent_decode(path),
+        query,
+        headers,
+        body,
+    })
+}
+
+fn respond(stream: &mut TcpStream, status: u16, headers: &[(&str, String)], body: &[u8]) {
+    let mut response = format!("HTTP/1.1 {} X\r\nContent-Length: {}\r\n", status, body.len());
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    stream.write_all(response.as_bytes()).unwrap();
+    stream.write_all(body).unwrap();
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// SigV4 signature computed independently of the client; `headers` are the signed
+// headers with lowercase names in sorted order
+fn sigv4_signature(
+    secret: &str,
+    region: &str,
+    method: &str,
+    canonical_uri: &str,
+    raw_query: &str,
+    headers: &[(&str, &str)],
+) -> String {
+    let mut query: Vec<String> = raw_query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| if pair.contains('=') { pair.to_string() } else { format!("{}=", pair) })
+        .collect();
+    query.sort();
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let payload_hash = headers.iter().find(|(name, _)| *name == "x-amz-content-sha256").unwrap().1;
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method, canonical_uri, query.join("&"), canonical_headers, signed_headers, payload_hash
+    );
+
+    let amz_date = headers.iter().find(|(name, _)| *name == "x-amz-date").unwrap().1;
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+        amz_date, scope, Sha256::digest(canonical_request.as_bytes())
+    );
+    let mut key = format!("AWS4{}", secret).into_bytes();
+    for part in [date, region, "s3", "aws4_request"] {
+        key = hmac_sha256(&key, part);
+    }
+    hmac_sha256(&key, &string_to_sign).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Recomputes the signature from the request as received and compares it with the header
+fn signature_valid(request: &Request) -> bool {
+    let Some(auth) = request.headers.get("authorization") else {
+        return false;
+    };
+    let Some(fields) = auth.strip_prefix("AWS4-HMAC-SHA256 ") else {
+        return false;
+    };
+    let fields: HashMap<&str, &str> = fields.split(", ").filter_map(|field| field.split_once('=')).collect();
+    let (Some(credential), Some(signed), Some(signature)) =
+        (fields.get("Credential"), fields.get("SignedHeaders"), fields.get("Signature"))
+    else {
+        return false;
+    };
+    let scope: Vec<&str> = credential.split('/').collect();
+    if scope.len() != 5 || scope[0] != "test-key" {
+        return false;
+    }
+
+    let mut headers = Vec::new();
+    for name in signed.split(';') {
+        match request.headers.get(name) {
+            Some(value) => headers.push((name, value.as_str())),
+            None => return false,
+        }
+    }
+    if !signed.contains("x-amz-date") || !signed.contains("x-amz-content-sha256") {
+        return false;
+    }
+    *signature == sigv4_signature("test-secret", scope[2], &request.method, &request.raw_path, &request.raw_query, &headers)
+}
+
+// S3 escapes keys in XML listings, as well as &apos; and &quot;
+fn xml_escape(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn handle(state: &Mutex<MockState>, request: Request) -> (u16, Vec<(&'static str, String)>, Vec<u8>) {
+    let authorized = signature_valid(&request);
+    let payload_ok = request.headers.get("x-amz-content-sha256")
+        .map(|hash| *hash == format!("{:x}", Sha256::digest(&request.body)))
+        .unwrap_or(false);
+    if !authorized || !payload_ok {
+        return (403, vec![], b"<Error><Code>AccessDenied</Code></Error>".to_vec());
+    }
+
+    let mut path = request.path.trim_start_matches('/');
+    if let Some(rest) = path.strip_prefix("gateway/s3/") {
+        path = rest;
+        state.lock().unwrap().gateway_requests += 1;
+    }
+    let (bucket, key) = path.split_once('/').unwrap_or((path, ""));
+    if bucket != "backups" {
+        return (404, vec![], b"<Error><Code>NoSuchBucket</Code></Error>".to_vec());
+    }
+
+    let mut state = state.lock().unwrap();
+    match (request.method.as_str(), key.is_empty()) {
+        ("GET", true) => {
+            let prefix = request.query.get("prefix").cloned().unwrap_or_default();
+            let keys: String = state.objects.keys()
+                .filter(|k| k.starts_with(&prefix))
+                .map(|k| format!("<Contents><Key>{}</Key></Contents>", xml_escape(k)))
+                .collect();
+            let body = format!("<ListBucketResult><IsTruncated>false</IsTruncated>{}</ListBucketResult>", keys);
+            (200, vec![], body.into_bytes())
+        }
+        ("POST", false) if request.query.contains_key("uploads") => {
+            let upload_id = format!("upload-{}", state.uploads.len() + 1);
+            state.uploads.insert(upload_id.clone(), BTreeMap::new());
+            let body = format!("<InitiateMultipartUploadResult><UploadId>{}</UploadId></InitiateMultipartUploadResult>", upload_id);
+            (200, vec![], body.into_bytes())
+        }
+        ("PUT", false) if request.query.contains_key("uploadId") => {
+            let upload_id = &request.query["uploadId"];
+            let part: u32 = request.query["partNumber"].parse().unwrap();
+            let etag = format!("\"{:x}\"", Sha256::digest(&request.body));
+            state.uploads.get_mut(upload_id).unwrap().insert(part, request.body);
+            (200, vec![("ETag", etag)], Vec::new())
+        }
+        ("POST", false) if request.query.contains_key("uploadId") => {
+            let parts = state.uploads.remove(&request.query["uploadId"]).unwrap();
+            let data: Vec<u8> = parts.into_values().flatten().collect();
+            state.objects.insert(key.to_string(), data);
+            state.multipart_completed += 1;
+            (200, vec![], b"<CompleteMultipartUploadResult></CompleteMultipartUploadResult>".to_vec())
+        }
+        ("PUT", false) => {
+            state.objects.insert(key.to_string(), request.body);
+            (200, vec![("ETag", "\"x\"".to_string())], Vec::new())
+        }
+        ("GET", false) => match state.objects.get(key) {
+            Some(data) => (200, vec![], data.clone()),
+            None => (404, vec![], b"<Error><Code>NoSuchKey</Code></Error>".to_vec()),
+        },
+        ("DELETE", false) => {
+            state.objects.remove(key);
+            (204, vec![], Vec::new())
+        }
+        _ => (400, vec![], b"<Error><Code>BadRequest</Code></Error>".to_vec()),
+    }
+}
+
+impl MockS3 {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let server_state = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = server_state.clone();
+                thread::spawn(move || {
+                    let mut writer = stream.try_clone().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    while let Some(request) = read_request(&mut reader) {
+                        let (status, headers, body) = handle(&state, request);
+                        respond(&mut writer, status, &headers, &body);
+                    }
+                });
+            }
+        });
+
+        MockS3 { endpoint, state }
+    }
+
+    fn config(&self, part_size: usize) -> S3Config {
+        S3Config {
+            endpoint: self.endpoint.clone(),
+            bucket: "backups".to_string(),
+            access_key: "test-key".to_string(),
+            secret_key: "test-secret".to_string(),
+            region: "us-east-1".to_string(),
+            prefix: "rust-db".to_string(),
+            part_size,
+        }
+    }
+
+    fn target(&self, part_size: usize) -> S3Target {
+        S3Target::new(self.config(part_size)).unwrap()
+    }
+}
+
+fn temp_db(name: &str) -> Database {
+    let dir = std::env::temp_dir().join(format!("rust_db_s3_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    Database::new(dir.join("db.json").to_str().unwrap())
+}
+
+#[test]
+fn backup_round_trip_uses_multipart_for_large_objects() {
+    let s3 = MockS3::start();
+    // Parts below the S3 minimum would be rejected by the server, chunks(0) would panic
+    for part_size in [0, 1024, MIB * 5 - 1] {
+        assert!(S3Target::new(s3.config(part_size)).is_err());
+    }
+    let target = s3.target(MIB * 5);
+
+    let db = temp_db("multipart");
+    for i in 0..200 {
+        db.set(&format!("article/{}/title", i), Value::String(format!("Article number {}", i))).unwrap();
+    }
+    db.set("article/body", Value::String("x".repeat(MIB * 6))).unwrap();
+    let manifest = db.create_backup_to(&target, "full.json").unwrap();
+    assert_eq!(manifest.key_count, 201);
+    assert!(s3.state.lock().unwrap().multipart_completed >= 1);
+    assert!(s3.state.lock().unwrap().objects.contains_key("rust-db/full.json.manifest.json"));
+
+    let verification = Database::verify_backup_in(&target, "full.json").unwrap();
+    assert!(verification[0].valid, "{:?}", verification[0].errors);
+
+    let restored = temp_db("multipart_restore");
+    restored.restore_from_target(&target, "full.json").unwrap();
+    assert_eq!(restored.find_by_path("article").unwrap().len(), 201);
+}
+
+#[test]
+fn catalog_chain_restores_from_s3() {
+    let s3 = MockS3::start();
+    let target = s3.target(8 * 1024 * 1024);
+
+    let db = temp_db("catalog");
+    db.set("user/1/name", Value::String("Alice".to_string())).unwrap();
+    db.set("user/2/name", Value::String("Bob".to_string())).unwrap();
+    db.create_catalog_backup_in(&target, BackupKind::Full).unwrap();
+
+    db.delete("user/1/name").unwrap();
+    db.set("user/3/name", Value::String("Carol".to_string())).unwrap();
+    let record = db.create_catalog_backup_in(&target, BackupKind::Incremental).unwrap();
+    assert_eq!(record.kind, BackupKind::Incremental);
+
+    let restored = temp_db("catalog_restore");
+    restored.restore_catalog_from(&target, None).unwrap();
+    let mut keys: Vec<String> = restored.find_by_path("user").unwrap().into_keys().collect();
+    keys.sort();
+    assert_eq!(keys, vec!["user/2/name", "user/3/name"]);
+
+    let listed = target.list("").unwrap();
+    assert!(listed.contains(&"catalog.json".to_string()));
+}
+
+#[test]
+fn mock_signature_matches_aws_test_vector() {
+    // "GET Object" example from the AWS SigV4 documentation for S3
+    let empty_hash = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    let signature = sigv4_signature(
+        "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY",
+        "us-east-1",
+        "GET",
+        "/test.txt",
+        "",
+        &[
+            ("host", "examplebucket.s3.amazonaws.com"),
+            ("range", "bytes=0-9"),
+            ("x-amz-content-sha256", empty_hash),
+            ("x-amz-date", "20130524T000000Z"),
+        ],
+    );
+    assert_eq!(signature, "f0e8bdb87c964420e857bd35b5d6ed310bd44f0170aba48dd91039c6036bdb41");
+}
+
+#[test]
+fn rejected_credentials_surface_as_errors() {
+    let s3 = MockS3::start();
+    let wrong_key = S3Config {
+        access_key: "wrong-key".to_string(),
+        ..s3.config(MIB * 5)
+    };
+    let wrong_secret = S3Config {
+        secret_key: "wrong-secret".to_string(),
+        ..s3.config(MIB * 5)
+    };
+    for config in [wrong_key, wrong_secret] {
+        let err = S3Target::new(config).unwrap().put("x.json", b"{}").unwrap_err();
+        assert!(err.to_string().contains("AccessDenied"));
+    }
+}
+
+#[test]
+fn listing_unescapes_xml_entities() {
+    let s3 = MockS3::start();
+    let target = s3.target(MIB * 5);
+    let names = ["a&b.json", "<tag>.json", "it's \"quoted\".json", "x&lt;y.json"];
+    for name in names {
+        target.put(name, b"{}").unwrap();
+    }
+    let mut listed = target.list("").unwrap();
+    listed.sort();
+    let mut expected: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+    expected.sort();
+    assert_eq!(listed, expected);
+}
+
+#[test]
+fn endpoint_path_is_part_of_the_signed_uri() {
+    let s3 = MockS3::start();
+    let target = S3Target::new(S3Config {
+        endpoint: format!("{}/gateway/s3/", s3.endpoint),
+        ..s3.config(MIB * 5)
+    })
+    .unwrap();
+    target.put("a.json", b"{}").unwrap();
+    assert_eq!(target.get("a.json").unwrap(), b"{}");
+    assert_eq!(s3.state.lock().unwrap().gateway_requests, 2);
+    assert!(s3.state.lock().unwrap().objects.contains_key("rust-db/a.json"));
+
+    for endpoint in ["", "minio:9000", "ftp://minio", "http://", "http://:9000", "http://user:pw@minio", "http://minio/a//b", "http://minio?x=1"] {
+        let config = S3Config {
+            endpoint: endpoint.to_string(),
+            ..s3.config(MIB * 5)
+        };
+        assert!(S3Target::new(config).is_err(), "{}", endpoint);
+    }
+}
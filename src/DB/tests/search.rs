@@ -0,0 +1,38 @@
+mod common;
+
+use rust_db::{Database, Value};
+
+fn text(value: &str) -> Value {
+    Value::String(value.to_string())
+}
+
+#[test]
+fn search_ranks_matches_and_follows_writes() {
+    let dir = common::temp_dir("search");
+    let db = Database::new(dir.join("db.json").to_str().unwrap());
+    db.set("article/1/body", text("Rust databases and indexing")).unwrap();
+    db.set("article/2/body", text("Indexing, indexing and more indexing in Přerov")).unwrap();
+    db.set("article/3/body", text("Cooking recipes")).unwrap();
+    db.set("user/1/bio", text("Writes about indexes")).unwrap();
+    assert!(db.search("indexing", "", 10).is_err());
+
+    db.enable_search(&["article"]);
+    let hits = db.search("indexing", "", 10).unwrap();
+    let paths: Vec<&str> = hits.iter().map(|h| h.path.as_str()).collect();
+    // Only the configured prefix is indexed, more occurrences score higher
+    assert_eq!(paths, vec!["article/2/body", "article/1/body"]);
+    assert!(hits[0].score > hits[1].score);
+
+    // Stemming and diacritics folding
+    assert_eq!(db.search("PREROV index", "", 10).unwrap()[0].path, "article/2/body");
+
+    db.set("article/3/body", text("Indexed cooking recipes")).unwrap();
+    db.delete("article/2/body").unwrap();
+    let paths: Vec<String> = db.search("index", "article", 10).unwrap().into_iter().map(|h| h.path).collect();
+    assert_eq!(paths.len(), 2);
+    assert!(paths.contains(&"article/3/body".to_string()));
+    assert_eq!(db.search("index", "article", 1).unwrap().len(), 1);
+
+    db.disable_search();
+    assert!(db.search("index", "", 10).is_err());
+}
@@ -0,0 +1,37 @@
+use rust_db::{Database, SlowlogConfig, Value};
+
+#[test]
+fn keeps_newest_operations_over_threshold() {
+    let db = Database::in_memory();
+    db.set_slowlog_config(SlowlogConfig {
+        threshold_us: 0,
+        max_len: 3,
+    });
+
+    db.set("users/1", Value::String("a".to_string())).unwrap();
+    db.set("users/2", Value::String("b".to_string())).unwrap();
+    db.list_directory("users").unwrap();
+    assert!(db.get("missing").is_err());
+
+    // save_to_disk inside each set is recorded as "persist" before the set itself
+    let entries = db.slowlog(10);
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].operation, "get");
+    assert_eq!(entries[0].path, "missing");
+    assert_eq!(entries[0].result_size, 0);
+    assert_eq!(entries[0].error.as_deref(), Some("Key not found"));
+    assert_eq!(entries[1].operation, "list_directory");
+    assert_eq!(entries[1].result_size, 2);
+    assert!(entries[0].id > entries[1].id);
+    assert_eq!(db.slowlog(1).len(), 1);
+
+    db.slowlog_reset();
+    assert_eq!(db.slowlog_len(), 0);
+
+    db.set_slowlog_config(SlowlogConfig {
+        threshold_us: 60_000_000,
+        max_len: 3,
+    });
+    db.get("users/1").unwrap();
+    assert_eq!(db.slowlog_len(), 0);
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use std::sync::Arc;
+use std::thread;
+
+use rust_db::{Database, Value};
+
+fn text(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn snapshot_keeps_its_view_while_writers_continue() {
+    let dir = common::temp_dir("snapshot");
+    let db = Database::new(dir.join("db.json").to_str().unwrap());
+    db.set("user/1/name", Value::String("Alice".to_string())).unwrap();
+    db.set("user/2/name", Value::String("Bob".to_string())).unwrap();
+
+    let snapshot = db.snapshot();
+    let revision = snapshot.revision();
+    db.set("user/1/name", Value::String("Changed".to_string())).unwrap();
+    db.delete("user/2/name").unwrap();
+    db.set("user/3/name", Value::String("Carol".to_string())).unwrap();
+
+    assert_eq!(snapshot.revision(), revision);
+    assert_eq!(text(snapshot.get("user/1/name").unwrap()), "Alice");
+    assert_eq!(text(snapshot.get("user/2/name").unwrap()), "Bob");
+    assert!(snapshot.get("user/3/name").is_err());
+    assert_eq!(snapshot.find_by_path("user/*/name").unwrap().len(), 2);
+    assert_eq!(db.find_by_path("user/*/name").unwrap().len(), 2);
+    assert_eq!(text(db.get("user/1/name").unwrap()), "Changed");
+
+    let export = dir.join("snapshot.json");
+    snapshot.export_json(export.to_str().unwrap()).unwrap();
+    let exported = std::fs::read_to_string(&export).unwrap();
+    assert!(exported.contains("Bob") && !exported.contains("Carol"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn snapshot_is_a_consistent_cut_under_concurrent_writes() {
+    let dir = common::temp_dir("snapshot_concurrent");
+    let db = Arc::new(Database::new(dir.join("db.json").to_str().unwrap()));
+    for i in 0..100 {
+        db.set(&format!("counter/{}", i), Value::Integer(0)).unwrap();
+    }
+
+    // Each round bumps the counters in path order, so a consistent cut is some counters
+    // at round r + 1 followed by the rest at round r
+    let writer = {
+        let db = db.clone();
+        thread::spawn(move || {
+            for round in 1..=20 {
+                for i in 0..100 {
+                    db.set(&format!("counter/{}", i), Value::Integer(round)).unwrap();
+                }
+            }
+        })
+    };
+    for _ in 0..20 {
+        let snapshot = db.snapshot();
+        let values: Vec<i64> = (0..100)
+            .map(|i| match snapshot.get(&format!("counter/{}", i)).unwrap() {
+                Value::Integer(n) => n,
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert!(values.windows(2).all(|w| w[0] >= w[1]), "{:?}", values);
+        assert!(values[0] - values[99] <= 1, "{:?}", values);
+    }
+    writer.join().unwrap();
+}
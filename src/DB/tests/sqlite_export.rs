@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+use rust_db::{Database, MapTableSpec, SqliteExportConfig, Value};
+
+#[test]
+fn exports_entries_and_flattened_map_tables() {
+    let dir = std::env::temp_dir().join(format!("rust_db_sqlite_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let db = Database::new(dir.join("db.json").to_str().unwrap());
+    db.set("article/1/meta", Value::Map(HashMap::from([
+        ("status".to_string(), "published".to_string()),
+        ("author".to_string(), "alice".to_string()),
+    ]))).unwrap();
+    db.set("article/2/meta", Value::Map(HashMap::from([
+        ("status".to_string(), "draft".to_string()),
+        ("path".to_string(), "/drafts".to_string()),
+    ]))).unwrap();
+    db.set("article/1/views", Value::Integer(10)).unwrap();
+    db.set("article/2/views", Value::Integer(5)).unwrap();
+    db.set("user/1/name", Value::String("Alice".to_string())).unwrap();
+
+    let path = dir.join("export.sqlite");
+    let summary = db.export_sqlite(path.to_str().unwrap(), &SqliteExportConfig {
+        pattern: Some("article".to_string()),
+        map_tables: vec![MapTableSpec {
+            name: "article_meta".to_string(),
+            pattern: "article/*/meta".to_string(),
+        }],
+    })
+    .unwrap();
+    assert_eq!(summary.entries, 4);
+    assert_eq!(summary.map_fields, 4);
+    assert_eq!(summary.tables["article_meta"], 2);
+
+    let conn = Connection::open(&path).unwrap();
+    let total: i64 = conn
+        .query_row("SELECT SUM(value) FROM entries WHERE type = 'integer'", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(total, 15);
+
+    let components: String = conn
+        .query_row("SELECT components FROM entries WHERE path = 'article/1/views'", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(components, r#"["article","1","views"]"#);
+
+    let published: String = conn
+        .query_row("SELECT path FROM article_meta WHERE status = 'published'", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(published, "article/1/meta");
+
+    let draft_path: Option<String> = conn
+        .query_row("SELECT path_field FROM article_meta WHERE path = 'article/2/meta'", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(draft_path.as_deref(), Some("/drafts"));
+
+    let users: i64 = conn
+        .query_row("SELECT COUNT(*) FROM entries WHERE path LIKE 'user/%'", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(users, 0);
+}
+
+#[test]
+fn map_fields_differing_only_in_case_get_their_own_columns() {
+    let dir = std::env::temp_dir().join(format!("rust_db_sqlite_case_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let db = Database::in_memory();
+    db.set("item/1", Value::Map(HashMap::from([
+        ("Name".to_string(), "upper".to_string()),
+        ("name".to_string(), "lower".to_string()),
+        ("PATH".to_string(), "a".to_string()),
+        ("path_field".to_string(), "b".to_string()),
+        ("".to_string(), "empty".to_string()),
+    ]))).unwrap();
+
+    let path = dir.join("export.sqlite");
+    db.export_sqlite(path.to_str().unwrap(), &SqliteExportConfig {
+        pattern: None,
+        map_tables: vec![MapTableSpec {
+            name: "items".to_string(),
+            pattern: "item/*".to_string(),
+        }],
+    })
+    .unwrap();
+
+    let conn = Connection::open(&path).unwrap();
+    let row: (String, String, String, String, String) = conn
+        .query_row("SELECT Name, name_2, PATH_field, path_field_2, field FROM items", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        })
+        .unwrap();
+    assert_eq!(row, ("upper".into(), "lower".into(), "a".into(), "b".into(), "empty".into()));
+    drop(conn);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
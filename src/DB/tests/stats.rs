@@ -0,0 +1,58 @@
+mod common;
+
+use std::collections::HashMap;
+use std::fs;
+
+use rust_db::{Database, StorageConfig, StorageEngine, Value};
+
+fn temp_path(name: &str) -> String {
+    common::temp_dir(&format!("stats_{}", name)).join("db.json").to_string_lossy().into_owned()
+}
+
+#[test]
+fn detailed_stats_break_memory_down_by_type_and_prefix() {
+    let db = Database::in_memory();
+    db.set("users/1/name", Value::String("x".repeat(500))).unwrap();
+    db.set("users/2/age", Value::Integer(30)).unwrap();
+    let mut map = HashMap::new();
+    map.insert("color".to_string(), "red".to_string());
+    db.set("config/theme", Value::Map(map)).unwrap();
+    db.set("flat", Value::Bool(true)).unwrap();
+
+    let stats = db.get_detailed_stats();
+    assert_eq!(stats.memory_usage, db.memory_stats().used_memory);
+    assert_eq!(stats.memory_by_type.values().sum::<u64>(), stats.memory_usage);
+    assert!(stats.memory_by_type["string"] > stats.memory_by_type["integer"] + 499);
+
+    assert_eq!(stats.prefixes["users"].keys, 2);
+    assert_eq!(stats.prefixes["config"].keys, 1);
+    assert_eq!(stats.prefixes[""].keys, 1);
+    assert_eq!(stats.prefixes.values().map(|p| p.memory_usage).sum::<u64>(), stats.memory_usage);
+
+    assert!(stats.live_memory > 0 && stats.live_memory <= stats.memory_usage);
+    assert!(stats.allocated_memory >= stats.memory_usage);
+    assert!(stats.fragmentation_ratio >= 1.0);
+    assert_eq!(stats.disk.total_bytes, 0);
+}
+
+#[test]
+fn disk_usage_splits_log_into_snapshot_and_appended_records() {
+    let path = temp_path("log");
+    let config = StorageConfig {
+        engine: StorageEngine::Log,
+        ..Default::default()
+    };
+    let db = Database::open(&path, &config).unwrap();
+    db.set("a", Value::Integer(1)).unwrap();
+    db.compact().unwrap();
+    let compacted = db.disk_usage();
+    assert!(compacted.snapshot_bytes > 0);
+    assert_eq!(compacted.log_bytes, 0);
+
+    db.set("b", Value::Integer(2)).unwrap();
+    let usage = db.disk_usage();
+    assert_eq!(usage.snapshot_bytes, compacted.snapshot_bytes);
+    assert!(usage.log_bytes > 0);
+    assert_eq!(usage.total_bytes, fs::metadata(&path).unwrap().len());
+    assert_eq!(db.get_stats().disk_usage, usage.total_bytes);
+}
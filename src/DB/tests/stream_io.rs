@@ -0,0 +1,92 @@
+use std::io::Cursor;
+
+use rust_db::{Database, ImportMode, ImportOptions, StreamFormat, Value};
+
+fn temp_db(name: &str) -> Database {
+    let dir = std::env::temp_dir().join(format!("rust_db_stream_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    Database::new(dir.join("db.json").to_str().unwrap())
+}
+
+#[test]
+fn ndjson_round_trip_with_pattern() {
+    let db = temp_db("ndjson");
+    db.set("user/1/name", Value::String("Alice".to_string())).unwrap();
+    db.set("user/1/tags", Value::Array(vec!["a".to_string(), "b".to_string()])).unwrap();
+    db.set("user/2/age", Value::Integer(42)).unwrap();
+    db.set("config/theme", Value::String("dark".to_string())).unwrap();
+    db.set_expiry("user/2/age", 3600).unwrap();
+
+    let mut out = Vec::new();
+    let summary = db.export_stream(&mut out, StreamFormat::Ndjson, Some("user")).unwrap();
+    assert_eq!(summary.exported, 3);
+    assert_eq!(String::from_utf8(out.clone()).unwrap().lines().count(), 3);
+
+    let restored = temp_db("ndjson_restore");
+    let report = restored.import_stream(Cursor::new(out), StreamFormat::Ndjson, &ImportOptions::default()).unwrap();
+    assert_eq!(report.imported, 3);
+    assert_eq!(restored.get("user/2/age").unwrap(), Value::Integer(42));
+    assert!(restored.ttl("user/2/age").unwrap().unwrap() > 0);
+    assert_eq!(
+        restored.get("user/1/tags").unwrap(),
+        Value::Array(vec!["a".to_string(), "b".to_string()])
+    );
+    assert!(restored.get("config/theme").is_err());
+}
+
+#[test]
+fn csv_quotes_multiline_values_and_skips_nested_types() {
+    let db = temp_db("csv");
+    db.set("note/1", Value::String("line one\nsays \"hi\", twice".to_string())).unwrap();
+    db.set("note/2", Value::Float(1.5)).unwrap();
+    db.set("note/3", Value::Bool(true)).unwrap();
+    db.set("note/4", Value::Array(vec![])).unwrap();
+
+    let mut out = Vec::new();
+    let summary = db.export_stream(&mut out, StreamFormat::Csv, None).unwrap();
+    assert_eq!(summary.exported, 3);
+    assert_eq!(summary.skipped, 1);
+
+    let restored = temp_db("csv_restore");
+    restored.set("note/2", Value::Float(9.0)).unwrap();
+    let options = ImportOptions {
+        mode: ImportMode::MergeOverwrite,
+        ..Default::default()
+    };
+    let report = restored.import_stream(Cursor::new(out), StreamFormat::Csv, &options).unwrap();
+    assert_eq!(report.imported, 3);
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(
+        restored.get("note/1").unwrap(),
+        Value::String("line one\nsays \"hi\", twice".to_string())
+    );
+    assert_eq!(restored.get("note/2").unwrap(), Value::Float(1.5));
+    assert_eq!(restored.get("note/3").unwrap(), Value::Bool(true));
+}
+
+#[test]
+fn corrupt_line_mid_stream_changes_nothing() {
+    let db = temp_db("corrupt");
+    db.set("user/1/name", Value::String("Alice".to_string())).unwrap();
+    db.set("user/9/name", Value::String("Zed".to_string())).unwrap();
+
+    let mut out = Vec::new();
+    db.export_stream(&mut out, StreamFormat::Ndjson, None).unwrap();
+    let mut lines: Vec<String> = String::from_utf8(out).unwrap().lines().map(|l| l.to_string()).collect();
+    lines.retain(|line| !line.contains("user/9"));
+    lines[0] = lines[0].replace("Alice", "Alicia");
+    lines.push("{\"path\": \"user/2/name\"".to_string());
+    lines.push(lines[0].replace("user/1", "user/3"));
+    let input = lines.join("\n");
+
+    // Replace would drop user/9 and overwrite user/1 if the head of the stream were applied
+    let options = ImportOptions {
+        mode: ImportMode::Replace,
+        ..Default::default()
+    };
+    assert!(db.import_stream(Cursor::new(input), StreamFormat::Ndjson, &options).is_err());
+    assert_eq!(db.get("user/1/name").unwrap(), Value::String("Alice".to_string()));
+    assert_eq!(db.get("user/9/name").unwrap(), Value::String("Zed".to_string()));
+    assert!(db.get("user/3/name").is_err());
+}
@@ -0,0 +1,99 @@
+use rust_db::{Volume, VolumeConfig, VolumeKind};
+
+#[test]
+fn directory_volume_reports_usage() {
+    let dir = std::env::temp_dir().join(format!("rust_db_volume_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mount_point = dir.join("data");
+    assert!(Volume::open(mount_point.to_str().unwrap()).is_err());
+
+    std::fs::create_dir_all(&mount_point).unwrap();
+    let volume = Volume::open(mount_point.to_str().unwrap()).unwrap();
+    assert_eq!(volume.kind(), VolumeKind::Directory);
+    assert!(volume.image().is_none());
+
+    let usage = volume.usage().unwrap();
+    assert!(usage.mounted);
+    assert!(usage.total_bytes >= usage.free_bytes);
+    assert!(usage.total_bytes > 0);
+    assert_eq!(usage.image_bytes, None);
+
+    assert!(volume.resize(64).is_err());
+    volume.unmount().unwrap();
+    assert!(mount_point.is_dir());
+}
+
+#[test]
+fn config_builds_mount_options() {
+    let config: VolumeConfig = serde_json::from_str(r#"{"compression": "zstd:3", "nodatacow": ["db.json"]}"#).unwrap();
+    assert_eq!(config.size_mb, 256);
+    assert!(config.noatime);
+    assert_eq!(config.btrfs_bin, "btrfs");
+    assert_eq!(config.mount_options().unwrap().as_deref(), Some("compress=zstd:3"));
+
+    let forced = VolumeConfig {
+        compression: Some("lzo".to_string()),
+        force_compression: true,
+        extra_options: vec!["space_cache=v2".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(forced.mount_options().unwrap().as_deref(), Some("compress-force=lzo,space_cache=v2"));
+    assert_eq!(VolumeConfig::default().mount_options().unwrap(), None);
+
+    for bad in ["gzip", "zstd:x", "lzo:1"] {
+        let config = VolumeConfig {
+            compression: Some(bad.to_string()),
+            ..Default::default()
+        };
+        assert!(config.mount_options().is_err());
+        assert!(Volume::create_with("/nonexistent/rust_db_volume", &config).is_err());
+    }
+}
+
+#[test]
+fn foreign_mount_is_not_a_volume() {
+    // /proc is mounted but not from a /proc.img loop image, so it must never be unmounted
+    assert!(Volume::open("/proc").is_err());
+    assert!(Volume::create("/proc", 64).is_err());
+    assert!(std::path::Path::new("/proc/self/mountinfo").exists());
+}
+
+#[test]
+fn oversized_volume_is_rejected() {
+    let dir = std::env::temp_dir().join(format!("rust_db_volume_huge_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mount_point = dir.join("huge");
+    // Without root the volume falls back to a directory and has no size to overflow
+    if let Err(e) = Volume::create(mount_point.to_str().unwrap(), u64::MAX) {
+        assert!(e.to_string().contains("too large"), "{}", e);
+        assert!(!dir.join("huge.img").exists());
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn loop_volume_mounts_and_remounts() {
+    // Needs root, loop devices and btrfs-progs; elsewhere create() falls back to a directory
+    let tools = std::process::Command::new("mkfs.btrfs").arg("--version").output().is_ok();
+    if !tools || !std::path::Path::new("/dev/loop-control").exists() || !nix::unistd::geteuid().is_root() {
+        return;
+    }
+    let dir = std::env::temp_dir().join(format!("rust_db_volume_loop_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mount_point = dir.join("data");
+    let path = mount_point.to_str().unwrap();
+
+    let volume = Volume::create(path, 128).unwrap();
+    assert_eq!(volume.kind(), VolumeKind::Btrfs);
+    assert!(volume.is_mounted());
+    std::fs::write(mount_point.join("db.json"), b"{}").unwrap();
+    volume.unmount().unwrap();
+    assert!(!volume.is_mounted());
+
+    // The image survives the unmount and mounts again with its data
+    let volume = Volume::create(path, 128).unwrap();
+    assert!(volume.is_mounted());
+    assert_eq!(std::fs::read(mount_point.join("db.json")).unwrap(), b"{}");
+    volume.unmount().unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+}